@@ -0,0 +1,111 @@
+use crate::error::ShellError;
+use crate::parser::quote_word;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+// 别名表：普通别名只在命令位置展开，全局别名在命令行任意位置展开，
+// 后缀别名按命令词的扩展名选择打开文件的程序
+#[derive(Debug, Default)]
+pub struct AliasTable {
+    normal: BTreeMap<String, String>,
+    global: BTreeMap<String, String>,
+    suffix: BTreeMap<String, String>,
+}
+
+impl AliasTable {
+    // 查找普通别名
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.normal.get(name).map(|v| v.as_str())
+    }
+
+    // 按名字顺序列出普通别名及其值
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.normal.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    // 查找全局别名
+    pub fn get_global(&self, name: &str) -> Option<&str> {
+        self.global.get(name).map(|v| v.as_str())
+    }
+
+    // 按文件扩展名查找后缀别名
+    pub fn get_suffix(&self, file: &str) -> Option<&str> {
+        let (_, ext) = file.rsplit_once('.')?;
+        if ext.is_empty() || ext.contains('/') {
+            return None;
+        }
+        self.suffix.get(ext).map(|v| v.as_str())
+    }
+}
+
+// 内建命令 alias：alias [-g|-s] [name[=value] ...]
+pub fn run_alias(out: &mut impl Write, table: &mut AliasTable, args: &[String]) -> Result<(), ShellError> {
+    let (flag, args) = match args.first().map(|a| a.as_str()) {
+        Some(flag @ ("-g" | "-s")) => (flag, &args[1..]),
+        _ => ("", args),
+    };
+
+    let map = match flag {
+        "-g" => &mut table.global,
+        "-s" => &mut table.suffix,
+        _ => &mut table.normal,
+    };
+
+    // 没有参数时列出所有别名
+    if args.is_empty() {
+        for (name, value) in map.iter() {
+            writeln!(out, "{}", format_alias(name, value, flag))?;
+        }
+        return Ok(());
+    }
+
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) => {
+                if name.is_empty() {
+                    return Err(ShellError::CommandError(format!("alias: 无效的别名名称 '{}'", arg)));
+                }
+                map.insert(name.to_string(), value.to_string());
+            }
+            None => match map.get(arg) {
+                Some(value) => writeln!(out, "{}", format_alias(arg, value, flag))?,
+                None => {
+                    return Err(ShellError::CommandError(format!("alias: 未找到别名 '{}'", arg)));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+// 内建命令 unalias：unalias [-a] name ...
+pub fn run_unalias(table: &mut AliasTable, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("unalias: 缺少参数".to_string()));
+    }
+
+    if args[0] == "-a" {
+        table.normal.clear();
+        table.global.clear();
+        table.suffix.clear();
+        return Ok(());
+    }
+
+    for name in args {
+        let removed = table.normal.remove(name).is_some()
+            | table.global.remove(name).is_some()
+            | table.suffix.remove(name).is_some();
+        if !removed {
+            return Err(ShellError::CommandError(format!("unalias: 未找到别名 '{}'", name)));
+        }
+    }
+
+    Ok(())
+}
+
+// 以可重新输入的形式显示别名
+fn format_alias(name: &str, value: &str, flag: &str) -> String {
+    let flag = if flag.is_empty() { String::new() } else { format!("{} ", flag) };
+    format!("alias {}{}={}", flag, name, quote_word(value))
+}
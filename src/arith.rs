@@ -0,0 +1,446 @@
+use crate::error::ShellError;
+use crate::vars::Variables;
+
+// 对整数算术表达式求值，用于 $((...))，也可供 let、((...)) 等使用
+// 支持 C 语言风格的运算符：?: || && | ^ & == != < <= > >= << >> + - * / % **，
+// 一元 - + ! ~，括号，十六进制（0x）和八进制（0 开头）数字，
+// 变量名（可以带 $）按其值参与运算，未定义或为空时为 0，赋值运算 = += -= *= /= %= 会修改变量，
+// 自增自减 ++x --x x++ x-- 也会修改变量，前缀形式的值是修改后的值，后缀形式是修改前的值
+pub fn evaluate(input: &str, vars: &mut Variables) -> Result<i64, ShellError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        vars,
+        skip: false,
+    };
+    parser.skip_whitespace();
+    if parser.peek().is_none() {
+        return Ok(0);
+    }
+    let value = parser.assignment()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(error(format!("无法识别的字符 '{}'", c)));
+    }
+    Ok(value)
+}
+
+fn error(message: String) -> ShellError {
+    ShellError::CommandError(format!("算术表达式: {}", message))
+}
+
+// 二元运算符，按优先级从低到高分组；同一组内较长的符号在前，避免 < 抢先匹配 <<、<=
+const LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+// 递归下降求值；skip 为 true 时只检查语法，不修改变量也不报除零错误，
+// 用于 && || ?: 中不需要求值的一侧
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    vars: &'a mut Variables,
+    skip: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // 跳过空白后如果接下来是 op 则消耗它
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_whitespace();
+        let matches = self.at(op);
+        if matches {
+            self.pos += op.chars().count();
+        }
+        matches
+    }
+
+    // 当前位置（不跳过空白）是否是 op
+    fn at(&self, op: &str) -> bool {
+        let len = op.chars().count();
+        self.chars.len() >= self.pos + len && self.chars[self.pos..self.pos + len].iter().copied().eq(op.chars())
+    }
+
+    // 赋值（右结合）：变量名 = 表达式、变量名 += 表达式 等，否则为条件表达式
+    fn assignment(&mut self) -> Result<i64, ShellError> {
+        let start = self.pos;
+        self.skip_whitespace();
+        if self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') {
+            let name = self.identifier();
+            self.skip_whitespace();
+            let op = ["=", "+=", "-=", "*=", "/=", "%="]
+                .into_iter()
+                .find(|op| self.chars[self.pos..].starts_with(&op.chars().collect::<Vec<_>>()));
+            // == 是比较，不是赋值
+            if let Some(op) = op
+                && !(op == "=" && self.chars.get(self.pos + 1) == Some(&'='))
+            {
+                self.pos += op.len();
+                let right = self.assignment()?;
+                let value = match op {
+                    "=" => right,
+                    _ => self.apply(&op[..1], self.variable(&name)?, right)?,
+                };
+                if !self.skip {
+                    self.vars.set(&name, &value.to_string());
+                }
+                return Ok(value);
+            }
+        }
+        self.pos = start;
+        self.conditional()
+    }
+
+    // 条件表达式 a ? b : c，只对选中的一侧求值
+    fn conditional(&mut self) -> Result<i64, ShellError> {
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let skip = self.skip;
+        self.skip = skip || condition == 0;
+        let then = self.assignment()?;
+        if !self.eat(":") {
+            self.skip = skip;
+            return Err(error("'?' 之后缺少 ':'".to_string()));
+        }
+        self.skip = skip || condition != 0;
+        let otherwise = self.assignment()?;
+        self.skip = skip;
+        Ok(if condition != 0 { then } else { otherwise })
+    }
+
+    // 第 level 层的左结合二元运算
+    fn binary(&mut self, level: usize) -> Result<i64, ShellError> {
+        let Some(ops) = LEVELS.get(level) else {
+            return self.power();
+        };
+        let mut value = self.binary(level + 1)?;
+        loop {
+            let Some(op) = ops.iter().copied().find(|op| self.eat_operator(op)) else {
+                return Ok(value);
+            };
+            // && 和 || 短路：右侧只检查语法
+            let skip = self.skip;
+            if (op == "&&" && value == 0) || (op == "||" && value != 0) {
+                self.skip = true;
+            }
+            let right = self.binary(level + 1);
+            self.skip = skip;
+            value = self.apply(op, value, right?)?;
+        }
+    }
+
+    // 读取二元运算符，但不把 && 的前半当作 &、把 *= 当作 * 等
+    fn eat_operator(&mut self, op: &str) -> bool {
+        let mark = self.pos;
+        if !self.eat(op) {
+            return false;
+        }
+        let next = self.peek();
+        let longer = match op {
+            "&" => next == Some('&'),
+            "|" => next == Some('|'),
+            "*" => matches!(next, Some('*') | Some('=')),
+            "<" => next == Some('<'),
+            ">" => next == Some('>'),
+            "==" | "!=" | "<=" | ">=" | "&&" | "||" | "<<" | ">>" => false,
+            _ => next == Some('='),
+        };
+        if longer {
+            self.pos = mark;
+        }
+        !longer
+    }
+
+    fn apply(&self, op: &str, left: i64, right: i64) -> Result<i64, ShellError> {
+        let value = match op {
+            "||" => (left != 0 || right != 0) as i64,
+            "&&" => (left != 0 && right != 0) as i64,
+            "|" => left | right,
+            "^" => left ^ right,
+            "&" => left & right,
+            "==" => (left == right) as i64,
+            "!=" => (left != right) as i64,
+            "<" => (left < right) as i64,
+            "<=" => (left <= right) as i64,
+            ">" => (left > right) as i64,
+            ">=" => (left >= right) as i64,
+            "<<" => left.wrapping_shl(right as u32),
+            ">>" => left.wrapping_shr(right as u32),
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            "*" => left.wrapping_mul(right),
+            "/" | "%" if right == 0 => {
+                if self.skip {
+                    return Ok(0);
+                }
+                return Err(error("除数为零".to_string()));
+            }
+            "/" => left.wrapping_div(right),
+            "%" => left.wrapping_rem(right),
+            _ => unreachable!("未知的运算符 {}", op),
+        };
+        Ok(value)
+    }
+
+    // 乘方（右结合），优先级高于乘除，低于一元运算：-2 ** 2 为 4
+    fn power(&mut self) -> Result<i64, ShellError> {
+        let base = self.unary()?;
+        if !self.eat("**") {
+            return Ok(base);
+        }
+        let exponent = self.power()?;
+        if exponent < 0 {
+            if self.skip {
+                return Ok(0);
+            }
+            return Err(error("指数不能为负".to_string()));
+        }
+        Ok(base.wrapping_pow(exponent.min(u32::MAX as i64) as u32))
+    }
+
+    fn unary(&mut self) -> Result<i64, ShellError> {
+        // ++x 和 --x；后面不是变量名时是两个一元运算符，例如 --5 为 5
+        let mark = self.pos;
+        for (op, delta) in [("++", 1), ("--", -1)] {
+            if self.eat(op) {
+                self.skip_whitespace();
+                if self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') {
+                    let name = self.identifier();
+                    return self.increment(&name, delta, true);
+                }
+                self.pos = mark;
+            }
+        }
+
+        if self.eat_operator("-") {
+            Ok(self.unary()?.wrapping_neg())
+        } else if self.eat_operator("+") {
+            self.unary()
+        } else if self.eat("!") {
+            Ok((self.unary()? == 0) as i64)
+        } else if self.eat("~") {
+            Ok(!self.unary()?)
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, ShellError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.assignment()?;
+                if !self.eat(")") {
+                    return Err(error("缺少 ')'".to_string()));
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() => self.number(),
+            Some('$') => {
+                self.pos += 1;
+                let braced = self.eat("{");
+                let name = self.identifier();
+                if name.is_empty() || (braced && !self.eat("}")) {
+                    return Err(error("错误的变量引用".to_string()));
+                }
+                self.variable(&name)
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.identifier();
+                // 紧跟在变量名之后的 ++ 和 -- 是后缀自增自减
+                for (op, delta) in [("++", 1), ("--", -1)] {
+                    if self.at(op) {
+                        self.pos += op.len();
+                        return self.increment(&name, delta, false);
+                    }
+                }
+                self.variable(&name)
+            }
+            Some(c) => Err(error(format!("无法识别的字符 '{}'", c))),
+            None => Err(error("表达式不完整".to_string())),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, ShellError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        parse_integer(&text).ok_or_else(|| error(format!("无效的数字 '{}'", text)))
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // 把变量加上 delta，prefix 时返回新值，否则返回原来的值
+    fn increment(&mut self, name: &str, delta: i64, prefix: bool) -> Result<i64, ShellError> {
+        let old = self.variable(name)?;
+        let new = old.wrapping_add(delta);
+        if !self.skip {
+            self.vars.set(name, &new.to_string());
+        }
+        Ok(if prefix { new } else { old })
+    }
+
+    // 变量的整数值，未定义或为空时为 0
+    fn variable(&self, name: &str) -> Result<i64, ShellError> {
+        let value = self.vars.get(name).unwrap_or("").trim();
+        if value.is_empty() {
+            return Ok(0);
+        }
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        match parse_integer(digits) {
+            Some(n) if negative => Ok(n.wrapping_neg()),
+            Some(n) => Ok(n),
+            None => Err(error(format!("变量 {} 的值 '{}' 不是整数", name, value))),
+        }
+    }
+}
+
+// 解析十进制、0x 开头的十六进制或 0 开头的八进制整数
+fn parse_integer(text: &str) -> Option<i64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> Result<i64, ShellError> {
+        evaluate(input, &mut Variables::default())
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(eval("10 - 4 - 3").unwrap(), 3);
+        assert_eq!(eval("2 ** 3 ** 2").unwrap(), 512);
+        assert_eq!(eval("-2 ** 2").unwrap(), 4);
+        assert_eq!(eval("1 << 2 + 1").unwrap(), 8);
+        assert_eq!(eval("5 & 3 | 8 ^ 1").unwrap(), 9);
+        assert_eq!(eval("1 < 2 == 1").unwrap(), 1);
+        assert_eq!(eval("0 ? 1 : 2 ? 3 : 4").unwrap(), 3);
+        assert_eq!(eval("  ").unwrap(), 0);
+    }
+
+    #[test]
+    fn number_bases() {
+        assert_eq!(eval("0x1f + 010").unwrap(), 39);
+        assert_eq!(eval("0X10").unwrap(), 16);
+        assert!(eval("09").is_err());
+        assert!(eval("12abc").is_err());
+    }
+
+    #[test]
+    fn overflow_wraps_instead_of_panicking() {
+        let min = "(-9223372036854775807 - 1)";
+        assert_eq!(eval(&format!("{} / -1", min)).unwrap(), i64::MIN);
+        assert_eq!(eval(&format!("{} % -1", min)).unwrap(), 0);
+        assert_eq!(eval(&format!("-{}", min)).unwrap(), i64::MIN);
+        assert_eq!(eval("9223372036854775807 + 1").unwrap(), i64::MIN);
+        assert_eq!(eval("2 ** 64").unwrap(), 0);
+        assert_eq!(eval("1 << 64").unwrap(), 1);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 % 0").is_err());
+        assert!(eval("2 ** -1").is_err());
+        // 短路时不需要求值的一侧不报错
+        assert_eq!(eval("0 && 1 / 0").unwrap(), 0);
+        assert_eq!(eval("1 || 1 / 0").unwrap(), 1);
+        assert_eq!(eval("1 ? 2 : 1 / 0").unwrap(), 2);
+    }
+
+    #[test]
+    fn assignment() {
+        let mut vars = Variables::default();
+        assert_eq!(evaluate("x = 2 + 3", &mut vars).unwrap(), 5);
+        assert_eq!(evaluate("x *= 2", &mut vars).unwrap(), 10);
+        assert_eq!(evaluate("y = x -= 4", &mut vars).unwrap(), 6);
+        assert_eq!(vars.get("x"), Some("6"));
+        assert_eq!(vars.get("y"), Some("6"));
+        assert_eq!(evaluate("x == 6", &mut vars).unwrap(), 1);
+        assert_eq!(evaluate("$x + ${y}", &mut vars).unwrap(), 12);
+        // 跳过的一侧不赋值
+        assert_eq!(evaluate("0 && (x = 1)", &mut vars).unwrap(), 0);
+        assert_eq!(vars.get("x"), Some("6"));
+    }
+
+    #[test]
+    fn increment_and_decrement() {
+        let mut vars = Variables::default();
+        vars.set("x", "5");
+        assert_eq!(evaluate("x++", &mut vars).unwrap(), 5);
+        assert_eq!(vars.get("x"), Some("6"));
+        assert_eq!(evaluate("++x", &mut vars).unwrap(), 7);
+        assert_eq!(evaluate("x--", &mut vars).unwrap(), 7);
+        assert_eq!(evaluate("--x", &mut vars).unwrap(), 5);
+        assert_eq!(evaluate("x++ + 1", &mut vars).unwrap(), 6);
+        assert_eq!(vars.get("x"), Some("6"));
+        assert_eq!(evaluate("1 || x++", &mut vars).unwrap(), 1);
+        assert_eq!(vars.get("x"), Some("6"));
+        assert_eq!(evaluate("counter++", &mut vars).unwrap(), 0);
+        assert_eq!(vars.get("counter"), Some("1"));
+        // 后面不是变量名时是两个一元运算符
+        assert_eq!(evaluate("--5", &mut vars).unwrap(), 5);
+        assert_eq!(evaluate("x - -1", &mut vars).unwrap(), 7);
+    }
+
+    #[test]
+    fn variables() {
+        let mut vars = Variables::default();
+        vars.set("n", " -12 ");
+        vars.set("word", "abc");
+        assert_eq!(evaluate("n * 2", &mut vars).unwrap(), -24);
+        assert_eq!(evaluate("unset + 1", &mut vars).unwrap(), 1);
+        assert!(evaluate("word + 1", &mut vars).is_err());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(eval("1 +").is_err());
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("1 ? 2").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("$").is_err());
+    }
+}
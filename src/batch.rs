@@ -0,0 +1,53 @@
+// 无终端的批处理模式：从输入逐行读取命令执行，每条命令输出一条JSON记录
+//   {"command":"...","argv":[["ls","-l"],["wc"]],"status":0,"duration_ms":1.234,"stdout":"...","stderr":"..."}
+// argv 按命令分组，是实际执行的每个命令展开变量等之后的参数，包括管道中、用 ; 分隔、
+// 用 && / || 连接和 { ...; } 中的命令；没有执行的命令（例如 && 之前的命令失败）和子Shell中的命令不在其中
+// 命令的标准输入是 /dev/null，与 each 一样，读取标准输入的命令不会读走后面的命令
+use crate::error::ShellError;
+use crate::json::quote;
+use crate::redirect::FdGuard;
+use crate::shell::Shell;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+// 执行输入中的全部命令，返回最后一条命令的状态码
+pub fn run_json(shell: &mut Shell, input: impl BufRead) -> Result<i32, ShellError> {
+    let mut stdout = io::stdout();
+
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        shell.executed_argv = Some(Vec::new());
+        let start = Instant::now();
+        let guard = FdGuard::apply(&[(libc::STDIN_FILENO, Some(File::open("/dev/null")?))], &shell.stdio)?;
+        let output = shell.run_str(line);
+        drop(guard);
+        let duration = start.elapsed();
+        let executed = shell.executed_argv.take().unwrap_or_default();
+        let output = output?;
+
+        let argv: Vec<String> = executed
+            .iter()
+            .map(|words| format!("[{}]", words.iter().map(|w| quote(w)).collect::<Vec<_>>().join(",")))
+            .collect();
+
+        writeln!(
+            stdout,
+            "{{\"command\":{},\"argv\":[{}],\"status\":{},\"duration_ms\":{:.3},\"stdout\":{},\"stderr\":{}}}",
+            quote(line),
+            argv.join(","),
+            output.status,
+            duration.as_secs_f64() * 1000.0,
+            quote(&output.stdout),
+            quote(&output.stderr)
+        )?;
+        stdout.flush()?;
+    }
+
+    Ok(shell.last_status)
+}
@@ -0,0 +1,91 @@
+use crate::error::ShellError;
+use crate::vars::Variables;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// 书签文件 ~/.rsh_bookmarks，每行一个书签：名字、制表符、目录
+fn bookmarks_path(vars: &Variables) -> Option<PathBuf> {
+    vars.get("HOME").map(|home| PathBuf::from(home).join(".rsh_bookmarks"))
+}
+
+// 读取全部书签，按文件中的顺序返回 (名字, 目录)；文件不存在时为空
+pub fn load_bookmarks(vars: &Variables) -> Result<Vec<(String, String)>, ShellError> {
+    let Some(path) = bookmarks_path(vars) else {
+        return Ok(Vec::new());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ShellError::Io(e)),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, dir)| (name.to_string(), dir.to_string()))
+        .collect())
+}
+
+fn save_bookmarks(vars: &Variables, bookmarks: &[(String, String)]) -> Result<(), ShellError> {
+    let path = bookmarks_path(vars)
+        .ok_or_else(|| ShellError::CommandError("bookmark: 无法确定HOME目录".to_string()))?;
+    let text: String = bookmarks
+        .iter()
+        .map(|(name, dir)| format!("{}\t{}\n", name, dir))
+        .collect();
+    fs::write(path, text)?;
+    Ok(())
+}
+
+// 内建命令 bookmark：bookmark [名字 [目录]] 或 bookmark -d 名字...
+// 不带参数时列出书签；给出名字时把目录（默认为当前目录）记为书签，同名的书签被替换；
+// -d 删除书签。书签出现在 Ctrl-P 命令面板中，选中后进入该目录
+pub fn run_bookmark(out: &mut impl Write, vars: &Variables, args: &[String]) -> Result<(), ShellError> {
+    let mut bookmarks = load_bookmarks(vars)?;
+
+    match args {
+        [] => {
+            let width = bookmarks.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+            for (name, dir) in &bookmarks {
+                writeln!(out, "{:<width$}  {}", name, dir, width = width)?;
+            }
+            Ok(())
+        }
+        [flag, names @ ..] if flag == "-d" => {
+            if names.is_empty() {
+                return Err(ShellError::CommandError("用法: bookmark -d 名字...".to_string()));
+            }
+            for name in names {
+                let before = bookmarks.len();
+                bookmarks.retain(|(existing, _)| existing != name);
+                if bookmarks.len() == before {
+                    return Err(ShellError::CommandError(format!("bookmark: 没有书签 '{}'", name)));
+                }
+            }
+            save_bookmarks(vars, &bookmarks)
+        }
+        [name] | [name, _] => {
+            if name.is_empty() || name.starts_with('-') || name.contains(['\t', '\n']) {
+                return Err(ShellError::CommandError(format!("bookmark: 无效的书签名 '{}'", name)));
+            }
+            let dir = match args.get(1) {
+                Some(dir) => fs::canonicalize(dir)
+                    .map_err(|e| ShellError::CommandError(format!("bookmark: {}: {}", dir, e)))?,
+                None => env::current_dir()?,
+            };
+            if !dir.is_dir() {
+                return Err(ShellError::CommandError(format!("bookmark: '{}' 不是目录", dir.display())));
+            }
+            let dir = dir.to_string_lossy().to_string();
+            match bookmarks.iter_mut().find(|(existing, _)| existing == name) {
+                Some(bookmark) => bookmark.1 = dir,
+                None => bookmarks.push((name.clone(), dir)),
+            }
+            save_bookmarks(vars, &bookmarks)
+        }
+        _ => Err(ShellError::CommandError(
+            "用法: bookmark [名字 [目录]] 或 bookmark -d 名字...".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,223 @@
+use crate::parser::{Segment, Word};
+
+// 序列最多展开为这么多个词，更长的序列（例如 {1..9223372036854775807}）按字面保留
+const MAX_SEQUENCE: u64 = 1_000_000;
+
+// 花括号展开中词的一个单位：未加引号的一个字符，或者引号、变量引用等整段
+// 只有未加引号的 { , } 起作用，引号和变量中的按字面处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Char(char),
+    Atom(Segment),
+}
+
+// 花括号展开，在其他展开之前进行，一个词可能展开为多个词：
+//   a{b,c}d   展开为 abd acd，可以嵌套，例如 {a,b{1,2}}
+//   {1..5}    展开为 1 2 3 4 5，支持倒序、步长 {1..10..3}、补零 {01..10} 和字母 {a..e}
+// 没有逗号也不是序列的 {a}、{} 按字面保留
+pub fn expand_braces(word: &Word) -> Vec<Word> {
+    let mut items = Vec::new();
+    for segment in &word.segments {
+        match segment {
+            Segment::Plain(s) => items.extend(s.chars().map(Item::Char)),
+            segment => items.push(Item::Atom(segment.clone())),
+        }
+    }
+
+    let mut words = Vec::new();
+    expand_items(items, &mut words);
+    words
+}
+
+fn expand_items(items: Vec<Item>, words: &mut Vec<Word>) {
+    let Some((open, close, alternatives)) = find_brace(&items) else {
+        words.push(assemble(items));
+        return;
+    };
+    for alternative in alternatives {
+        let mut expanded = items[..open].to_vec();
+        expanded.extend(alternative);
+        expanded.extend_from_slice(&items[close + 1..]);
+        // 其余部分（以及选项内部）可能还有花括号
+        expand_items(expanded, words);
+    }
+}
+
+// 找到第一个可以展开的花括号，返回开、闭括号的位置和各个选项
+fn find_brace(items: &[Item]) -> Option<(usize, usize, Vec<Vec<Item>>)> {
+    for open in 0..items.len() {
+        if items[open] != Item::Char('{') {
+            continue;
+        }
+
+        let mut depth = 0;
+        let mut commas = Vec::new();
+        let mut close = None;
+        for (i, item) in items.iter().enumerate().skip(open + 1) {
+            match item {
+                Item::Char('{') => depth += 1,
+                Item::Char('}') if depth == 0 => {
+                    close = Some(i);
+                    break;
+                }
+                Item::Char('}') => depth -= 1,
+                Item::Char(',') if depth == 0 => commas.push(i),
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            continue;
+        };
+
+        if !commas.is_empty() {
+            let mut bounds = vec![open];
+            bounds.extend(commas);
+            bounds.push(close);
+            let alternatives = bounds.windows(2).map(|pair| items[pair[0] + 1..pair[1]].to_vec()).collect();
+            return Some((open, close, alternatives));
+        }
+        if let Some(sequence) = sequence(&items[open + 1..close]) {
+            let alternatives = sequence.into_iter().map(|s| s.chars().map(Item::Char).collect()).collect();
+            return Some((open, close, alternatives));
+        }
+    }
+    None
+}
+
+// 解析序列 x..y 或 x..y..步长，x 和 y 同为整数或同为单个字母
+fn sequence(items: &[Item]) -> Option<Vec<String>> {
+    let text = items
+        .iter()
+        .map(|item| match item {
+            Item::Char(c) => Some(*c),
+            Item::Atom(_) => None,
+        })
+        .collect::<Option<String>>()?;
+    let parts: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match parts.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, step.parse::<i64>().ok()?.unsigned_abs().max(1)),
+        _ => return None,
+    };
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        // 任一端以 0 开头时按较长的一端补零
+        let padded = [start, end]
+            .iter()
+            .any(|s| s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0'));
+        let width = if padded { start.len().max(end.len()) } else { 0 };
+        let values = range(first, last, step)?;
+        return Some(values.into_iter().map(|n| format!("{:0width$}", n, width = width)).collect());
+    }
+
+    let (mut first, mut last) = (start.chars(), end.chars());
+    match (first.next(), first.next(), last.next(), last.next()) {
+        (Some(a), None, Some(b), None) if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+            let values = range(a as i64, b as i64, step)?;
+            Some(values.into_iter().map(|n| (n as u8 as char).to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+// 从 first 到 last（包含）按步长递增或递减，超过 MAX_SEQUENCE 个时为 None
+fn range(first: i64, last: i64, step: u64) -> Option<Vec<i64>> {
+    let steps = first.abs_diff(last) / step;
+    if steps >= MAX_SEQUENCE {
+        return None;
+    }
+    // 用 i128 计算，步长接近 i64 的范围时也不会溢出；结果都在 first 和 last 之间
+    let (start, step) = (first as i128, step as i128);
+    let values = (0..=steps as i128)
+        .map(|i| if first <= last { start + i * step } else { start - i * step } as i64)
+        .collect();
+    Some(values)
+}
+
+// 把单位重新组成词，相邻的字符合并为一个未加引号段
+fn assemble(items: Vec<Item>) -> Word {
+    let mut word = Word::default();
+    for item in items {
+        match item {
+            Item::Char(c) => match word.segments.last_mut() {
+                Some(Segment::Plain(s)) => s.push(c),
+                _ => word.segments.push(Segment::Plain(c.to_string())),
+            },
+            Item::Atom(segment) => word.segments.push(segment),
+        }
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> Word {
+        Word {
+            segments: vec![Segment::Plain(text.to_string())],
+        }
+    }
+
+    fn expand(text: &str) -> Vec<String> {
+        expand_braces(&plain(text)).iter().map(Word::text).collect()
+    }
+
+    #[test]
+    fn alternatives() {
+        assert_eq!(expand("a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(expand("{a,}x"), ["ax", "x"]);
+        assert_eq!(expand("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(expand("{a,b{1,2}}"), ["a", "b1", "b2"]);
+        assert_eq!(expand("x{a,{b,c}d}y"), ["xay", "xbdy", "xcdy"]);
+        assert_eq!(expand("{{a,b},{1..2}}"), ["a", "b", "1", "2"]);
+    }
+
+    #[test]
+    fn literal_braces() {
+        assert_eq!(expand("{a}"), ["{a}"]);
+        assert_eq!(expand("{}"), ["{}"]);
+        assert_eq!(expand("{a,b"), ["{a,b"]);
+        assert_eq!(expand("a}b"), ["a}b"]);
+        assert_eq!(expand("{1..}"), ["{1..}"]);
+        assert_eq!(expand("{a..5}"), ["{a..5}"]);
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(expand("{1..4}"), ["1", "2", "3", "4"]);
+        assert_eq!(expand("{3..1}"), ["3", "2", "1"]);
+        assert_eq!(expand("{1..10..4}"), ["1", "5", "9"]);
+        assert_eq!(expand("{10..1..-4}"), ["10", "6", "2"]);
+        assert_eq!(expand("{-1..1}"), ["-1", "0", "1"]);
+        assert_eq!(expand("{08..10}"), ["08", "09", "10"]);
+        assert_eq!(expand("{a..c}"), ["a", "b", "c"]);
+        assert_eq!(expand("{C..A}"), ["C", "B", "A"]);
+    }
+
+    #[test]
+    fn huge_sequences_stay_literal() {
+        assert_eq!(expand("{1..9223372036854775807}"), ["{1..9223372036854775807}"]);
+        assert_eq!(
+            expand("{-9223372036854775808..9223372036854775807..9223372036854775807}"),
+            ["-9223372036854775808", "-1", "9223372036854775806"]
+        );
+    }
+
+    #[test]
+    fn quoted_braces_are_literal() {
+        let word = Word {
+            segments: vec![
+                Segment::Plain("a".to_string()),
+                Segment::Single("{b,c}".to_string()),
+                Segment::Plain("{1,2}".to_string()),
+            ],
+        };
+        let words: Vec<String> = expand_braces(&word).iter().map(Word::text).collect();
+        assert_eq!(words, ["a{b,c}1", "a{b,c}2"]);
+    }
+}
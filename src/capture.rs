@@ -0,0 +1,166 @@
+use crate::error::ShellError;
+use crate::redirect::SAVED_FD_MIN;
+use crate::stdio::Stdio;
+use crate::vars::Variables;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::io::AsRawFd;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+
+static CAPTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// 输出捕获：执行期间把Shell的标准输出和标准错误（Stdio）换成临时文件，
+// 内建命令和启动的子进程的输出都写到这里。进程的描述符 1 和 2 不变，
+// 多个线程中的 Shell 可以同时捕获，也不会捕获到其他线程写到标准输出的内容
+// 用文件而不是管道：命令中启动的后台作业可能一直持有写入端，结束捕获时不必等它们退出
+pub struct Capture {
+    saved: Stdio,
+    stdout_file: File,
+    stderr_file: File,
+}
+
+impl Capture {
+    // 开始捕获，stdio 是被换掉的 Shell 的标准输出和标准错误
+    // 换给 Shell 的是两个文件的副本，归这次捕获所有：exec > 文件 之类的重定向只改变副本，
+    // 不影响之后读出捕获的内容，结束时关闭 stdio 中那时的描述符
+    pub fn start(stdio: &mut Stdio) -> Result<Capture, ShellError> {
+        let stdout_file = temp_file()?;
+        let stderr_file = temp_file()?;
+        let out = duplicate(&stdout_file)?;
+        let err = duplicate(&stderr_file)?;
+        let saved = *stdio;
+        *stdio = Stdio {
+            out: out.into_raw_fd(),
+            err: err.into_raw_fd(),
+        };
+        Ok(Capture {
+            saved,
+            stdout_file,
+            stderr_file,
+        })
+    }
+
+    // 结束捕获，恢复原来的标准输出/标准错误并返回捕获到的内容
+    pub fn finish(mut self, stdio: &mut Stdio) -> Result<(String, String), ShellError> {
+        for fd in [stdio.out, stdio.err] {
+            if fd >= 0 {
+                // SAFETY: start 换上的副本或 exec 另外复制的描述符，只在这里关闭一次
+                unsafe { libc::close(fd) };
+            }
+        }
+        *stdio = self.saved;
+        Ok((read_all(&mut self.stdout_file)?, read_all(&mut self.stderr_file)?))
+    }
+}
+
+// 与重定向打开的文件一样放在 10 以上并设置 close-on-exec
+pub(crate) fn duplicate(file: &File) -> Result<OwnedFd, ShellError> {
+    // SAFETY: F_DUPFD_CLOEXEC 成功时返回一个新的描述符，由 OwnedFd 独占
+    unsafe {
+        let fd = libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, SAVED_FD_MIN);
+        if fd < 0 {
+            return Err(ShellError::Io(io::Error::last_os_error()));
+        }
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+// 重定向 >&$NAME 和 >>&$NAME：命令的描述符写入管道，由另一个线程读出，不经过临时文件；
+// 写入端全部关闭（命令结束）后 finish 把内容存入变量。超过 limit 字节的部分被丢弃，
+// 但仍然读完，命令不会因为管道写满而阻塞
+pub struct VarCapture {
+    name: String,
+    append: bool,
+    limit: usize,
+    reader: JoinHandle<io::Result<(Vec<u8>, bool)>>,
+}
+
+impl VarCapture {
+    // 开始捕获，返回捕获和管道的写入端
+    pub fn start(name: &str, append: bool, limit: usize) -> Result<(VarCapture, File), ShellError> {
+        let (mut pipe, writer) = io::pipe()?;
+        let reader = thread::spawn(move || {
+            let mut data = Vec::new();
+            let mut truncated = false;
+            let mut buffer = [0; 8192];
+            loop {
+                let n = match pipe.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                let room = limit - data.len();
+                truncated |= n > room;
+                data.extend_from_slice(&buffer[..n.min(room)]);
+            }
+            Ok((data, truncated))
+        });
+        let capture = VarCapture {
+            name: name.to_string(),
+            append,
+            limit,
+            reader,
+        };
+        Ok((capture, File::from(OwnedFd::from(writer))))
+    }
+
+    // 等待读完后存入变量，与命令替换一样去掉末尾的换行；
+    // 追加时如果变量原来不为空，用换行与新的内容隔开
+    pub fn finish(self, vars: &mut Variables) -> Result<(), ShellError> {
+        let (data, truncated) = self
+            .reader
+            .join()
+            .map_err(|_| ShellError::CommandError(format!("捕获到变量 {} 时出错", self.name)))??;
+        let text = String::from_utf8_lossy(&data);
+        let text = text.trim_end_matches('\n');
+        let value = match vars.get(&self.name) {
+            Some(old) if self.append && !old.is_empty() && !text.is_empty() => format!("{}\n{}", old, text),
+            Some(old) if self.append => format!("{}{}", old, text),
+            _ => text.to_string(),
+        };
+        vars.set(&self.name, &value);
+        if truncated {
+            return Err(ShellError::CommandError(format!(
+                "输出超过 CAPTURE_LIMIT（{} 字节），变量 {} 中只有前面的部分",
+                self.limit, self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+// 依次结束多个捕获，全部存入变量后返回第一个错误
+pub fn finish_captures(captures: Vec<VarCapture>, vars: &mut Variables) -> Result<(), ShellError> {
+    let mut result = Ok(());
+    for capture in captures {
+        let finished = capture.finish(vars);
+        if result.is_ok() {
+            result = finished;
+        }
+    }
+    result
+}
+
+// 创建一个已删除目录项的临时文件
+fn temp_file() -> Result<File, ShellError> {
+    let n = CAPTURE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("rsh-capture-{}-{}", process::id(), n));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    Ok(file)
+}
+
+fn read_all(file: &mut File) -> Result<String, ShellError> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,400 @@
+use crate::error::ShellError;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::FromRawFd;
+
+// 内建命令 hash-file：hash-file [--md5|--sha1|--sha256] [文件...]
+// 按 sha256sum 的格式输出"摘要  文件名"，默认使用 SHA-256，没有文件时读取标准输入
+pub fn run_hash_file(out: &mut impl Write, err: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut algorithm = Algorithm::Sha256;
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--md5" => algorithm = Algorithm::Md5,
+            "--sha1" => algorithm = Algorithm::Sha1,
+            "--sha256" => algorithm = Algorithm::Sha256,
+            "--" => paths.extend(iter.by_ref()),
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!(
+                    "hash-file: 未知的选项 '{}'\n用法: hash-file [--md5|--sha1|--sha256] [文件...]",
+                    flag
+                )));
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        // 直接读取描述符 0，不经过标准库的缓冲
+        // SAFETY: ManuallyDrop 保证不会关闭Shell的标准输入
+        let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(libc::STDIN_FILENO) });
+        writeln!(out, "{}  -", algorithm.digest(&mut *stdin)?)?;
+        return Ok(());
+    }
+
+    // 某个文件无法读取时继续处理其余文件，最后报告失败
+    let mut failed = false;
+    for path in paths {
+        match File::open(path).and_then(|mut file| algorithm.digest(&mut file)) {
+            Ok(digest) => writeln!(out, "{}  {}", digest, path)?,
+            Err(e) => {
+                writeln!(err, "hash-file: '{}': {}", path, e)?;
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return Err(ShellError::CommandError("hash-file: 部分文件无法读取".to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    // 读完 input 并返回十六进制的摘要
+    fn digest(self, input: &mut impl Read) -> io::Result<String> {
+        let mut hasher: Box<dyn Hasher> = match self {
+            Algorithm::Md5 => Box::new(Md5::default()),
+            Algorithm::Sha1 => Box::new(Sha1::default()),
+            Algorithm::Sha256 => Box::new(Sha256::default()),
+        };
+
+        let mut blocks = Blocks::default();
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            blocks.update(&buf[..n], hasher.as_mut());
+        }
+        blocks.finish(hasher.as_mut());
+
+        Ok(hasher.output().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+// 三种算法都按64字节分块处理，区别在于压缩函数和长度的字节序
+trait Hasher {
+    fn compress(&mut self, block: &[u8; 64]);
+    // 填充时长度是否按大端序写入
+    fn big_endian(&self) -> bool;
+    fn output(&self) -> Vec<u8>;
+}
+
+// 把输入拼成完整的块交给压缩函数，最后按 Merkle–Damgård 方式填充
+#[derive(Default)]
+struct Blocks {
+    pending: Vec<u8>,
+    length: u64,
+}
+
+impl Blocks {
+    fn update(&mut self, mut data: &[u8], hasher: &mut dyn Hasher) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        if !self.pending.is_empty() {
+            let take = (64 - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < 64 {
+                return;
+            }
+            hasher.compress(self.pending.as_slice().try_into().unwrap());
+            self.pending.clear();
+        }
+
+        let mut chunks = data.chunks_exact(64);
+        for block in chunks.by_ref() {
+            hasher.compress(block.try_into().unwrap());
+        }
+        self.pending.extend_from_slice(chunks.remainder());
+    }
+
+    fn finish(&mut self, hasher: &mut dyn Hasher) {
+        let bits = self.length.wrapping_mul(8);
+        let mut tail = std::mem::take(&mut self.pending);
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        if hasher.big_endian() {
+            tail.extend_from_slice(&bits.to_be_bytes());
+        } else {
+            tail.extend_from_slice(&bits.to_le_bytes());
+        }
+        for block in tail.chunks_exact(64) {
+            hasher.compress(block.try_into().unwrap());
+        }
+    }
+}
+
+struct Md5 {
+    state: [u32; 4],
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+        }
+    }
+}
+
+// MD5 每一步的循环左移位数
+const MD5_SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+impl Hasher for Md5 {
+    fn compress(&mut self, block: &[u8; 64]) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = self.state;
+
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            // 常量是 |sin(i + 1)| * 2^32 的整数部分
+            let k = (((i + 1) as f64).sin().abs() * 4294967296.0) as u32;
+            let shift = MD5_SHIFTS[(i / 16) * 4 + i % 4];
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(k)
+                .wrapping_add(words[g])
+                .rotate_left(shift);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn big_endian(&self) -> bool {
+        false
+    }
+
+    fn output(&self) -> Vec<u8> {
+        self.state.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+}
+
+struct Sha1 {
+    state: [u32; 5],
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0],
+        }
+    }
+}
+
+impl Hasher for Sha1 {
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 80];
+        for (i, b) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn big_endian(&self) -> bool {
+        true
+    }
+
+    fn output(&self) -> Vec<u8> {
+        self.state.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+}
+
+struct Sha256 {
+    state: [u32; 8],
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256 {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+            ],
+        }
+    }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+impl Hasher for Sha256 {
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+        for (i, b) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (k, word) in SHA256_K.iter().zip(w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn big_endian(&self) -> bool {
+        true
+    }
+
+    fn output(&self) -> Vec<u8> {
+        self.state.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 标准的测试向量：空输入、"abc"、正好填充出两个块的56字节消息，以及超过一个块的112字节消息
+    const INPUTS: [&str; 4] = [
+        "",
+        "abc",
+        "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+    ];
+
+    // 每次只读出几个字节的输入，检查跨越块边界的拼接
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn check(algorithm: Algorithm, expected: [&str; 4]) {
+        for (input, expected) in INPUTS.iter().zip(expected) {
+            assert_eq!(algorithm.digest(&mut input.as_bytes()).unwrap(), expected, "{:?}", input);
+            assert_eq!(algorithm.digest(&mut Trickle(input.as_bytes())).unwrap(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn md5_known_answers() {
+        check(
+            Algorithm::Md5,
+            [
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "900150983cd24fb0d6963f7d28e17f72",
+                "8215ef0796a20bcaaae116d3876c664a",
+                "03dd8807a93175fb062dfb55dc7d359c",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha1_known_answers() {
+        check(
+            Algorithm::Sha1,
+            [
+                "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+                "a49b2446a02c645bf419f995b67091253a04a259",
+            ],
+        );
+    }
+
+    #[test]
+    fn sha256_known_answers() {
+        check(
+            Algorithm::Sha256,
+            [
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+            ],
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use clap::{Arg, ArgAction, Command};
+use std::path::PathBuf;
+
+// 命令行参数解析的结果
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    // -c：执行给定的命令字符串后退出
+    pub command: Option<String>,
+    // -l：作为登录Shell启动，先读取 ~/.rsh_profile
+    pub login: bool,
+    // --posix：POSIX 兼容模式
+    pub posix: bool,
+    // --rcfile：交互模式下读取的启动文件，取代 ~/.rshrc
+    pub rcfile: Option<PathBuf>,
+    // --server：在Unix套接字上提供服务
+    pub server: Option<PathBuf>,
+    // --output json：无终端的批处理模式
+    pub json_output: bool,
+    // --profile-startup：打印启动各阶段的耗时
+    pub profile_startup: bool,
+    // -n / --syntax-check：只检查脚本或命令字符串的语法，不执行
+    pub syntax_check: bool,
+    // --dump-ast：不执行，把解析得到的语法树以JSON输出
+    pub dump_ast: bool,
+    // 要执行的脚本；与 -c 一起使用时作为 $0
+    pub script: Option<String>,
+    // 脚本的位置参数
+    pub script_args: Vec<String>,
+}
+
+impl CliArgs {
+    // 是否进入交互模式
+    pub fn interactive(&self) -> bool {
+        self.command.is_none() && self.script.is_none() && self.server.is_none() && !self.json_output
+    }
+}
+
+fn command() -> Command {
+    Command::new("rsh")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("用Rust编写的命令行解释器")
+        .arg(
+            Arg::new("command")
+                .short('c')
+                .value_name("命令")
+                .help("执行给定的命令字符串后退出"),
+        )
+        .arg(
+            Arg::new("login")
+                .short('l')
+                .long("login")
+                .action(ArgAction::SetTrue)
+                .help("作为登录Shell启动，先读取 ~/.rsh_profile"),
+        )
+        .arg(
+            Arg::new("posix")
+                .long("posix")
+                .action(ArgAction::SetTrue)
+                .help("POSIX 兼容模式，交互时读取 $ENV 而不是 ~/.rshrc"),
+        )
+        .arg(
+            Arg::new("rcfile")
+                .long("rcfile")
+                .value_name("文件")
+                .help("交互模式下读取的启动文件，取代 ~/.rshrc"),
+        )
+        .arg(
+            Arg::new("server")
+                .long("server")
+                .value_name("套接字")
+                .conflicts_with_all(["command", "output"])
+                .help("在Unix套接字上提供服务"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("格式")
+                .value_parser(["json"])
+                .conflicts_with("command")
+                .help("从标准输入读取命令，以给定格式输出每条命令的结果"),
+        )
+        .arg(
+            Arg::new("profile-startup")
+                .long("profile-startup")
+                .action(ArgAction::SetTrue)
+                .help("打印启动文件、插件和历史记录加载各阶段的耗时"),
+        )
+        .arg(
+            Arg::new("syntax-check")
+                .short('n')
+                .long("syntax-check")
+                .action(ArgAction::SetTrue)
+                .help("只检查语法、报告错误而不执行，有语法错误时状态码为 2"),
+        )
+        .arg(
+            Arg::new("dump-ast")
+                .long("dump-ast")
+                .action(ArgAction::SetTrue)
+                .help("不执行，把每条命令的语法树以JSON输出，每行一条"),
+        )
+        .arg(Arg::new("script").value_name("脚本").help("要执行的脚本文件"))
+        .arg(
+            Arg::new("args")
+                .value_name("参数")
+                .num_args(1..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)
+                .help("传给脚本的参数"),
+        )
+}
+
+// 解析进程的命令行参数，--help、--version 和参数错误时由clap打印信息并退出
+pub fn parse_args() -> CliArgs {
+    let matches = command().get_matches();
+
+    CliArgs {
+        command: matches.get_one::<String>("command").cloned(),
+        login: matches.get_flag("login"),
+        posix: matches.get_flag("posix"),
+        rcfile: matches.get_one::<String>("rcfile").map(PathBuf::from),
+        server: matches.get_one::<String>("server").map(PathBuf::from),
+        json_output: matches.get_one::<String>("output").is_some(),
+        profile_startup: matches.get_flag("profile-startup"),
+        syntax_check: matches.get_flag("syntax-check"),
+        dump_ast: matches.get_flag("dump-ast"),
+        script: matches.get_one::<String>("script").cloned(),
+        script_args: matches
+            .get_many::<String>("args")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default(),
+    }
+}
@@ -1,20 +1,91 @@
+use crate::alias::{run_alias, run_unalias};
+use crate::bookmark::run_bookmark;
+use crate::capture::finish_captures;
+use crate::checksum::run_hash_file;
+use crate::completion::{run_compgen_from, run_complete, run_complete_import};
+use crate::console;
+use crate::date::run_date;
+use crate::diagnostic;
+use crate::disk::{run_dfree, run_dsize};
+use crate::each::run_each;
 use crate::error::ShellError;
-use crate::parser::Command;
+use crate::expand::expand_command;
+#[cfg(feature = "archive")]
+use crate::extract::run_extract;
+#[cfg(feature = "fetch")]
+use crate::fetch::run_fetch;
+#[cfg(feature = "watch")]
+use crate::watch::run_onchange;
+use crate::glob;
+use crate::hexdump::run_hexdump;
+use crate::hooks::{run_hook, run_trap, HookKind};
+use crate::jobs::{fork_background, output_file, redirect_to_output, run_disown, run_jobs, Jobs};
+use crate::math::run_math;
+use crate::mock::{run_mock, run_unmock, spawn_mock};
+use crate::options::run_set;
+use crate::parser::{
+    parse_input, tokenize, AndOrList, Command, CompoundCommand, CompoundList, Connector, Group, RedirectKind, Segment,
+    SimpleCommand, Token,
+};
+use crate::pathcache;
+use crate::pathutil::{run_basename, run_dirname, run_realpath};
+use crate::procs::run_procs;
+use crate::random::{run_rand, run_uuid};
+use crate::read::{read_stdin_line, run_mapfile, run_read};
+use crate::redirect::{install_child_redirects, open_redirects, run_umask, CreateOptions, FdGuard, OpenRedirect};
+use crate::rusage::wait_with_rusage;
+use crate::schedule::run_schedule;
+use crate::shell::Shell;
+use crate::shtest::run_shtest;
+use crate::signals::{describe_signal, fork_child};
+use crate::spawn::{self, spawn};
+use crate::strings::run_string;
+use crate::task::run_task;
+use crate::trash::run_del;
+use crate::tutor::run_tutor;
+use crate::vars::{run_env_restore, run_env_save, run_export, run_popenv, run_pushenv, run_unset};
+use std::borrow::Cow;
 use std::env;
-use std::path::Path;
-use std::process::{Child, Command as ProcessCommand, Stdio};
+use std::fs::File;
+use std::io::{self, PipeReader, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Instant;
 
-// 内建命令
-fn execute_builtin(cmd: &Command) -> Result<bool, ShellError> {
+// 内建命令的名字，供命令面板列出；新增内建命令时也要加到这里
+pub const BUILTINS: &[&str] = &[
+    "cd", "pwd", "echo", "str", #[cfg(feature = "archive")] "extract", #[cfg(feature = "watch")] "onchange",
+    #[cfg(feature = "fetch")] "fetch", "dsize", "dfree", "hash-file", "hexdump", "math", "rand", "uuid", "date",
+    "basename", "dirname", "realpath", "alias", "unalias", "hook", "trap", "read", "mapfile", "readarray",
+    "del", "bookmark", "procs", "jobs", "disown", "each", "mock", "unmock", "tutor", "shtest", "schedule", "task", "set", "umask", "time", "export",
+    "unset", "env-save", "env-restore", "pushenv", "popenv", "compgen-from", "complete", "complete-import", "rehash", "exec",
+    "exit",
+];
+
+// 可能修改 PATH 的内建命令（"" 是只有赋值的命令），执行之后检查是否需要丢弃可执行文件的索引
+const CHANGES_PATH: &[&str] = &["", "export", "unset", "pushenv", "popenv", "env-restore"];
+
+// 内建命令，返回它的状态码；不是内建命令时返回 None
+fn execute_builtin(shell: &mut Shell, cmd: &SimpleCommand) -> Result<Option<i32>, ShellError> {
     match cmd.program.as_str() {
+        // 没有程序的命令：FOO=bar、> 文件，或者全部展开为空的 $EMPTY
+        "" => {
+            for (name, value) in &cmd.assignments {
+                shell.vars.set(name, &value.text());
+            }
+            Ok(Some(0))
+        }
         "cd" => {
-            let new_dir = match cmd.args.get(0) {
+            let new_dir = match cmd.args.first() {
                 Some(dir) => dir.clone(),
                 None => {
                     // 如果没有参数，默认进入HOME目录
-                    match env::var("HOME") {
-                        Ok(home) => home, // 返回所有权而非引用
-                        Err(_) => {
+                    match shell.vars.get("HOME") {
+                        Some(home) => home.to_string(),
+                        None => {
                             return Err(ShellError::CommandError(
                                 "无法确定HOME目录".to_string(),
                             ))
@@ -23,137 +94,940 @@ fn execute_builtin(cmd: &Command) -> Result<bool, ShellError> {
                 }
             };
             
+            let old_dir = env::current_dir()?;
             if let Err(e) = env::set_current_dir(Path::new(&new_dir)) {
                 return Err(ShellError::Io(e));
             }
-            Ok(true)
+            
+            let current_dir = env::current_dir()?;
+            shell.recent_dirs.visit(&old_dir);
+            shell.recent_dirs.visit(&current_dir);
+            shell.vars.set("OLDPWD", &old_dir.to_string_lossy());
+            shell.vars.set("PWD", &current_dir.to_string_lossy());
+            shell.run_hooks(HookKind::Chpwd, &current_dir.to_string_lossy());
+            Ok(Some(0))
         }
         "pwd" => {
             let current_dir = env::current_dir()?;
-            println!("{}", current_dir.display());
-            Ok(true)
+            writeln!(shell.stdio.out(), "{}", current_dir.display())?;
+            Ok(Some(0))
         }
         "echo" => {
-            println!("{}", cmd.args.join(" "));
-            Ok(true)
+            writeln!(shell.stdio.out(), "{}", cmd.args.join(" "))?;
+            Ok(Some(0))
+        }
+        "str" => {
+            run_string(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        #[cfg(feature = "archive")]
+        "extract" => {
+            run_extract(&cmd.args)?;
+            Ok(Some(0))
+        }
+        #[cfg(feature = "watch")]
+        "onchange" => {
+            run_onchange(shell, &cmd.args)?;
+            Ok(Some(0))
+        }
+        #[cfg(feature = "fetch")]
+        "fetch" => {
+            run_fetch(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "dsize" => {
+            run_dsize(&mut shell.stdio.out(), &mut shell.stdio.err(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "dfree" => {
+            run_dfree(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "hash-file" => {
+            run_hash_file(&mut shell.stdio.out(), &mut shell.stdio.err(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "hexdump" => {
+            run_hexdump(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "math" => {
+            run_math(&mut shell.stdio.out(), &shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "rand" => {
+            run_rand(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "uuid" => {
+            run_uuid(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "date" => {
+            run_date(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "basename" => {
+            run_basename(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "dirname" => {
+            run_dirname(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "realpath" => {
+            run_realpath(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "alias" => {
+            run_alias(&mut shell.stdio.out(), &mut shell.aliases, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "unalias" => {
+            run_unalias(&mut shell.aliases, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "hook" => {
+            run_hook(&mut shell.stdio.out(), &mut shell.hooks, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "trap" => {
+            run_trap(&mut shell.stdio.out(), &mut shell.exit_trap, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "read" => run_read(&mut shell.stdio.err(), &mut shell.vars, &cmd.args).map(Some),
+        "mapfile" | "readarray" => {
+            run_mapfile(&mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "del" => {
+            run_del(&mut shell.stdio.out(), &shell.vars, &cmd.args)?;
+            Ok(Some(0))
         }
-        _ => Ok(false), // 不是内建命令
+        "bookmark" => {
+            run_bookmark(&mut shell.stdio.out(), &shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "procs" => {
+            run_procs(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "jobs" => {
+            run_jobs(&mut shell.stdio.out(), &mut shell.stdio.err(), &mut shell.jobs, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "disown" => {
+            run_disown(&mut shell.jobs, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "mock" => {
+            run_mock(&mut shell.stdio.out(), &mut shell.mocks, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "unmock" => {
+            run_unmock(&mut shell.mocks, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "tutor" => {
+            run_tutor(shell, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "shtest" => {
+            run_shtest(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "schedule" => {
+            run_schedule(shell, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "task" => {
+            run_task(shell, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "set" => {
+            run_set(&mut shell.stdio.out(), &mut shell.options, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "umask" => {
+            run_umask(&mut shell.stdio.out(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "time" => run_time(shell, &cmd.args).map(Some),
+        "each" => run_each(shell, &cmd.args).map(Some),
+        "export" => {
+            run_export(&mut shell.stdio.out(), &mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "unset" => {
+            run_unset(&mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "env-save" => {
+            run_env_save(&mut shell.stdio.out(), &mut shell.env_snapshots, &shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "env-restore" => {
+            run_env_restore(&shell.env_snapshots, &mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "pushenv" => {
+            run_pushenv(&mut shell.env_stack, &mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "popenv" => {
+            run_popenv(&mut shell.env_stack, &mut shell.vars, &cmd.args)?;
+            Ok(Some(0))
+        }
+        "compgen-from" => {
+            run_compgen_from(&mut shell.stdio.out(), &mut shell.completions.borrow_mut(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        "complete" => {
+            run_complete(&mut shell.stdio.out(), &mut shell.completions.borrow_mut(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        // 在管道中执行时重定向已经在子进程中生效
+        "exec" => run_exec(shell, cmd, &[]).map(Some),
+        "exit" => Err(run_exit(shell, &cmd.args)),
+        "rehash" => {
+            shell.path_cache.borrow_mut().clear();
+            Ok(Some(0))
+        }
+        "complete-import" => {
+            run_complete_import(&mut shell.stdio.out(), &mut shell.stdio.err(), &mut shell.completions.borrow_mut(), &cmd.args)?;
+            Ok(Some(0))
+        }
+        // 插件命令的非零状态码与外部命令一样只记录在 $? 中
+        _ => match execute_plugin(shell, cmd) {
+            Some(result) => result.map(Some),
+            None => Ok(None), // 不是内建命令
+        },
+    }
+}
+
+// 命令是否由Shell自己执行：内建命令、插件命令，以及只有赋值或重定向、没有程序的命令
+#[cfg_attr(
+    not(any(feature = "plugins", feature = "wasm-plugins")),
+    allow(unused_variables)
+)]
+fn is_builtin(shell: &Shell, cmd: &SimpleCommand) -> bool {
+    let name = cmd.program.as_str();
+    if name.is_empty() || BUILTINS.contains(&name) {
+        return true;
+    }
+    #[cfg(feature = "plugins")]
+    if shell.plugins.contains(name) {
+        return true;
+    }
+    #[cfg(feature = "wasm-plugins")]
+    if shell.wasm_plugins.contains(name) {
+        return true;
     }
+    false
 }
 
-// 执行外部命令
-fn execute_external(cmd: &Command) -> Result<Child, ShellError> {
-    let child = ProcessCommand::new(&cmd.program)
-        .args(&cmd.args)
-        .spawn()
-        .map_err(|e| ShellError::CommandError(format!("无法执行命令 '{}': {}", cmd.program, e)))?;
+// 执行插件提供的内建命令，没有对应的插件命令时返回 None
+#[cfg_attr(
+    not(any(feature = "plugins", feature = "wasm-plugins")),
+    allow(unused_variables)
+)]
+fn execute_plugin(shell: &mut Shell, cmd: &SimpleCommand) -> Option<Result<i32, ShellError>> {
+    #[cfg(feature = "plugins")]
+    if let Some(result) = shell.plugins.call(&cmd.program, &cmd.args) {
+        return Some(result);
+    }
+    
+    #[cfg(feature = "wasm-plugins")]
+    if let Some(result) = shell.wasm_plugins.call(shell.stdio, &cmd.program, &cmd.args) {
+        return Some(result);
+    }
     
-    Ok(child)
+    None
+}
+
+// 执行外部命令，标准输入、输出和错误指向重定向的文件或管道（如果有），返回进程号
+// 命令前的 FOO=bar 赋值只加入这个命令的环境变量
+// 不含 / 的命令名先在 PATH 的索引中查找，索引中没有时再查找 PATH 的目录；
+// 索引中的文件已被删除时丢弃索引，再按 PATH 查找一次。命令前有 PATH=... 时只按这个 PATH 查找
+fn execute_external(shell: &Shell, cmd: &SimpleCommand, files: &[OpenRedirect]) -> Result<libc::pid_t, ShellError> {
+    let assignments: Vec<(String, String)> = cmd
+        .assignments
+        .iter()
+        .map(|(name, value)| (name.clone(), value.text()))
+        .collect();
+    let environ = if assignments.is_empty() {
+        Cow::Borrowed(shell.vars.environ())
+    } else {
+        Cow::Owned(shell.vars.environ_with(&assignments))
+    };
+    let temporary_path = assignments.iter().rev().find(|(name, _)| name == "PATH").map(|(_, value)| value.as_str());
+
+    let run = |path: &Path| spawn(path, &cmd.program, &cmd.args, &environ, &shell.stdio, files);
+    let search = || {
+        let path = temporary_path.unwrap_or_else(|| shell.vars.get("PATH").unwrap_or(""));
+        match pathcache::search(path, &cmd.program) {
+            Some(path) => run(&path),
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    };
+    let result = if cmd.program.contains('/') {
+        run(Path::new(&cmd.program))
+    } else if temporary_path.is_some() {
+        search()
+    } else {
+        let cached = shell.path_cache.borrow_mut().lookup(&cmd.program);
+        match cached.map(|path| run(&path)) {
+            Some(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+                shell.path_cache.borrow_mut().clear();
+                search()
+            }
+            Some(result) => result,
+            None => search(),
+        }
+    };
+    result.map_err(|e| ShellError::SpawnError(cmd.program.clone(), e))
+}
+
+// 内建命令 exit：exit [状态码]，省略状态码时为上一条命令的状态码
+// 返回 ShellError::Exit，由读取命令的地方结束Shell；参数无效时返回普通的错误，Shell不退出
+fn run_exit(shell: &Shell, args: &[String]) -> ShellError {
+    match args {
+        [] => ShellError::Exit(shell.last_status),
+        [code] => match code.parse::<i32>() {
+            Ok(code) => ShellError::Exit(code & 0xff),
+            Err(_) => ShellError::CommandError(format!("exit: 需要数字参数 '{}'", code)),
+        },
+        _ => ShellError::CommandError("exit: 参数太多".to_string()),
+    }
+}
+
+// 不经过索引直接在 PATH 的目录中查找命令
+fn search_path(shell: &Shell, program: &str) -> Option<PathBuf> {
+    pathcache::search(shell.vars.get("PATH").unwrap_or(""), program)
+}
+
+// 内建命令 exec：exec [命令 [参数...]] [重定向...]
+// 没有命令时重定向在当前Shell中永久生效，例如 exec 3< 文件 之后可以用 <&3 读取，用 exec 3>&- 关闭；
+// 既没有命令也没有重定向时列出这样设置的描述符。有命令时重定向同样生效，然后用命令取代Shell进程
+fn run_exec(shell: &mut Shell, cmd: &SimpleCommand, files: &[OpenRedirect]) -> Result<i32, ShellError> {
+    if cmd.redirects.iter().any(|redirect| matches!(redirect.kind, RedirectKind::Capture | RedirectKind::CaptureAppend)) {
+        return Err(ShellError::CommandError("exec: 不能把输出永久捕获到变量中".to_string()));
+    }
+    shell.fds.install(files, &cmd.redirects, &mut shell.stdio)?;
+    let Some((program, args)) = cmd.args.split_first() else {
+        if cmd.redirects.is_empty() {
+            let mut out = shell.stdio.out();
+            for (fd, text) in shell.fds.iter() {
+                writeln!(out, "{}{}", fd, text)?;
+            }
+        }
+        return Ok(0);
+    };
+
+    // 命令前的 PATH=... 此时已经临时生效，不使用按原来的 PATH 建立的索引
+    let path = if program.contains('/') {
+        Some(PathBuf::from(program))
+    } else if cmd.assignments.iter().any(|(name, _)| name == "PATH") {
+        search_path(shell, program)
+    } else {
+        let cached = shell.path_cache.borrow_mut().lookup(program);
+        cached.or_else(|| search_path(shell, program))
+    };
+    let error = match path {
+        Some(path) => spawn::exec(&path, program, args, shell.vars.environ()),
+        None => io::Error::from_raw_os_error(libc::ENOENT),
+    };
+    Err(ShellError::SpawnError(program.clone(), error))
+}
+
+// 后缀别名分派：命令词是带有已注册扩展名的非可执行文件时，改用别名指定的程序打开
+// 文件不存在或者是目录时不分派，按普通命令报告找不到（127）
+// 命令的赋值和重定向原样保留在改写后的命令上
+fn dispatch_suffix_alias(shell: &Shell, cmd: SimpleCommand) -> Result<SimpleCommand, ShellError> {
+    let opener = match shell.aliases.get_suffix(&cmd.program) {
+        Some(opener) => opener,
+        None => return Ok(cmd),
+    };
+    
+    let openable = Path::new(&cmd.program)
+        .metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 == 0);
+    if !openable {
+        return Ok(cmd);
+    }
+    
+    let mut words = Vec::new();
+    for token in tokenize(opener)? {
+        match token {
+            Token::Word(word) => words.push(word.text()),
+            _ => {
+                return Err(ShellError::CommandError(format!(
+                    "后缀别名 '{}' 不能包含管道、分号、括号或重定向",
+                    opener
+                )))
+            }
+        }
+    }
+    if words.is_empty() {
+        return Ok(cmd);
+    }
+    
+    let program = words.remove(0);
+    words.push(cmd.program);
+    words.extend(cmd.args);
+    Ok(SimpleCommand {
+        program,
+        args: words,
+        ..cmd
+    })
+}
+
+// 启动一个命令（外部命令、内建命令或子Shell）但不等待，返回进程号
+// 管道中的 { ...; } 和内建命令与 bash 一样也在子Shell中执行，例如 echo x | read v 不改变当前Shell的变量
+fn spawn_command(shell: &mut Shell, cmd: &Command, files: &[OpenRedirect]) -> Result<libc::pid_t, ShellError> {
+    let cmd = match cmd {
+        Command::Compound(compound) => return spawn_subshell(shell, compound.group.lists().to_vec(), files),
+        Command::Simple(cmd) => cmd,
+    };
+    if is_builtin(shell, cmd) {
+        return spawn_builtin(shell, cmd, files);
+    }
+    match shell.mocks.get_mut(&cmd.program) {
+        Some(mock) => {
+            mock.calls += 1;
+            spawn_mock(mock, shell.stdio, files)
+        }
+        None => execute_external(shell, cmd, files),
+    }
+}
+
+// 在子Shell中执行括号中的列表，变量和当前目录等的修改不会影响当前Shell
+fn spawn_subshell(shell: &mut Shell, lists: CompoundList, files: &[OpenRedirect]) -> Result<libc::pid_t, ShellError> {
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    
+    let pid = fork_child()?;
+    if pid == 0 {
+        if let Err(e) = install_child_redirects(files, &mut shell.stdio) {
+            diagnostic::report(&mut shell.stdio.err(), &e);
+            // SAFETY: 子Shell直接退出
+            unsafe { libc::_exit(1) }
+        }
+        exit_child(shell, lists);
+    }
+    Ok(pid)
+}
+
+// 在子Shell中执行内建命令，标准描述符指向管道或重定向的文件，内建命令的状态码是子Shell的退出码
+fn spawn_builtin(shell: &mut Shell, cmd: &SimpleCommand, files: &[OpenRedirect]) -> Result<libc::pid_t, ShellError> {
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    
+    let pid = fork_child()?;
+    if pid == 0 {
+        // jobs | grep 等需要父Shell的作业表
+        shell.jobs.inherit();
+        let status = match install_child_redirects(files, &mut shell.stdio).and_then(|()| with_assignments(shell, cmd, |shell| execute_builtin(shell, cmd))) {
+            Ok(status) => status.unwrap_or(0),
+            Err(e) => {
+                diagnostic::report(&mut shell.stdio.err(), &e);
+                e.status()
+            }
+        };
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        // SAFETY: 子Shell直接退出，缓冲的输出已经写出
+        unsafe { libc::_exit(status) }
+    }
+    Ok(pid)
+}
+
+// 在 fork 出的子Shell中执行列表后退出，不运行析构函数和 EXIT trap
+pub(crate) fn exit_child(shell: &mut Shell, lists: CompoundList) -> ! {
+    // 作业表中的进程不是子Shell的子进程
+    shell.jobs = Jobs::default();
+    // 只有交互的Shell本身请求确认，子Shell的标准输入可能是管道
+    shell.options.preview = false;
+    let status = match execute_command(shell, lists) {
+        Ok(status) => status,
+        Err(e) => {
+            diagnostic::report(&mut shell.stdio.err(), &e);
+            e.status()
+        }
+    };
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    // SAFETY: 子Shell直接退出，缓冲的输出已经写出
+    unsafe { libc::_exit(status) }
+}
+
+// 命令替换 $(...)：在子Shell中执行命令，返回它的标准输出，去掉末尾的换行
+pub fn command_substitution(shell: &mut Shell, command: &str) -> Result<String, ShellError> {
+    if command.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let lists = parse_input(command, &shell.aliases)?;
+    let (mut reader, writer) = io::pipe()?;
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    let pid = fork_child()?;
+    if pid == 0 {
+        drop(reader);
+        // 标准错误仍然是Shell的标准错误
+        let _ = shell.stdio.install();
+        // SAFETY: 子Shell的标准输出改为管道的写端
+        unsafe { libc::dup2(writer.as_raw_fd(), libc::STDOUT_FILENO) };
+        drop(writer);
+        exit_child(shell, lists);
+    }
+
+    // 关闭父进程中的写端，子Shell退出后才能读到文件结尾
+    drop(writer);
+    let mut output = Vec::new();
+    let read = reader.read_to_end(&mut output);
+    let (status, _) = wait_with_rusage(pid)?;
+    read?;
+    shell.last_status = exit_code(status);
+
+    let mut output = String::from_utf8_lossy(&output).into_owned();
+    output.truncate(output.trim_end_matches('\n').len());
+    Ok(output)
+}
+
+// 命令的状态码：正常退出时为退出码，被信号终止时为 128 加信号编号
+pub fn exit_code(status: ExitStatus) -> i32 {
+    match status.signal() {
+        Some(signal) => 128 + signal,
+        None => status.code().unwrap_or(1),
+    }
 }
 
-// 执行带管道的命令
-fn execute_piped_commands(commands: Vec<Command>) -> Result<(), ShellError> {
+// 前台命令结束后的状态码。非零状态默认只记录在 $? 中，例如 grep 没有匹配时返回 1；
+// 被信号终止（Ctrl-C 和 SIGPIPE 除外）或者 errexit 对这条命令起作用时才报告命令退出的原因
+fn command_status(shell: &Shell, cmd: &Command, status: ExitStatus) -> i32 {
+    let signaled = status
+        .signal()
+        .is_some_and(|signal| signal != libc::SIGINT && signal != libc::SIGPIPE);
+    if signaled || (shell.options.errexit && !shell.errexit_exempt && !status.success()) {
+        diagnostic::report(&mut shell.stdio.err(), &exit_error(cmd, status));
+    }
+    exit_code(status)
+}
+
+// 命令以非零状态结束或被信号终止时的错误
+fn exit_error(cmd: &Command, status: ExitStatus) -> ShellError {
+    let name = match cmd {
+        Command::Simple(simple) => simple.program.clone(),
+        Command::Compound(_) => cmd.text(),
+    };
+    match status.signal() {
+        Some(signal) => ShellError::CommandError(format!(
+            "命令 '{}' {}",
+            name,
+            describe_signal(signal, status.core_dumped())
+        )),
+        None => ShellError::CommandError(format!(
+            "命令 '{}' 退出，状态码: {}",
+            name,
+            status.code().unwrap_or(-1)
+        )),
+    }
+}
+
+// 执行带管道的命令，返回最后一个命令的状态码；展开时开始的进程替换在整个管道结束后清理
+fn execute_piped_commands(shell: &mut Shell, commands: Vec<Command>) -> Result<i32, ShellError> {
+    let mark = shell.process_substitutions.mark();
+    let result = run_pipeline(shell, commands);
+    shell.process_substitutions.finish(mark);
+    result
+}
+
+fn run_pipeline(shell: &mut Shell, commands: Vec<Command>) -> Result<i32, ShellError> {
     if commands.is_empty() {
-        return Ok(());
+        return Ok(0);
+    }
+    
+    // 展开之后就无法知道参数是否来自通配符
+    let globbed: Vec<bool> = commands
+        .iter()
+        .map(|cmd| matches!(cmd, Command::Simple(simple) if has_unquoted_glob(simple)))
+        .collect();
+    // 简单命令在这里展开，复合命令中的命令在执行到时才展开
+    let commands = commands
+        .into_iter()
+        .map(|cmd| match cmd {
+            Command::Simple(simple) => expand_command(shell, &simple)
+                .and_then(|simple| dispatch_suffix_alias(shell, simple))
+                .map(Command::Simple),
+            compound => Ok(compound),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(executed) = &mut shell.executed_argv {
+        let simple = commands.iter().filter_map(|cmd| match cmd {
+            Command::Simple(simple) if !simple.program.is_empty() => Some(simple),
+            _ => None,
+        });
+        executed.extend(simple.map(|cmd| std::iter::once(&cmd.program).chain(&cmd.args).cloned().collect()));
+    }
+    
+    if shell.options.preview && !confirm_preview(shell, &commands)? {
+        return Err(ShellError::CommandError("已取消执行".to_string()));
+    }
+    if shell.options.saferm {
+        for (cmd, globbed) in commands.iter().zip(globbed) {
+            if let Command::Simple(cmd) = cmd
+                && globbed
+                && cmd.program == "rm"
+                && !confirm_rm(shell, cmd)?
+            {
+                return Err(ShellError::CommandError("已取消执行".to_string()));
+            }
+        }
     }
     
     if commands.len() == 1 {
-        return execute_single_command(&commands[0]);
+        return execute_single_command(shell, &commands[0]);
     }
     
-    let mut previous_stdout = None;
+    let mut previous_reader: Option<PipeReader> = None;
     let mut processes = Vec::new();
+    let mut captures = Vec::new();
+    let mut status = 0;
     
     // 处理管道链中的所有命令，除了最后一个
     for (i, cmd) in commands.iter().enumerate() {
-        // 检查是否为内建命令，内建命令不支持管道（简化实现）
-        if execute_builtin(cmd)? {
-            return Err(ShellError::CommandError(
-                "内建命令不支持管道".to_string(),
-            ));
-        }
-        
         let is_last = i == commands.len() - 1;
         
-        let stdin = match previous_stdout {
-            Some(prev_out) => Stdio::from(prev_out),
-            None => Stdio::inherit(),
-        };
-        
-        let stdout = if is_last {
-            Stdio::inherit()
-        } else {
-            Stdio::piped()
-        };
-        
-        let mut process = ProcessCommand::new(&cmd.program)
-            .args(&cmd.args)
-            .stdin(stdin)
-            .stdout(stdout)
-            .spawn()
-            .map_err(|e| {
-                ShellError::CommandError(format!("无法执行命令 '{}': {}", cmd.program, e))
-            })?;
+        // 管道的两端先作为标准输入/输出，命令自己的重定向在其后生效，
+        // 因此 2>&1 会复制到管道上，> 文件 则取代管道
+        let mut pipes = Vec::new();
+        if let Some(reader) = previous_reader.take() {
+            pipes.push((libc::STDIN_FILENO, Some(File::from(OwnedFd::from(reader)))));
+        }
+        if !is_last {
+            let (reader, writer) = io::pipe()?;
+            pipes.push((libc::STDOUT_FILENO, Some(File::from(OwnedFd::from(writer)))));
+            previous_reader = Some(reader);
+        }
+        let files = open_redirects(pipes, cmd.redirects(), &CreateOptions::new(&shell.options, &shell.vars), &shell.stdio, &mut captures)?;
         
-        // 保存当前命令的stdout，用于下一个命令的stdin
-        previous_stdout = if !is_last {
-            Some(process.stdout.take().unwrap())
-        } else {
-            None
-        };
+        let pid = spawn_command(shell, cmd, &files)?;
+        // 关闭Shell持有的写端，读端才能在写入的命令结束后读到文件结尾
+        drop(files);
         
         if is_last {
-            // 等待最后一个进程完成
-            let status = process.wait()?;
-            if !status.success() {
-                return Err(ShellError::CommandError(format!(
-                    "命令 '{}' 退出，状态码: {}",
-                    cmd.program,
-                    status.code().unwrap_or(-1)
-                )));
-            }
+            // 等待最后一个进程完成，它的状态码是整个管道的状态码
+            let finished = wait_foreground(shell, pid)?;
+            status = command_status(shell, cmd, finished);
         } else {
-            processes.push(process);
+            processes.push(pid);
         }
     }
     
-    // 等待所有中间进程完成
-    for mut process in processes {
-        let status = process.wait()?;
-        if !status.success() {
-            return Err(ShellError::CommandError(
-                "管道中的命令失败".to_string(),
-            ));
-        }
+    // 等待所有中间进程完成，与 POSIX 一样它们的状态码不影响结果
+    for pid in processes {
+        wait_with_rusage(pid)?;
     }
     
-    Ok(())
+    finish_captures(captures, &mut shell.vars)?;
+    Ok(status)
 }
 
-// 执行单个命令（没有管道）
-fn execute_single_command(cmd: &Command) -> Result<(), ShellError> {
-    // 先尝试执行内建命令
-    if execute_builtin(cmd)? {
-        return Ok(());
+// set -o preview 时显示别名、变量、通配符等全部展开之后的命令，确认后才执行；
+// 直接回车表示执行，标准输入不是终端时不询问
+fn confirm_preview(shell: &Shell, commands: &[Command]) -> Result<bool, ShellError> {
+    if !stdin_is_terminal() {
+        return Ok(true);
+    }
+    let text: Vec<String> = commands.iter().map(Command::text).collect();
+    let mut err = shell.stdio.err();
+    writeln!(err, "预览: {}", text.join(" | "))?;
+    ask(&mut err, "执行? [Y/n] ", true)
+}
+
+// 命令的词中是否有未加引号的通配符
+fn has_unquoted_glob(cmd: &SimpleCommand) -> bool {
+    cmd.words.iter().any(|word| {
+        word.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Plain(s) if glob::has_wildcards(s)))
+    })
+}
+
+// set -o saferm 时，参数中有通配符的 rm 在展开出的文件数超过 SAFERM_THRESHOLD（默认 10）时
+// 列出这些文件并请求确认，默认不删除；标准输入不是终端时无法确认，直接拒绝
+fn confirm_rm(shell: &Shell, cmd: &SimpleCommand) -> Result<bool, ShellError> {
+    let threshold = shell
+        .vars
+        .get("SAFERM_THRESHOLD")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(10);
+    let files: Vec<&String> = cmd.args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    if files.len() <= threshold {
+        return Ok(true);
+    }
+    let mut err = shell.stdio.err();
+    if !stdin_is_terminal() {
+        writeln!(err, "rm: 通配符展开为 {} 个文件，超过 {}，需要在终端中确认", files.len(), threshold)?;
+        return Ok(false);
+    }
+
+    writeln!(err, "rm 将删除 {} 个文件:", files.len())?;
+    for file in files.iter().take(PREVIEW_FILES) {
+        writeln!(err, "  {}", file)?;
+    }
+    if files.len() > PREVIEW_FILES {
+        writeln!(err, "  …还有 {} 个", files.len() - PREVIEW_FILES)?;
+    }
+    ask(&mut err, &format!("确认删除这 {} 个文件? [y/N] ", files.len()), false)
+}
+
+// confirm_rm 最多列出的文件数
+const PREVIEW_FILES: usize = 20;
+
+fn stdin_is_terminal() -> bool {
+    // SAFETY: isatty 只查询描述符
+    unsafe { libc::isatty(libc::STDIN_FILENO) != 0 }
+}
+
+// 在终端上询问，直接回车时取 default，输入结束视为否
+fn ask(err: &mut impl Write, question: &str, default: bool) -> Result<bool, ShellError> {
+    write!(err, "{}", question)?;
+    let answer = match read_stdin_line()? {
+        Some(line) => String::from_utf8_lossy(&line).trim().to_lowercase(),
+        None => return Ok(false),
+    };
+    Ok(match answer.as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+// FOO=bar 内建命令：赋值在内建命令执行期间生效并导出，例如 HOME=/tmp cd，结束后恢复原来的变量
+// 外部命令的赋值由 execute_external 只加入子进程的环境变量
+fn with_assignments<T>(shell: &mut Shell, cmd: &SimpleCommand, f: impl FnOnce(&mut Shell) -> T) -> T {
+    if cmd.program.is_empty() || cmd.assignments.is_empty() || !is_builtin(shell, cmd) {
+        return f(shell);
+    }
+    let saved: Vec<_> = cmd
+        .assignments
+        .iter()
+        .map(|(name, _)| (name, shell.vars.get_variable(name).cloned()))
+        .collect();
+    for (name, value) in &cmd.assignments {
+        shell.vars.export(name, Some(&value.text()));
+    }
+    let result = f(shell);
+    // 倒序恢复，同一个变量赋值多次时最后恢复的是最初的值
+    for (name, var) in saved.into_iter().rev() {
+        shell.vars.restore_variable(name, var);
+    }
+    result
+}
+
+// 执行单个命令（没有管道），返回它的状态码
+pub fn execute_single_command(shell: &mut Shell, cmd: &Command) -> Result<i32, ShellError> {
+    let mut captures = Vec::new();
+    let files = open_redirects(Vec::new(), cmd.redirects(), &CreateOptions::new(&shell.options, &shell.vars), &shell.stdio, &mut captures)?;
+    let result = run_single_command(shell, cmd, files);
+    // 命令已经结束，捕获的写入端都已关闭
+    let captured = finish_captures(captures, &mut shell.vars);
+    result.and_then(|status| captured.map(|()| status))
+}
+
+fn run_single_command(shell: &mut Shell, cmd: &Command, files: Vec<OpenRedirect>) -> Result<i32, ShellError> {
+    // 先尝试执行内建命令和 { ...; }，执行期间标准描述符指向重定向的文件
+    match cmd {
+        Command::Compound(CompoundCommand {
+            group: Group::Brace(lists),
+            ..
+        }) => {
+            let guard = FdGuard::apply(&files, &shell.stdio)?;
+            let result = execute_command(shell, lists.clone());
+            drop(guard);
+            return result;
+        }
+        Command::Compound(_) => {}
+        // exec 的重定向在命令结束后不恢复
+        Command::Simple(cmd) if cmd.program == "exec" => {
+            return with_assignments(shell, cmd, |shell| run_exec(shell, cmd, &files))
+        }
+        Command::Simple(cmd) => {
+            let guard = FdGuard::apply(&files, &shell.stdio)?;
+            let builtin = with_assignments(shell, cmd, |shell| execute_builtin(shell, cmd));
+            drop(guard);
+            if CHANGES_PATH.contains(&cmd.program.as_str()) {
+                shell.refresh_path_cache();
+            }
+            if let Some(status) = builtin? {
+                return Ok(status);
+            }
+        }
     }
     
-    // 执行外部命令
-    let mut child = execute_external(cmd)?;
+    // 执行外部命令或子Shell
+    let pid = spawn_command(shell, cmd, &files)?;
+    drop(files);
     
     // 等待命令完成
-    let status = child.wait()?;
-    if !status.success() {
-        return Err(ShellError::CommandError(format!(
-            "命令 '{}' 退出，状态码: {}",
-            cmd.program,
-            status.code().unwrap_or(-1)
-        )));
+    let status = wait_foreground(shell, pid)?;
+    Ok(command_status(shell, cmd, status))
+}
+
+// 等待前台命令结束并记录资源使用情况，开启 rusage 选项时打印出来
+fn wait_foreground(shell: &mut Shell, pid: libc::pid_t) -> Result<ExitStatus, ShellError> {
+    let (status, usage) = wait_with_rusage(pid)?;
+    if shell.options.rusage {
+        writeln!(shell.stdio.err(), "{}", usage)?;
     }
+    shell.last_rusage = Some(usage);
+    Ok(status)
+}
+
+// 内建命令 time：time [-v] 命令 [参数...]
+// 打印耗时，-v 时额外打印最大常驻内存和缺页次数；状态码是命令的状态码
+fn run_time(shell: &mut Shell, args: &[String]) -> Result<i32, ShellError> {
+    let (verbose, args) = match args.first() {
+        Some(flag) if flag == "-v" => (true, &args[1..]),
+        _ => (false, args),
+    };
+    
+    let inner = match args.split_first() {
+        Some((program, rest)) => Command::Simple(SimpleCommand {
+            program: program.clone(),
+            args: rest.to_vec(),
+            ..SimpleCommand::default()
+        }),
+        None => return Err(ShellError::CommandError("用法: time [-v] 命令 [参数...]".to_string())),
+    };
     
+    shell.last_rusage = None;
+    let start = Instant::now();
+    let result = execute_single_command(shell, &inner);
+    let elapsed = start.elapsed();
+    
+    let mut err = shell.stdio.err();
+    writeln!(err, "real {:.3}s", elapsed.as_secs_f64())?;
+    if let Some(usage) = shell.last_rusage {
+        if verbose {
+            writeln!(err, "{}", usage)?;
+        } else {
+            writeln!(err, "user {:.3}s", usage.user.as_secs_f64())?;
+            writeln!(err, "sys  {:.3}s", usage.system.as_secs_f64())?;
+        }
+    }
+    
+    result
+}
+
+// 执行 && / || 列表：&& 在前一个管道成功时、|| 在失败时才执行后一个管道，
+// 跳过的管道不改变结果，返回最后一个执行的管道的结果；出错的管道视为失败
+// 与 POSIX 一样，结果不是来自最后一个管道时 errexit 不起作用，例如 false && true
+fn execute_and_or(shell: &mut Shell, list: AndOrList) -> Result<i32, ShellError> {
+    let last = list.pipelines.len().saturating_sub(1);
+    let mut pipelines = list.pipelines.into_iter().enumerate();
+    shell.errexit_exempt = last > 0;
+    let mut result = match pipelines.next() {
+        Some((_, pipeline)) => execute_piped_commands(shell, pipeline),
+        None => return Ok(0),
+    };
+    let mut ran_last = last == 0;
+    
+    for (connector, (i, pipeline)) in list.connectors.into_iter().zip(pipelines) {
+        // exit 之后的命令都不再执行
+        if let Err(ShellError::Exit(_)) = result {
+            return result;
+        }
+        let succeeded = matches!(result, Ok(0));
+        ran_last = match connector {
+            Connector::And => succeeded,
+            Connector::Or => !succeeded,
+        };
+        if ran_last {
+            record_result(shell, result);
+            shell.errexit_exempt = i < last;
+            result = execute_piped_commands(shell, pipeline);
+        }
+    }
+    
+    // 最后一个管道执行时保留它设置的值：{ false && true; } 的失败同样不结束执行；
+    // 没有执行时结果来自前面的管道，其中嵌套的列表可能已经改变了这个值
+    if !ran_last {
+        shell.errexit_exempt = true;
+    }
+    result
+}
+
+// 在后台子Shell中执行列表，不等待它结束；打印作业编号和进程号并登记到作业表
+// 作业写到终端的输出记录在作业表中，用 jobs --tail 查看；set -o bg-prefix 时还加上前缀打印出来
+fn execute_background(shell: &mut Shell, list: AndOrList) -> Result<(), ShellError> {
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    
+    let output = output_file(&shell.stdio)?;
+    let forward = match &output {
+        Some(output) if shell.options.bg_prefix => Some((io::pipe()?, output.try_clone()?)),
+        _ => None,
+    };
+    let pid = fork_background()?;
+    let text = list.text();
+    if pid == 0 {
+        let _ = shell.stdio.install();
+        match (&forward, &output) {
+            (Some(((_, writer), _)), _) => redirect_to_output(writer),
+            (None, Some(output)) => redirect_to_output(output),
+            (None, None) => {}
+        }
+        exit_child(shell, vec![AndOrList { background: false, ..list }]);
+    }
+    
+    let id = shell.jobs.add(pid, text, output);
+    if let Some(((reader, writer), output)) = forward {
+        drop(writer);
+        console::forward(reader, format!("[job {}] ", id), Some(output));
+    }
+    writeln!(shell.stdio.err(), "[{}] {}", id, pid)?;
     Ok(())
 }
 
-// 公共API：执行命令（支持管道）
-pub fn execute_command(commands: Vec<Command>) -> Result<(), ShellError> {
-    execute_piped_commands(commands)
+// 记录中间结果的状态码，出错时打印错误
+fn record_result(shell: &mut Shell, result: Result<i32, ShellError>) {
+    match result {
+        Ok(status) => shell.last_status = status,
+        Err(e) => {
+            diagnostic::report(&mut shell.stdio.err(), &e);
+            shell.last_status = e.status();
+        }
+    }
+}
+
+// 公共API：依次执行用 ; 或 & 分隔的列表，某个列表出错时打印错误并继续执行后面的，
+// 每个列表结束后记录状态码供 $? 使用，返回最后一个列表的结果；exit 时立即返回 ShellError::Exit
+pub fn execute_command(shell: &mut Shell, lists: CompoundList) -> Result<i32, ShellError> {
+    let count = lists.len();
+    for (i, list) in lists.into_iter().enumerate() {
+        // set -n 之后的命令只解析不执行
+        if shell.options.noexec {
+            return Ok(shell.last_status);
+        }
+        let result = if list.background {
+            execute_background(shell, list).map(|()| 0)
+        } else {
+            execute_and_or(shell, list)
+        };
+        if let Ok(status) | Err(ShellError::Exit(status)) = result {
+            shell.last_status = status;
+        }
+        if i + 1 == count || matches!(result, Err(ShellError::Exit(_))) {
+            return result;
+        }
+        record_result(shell, result);
+        // errexit：&& 和 || 列表之外的命令（或列表中最后一个管道）失败时不再执行之后的列表
+        if shell.options.errexit && shell.last_status != 0 && !shell.errexit_exempt {
+            return Ok(shell.last_status);
+        }
+    }
+    Ok(0)
 }
@@ -0,0 +1,374 @@
+use crate::command::BUILTINS;
+use crate::console;
+use crate::error::ShellError;
+use crate::parser::{has_unclosed_quote, tokenize, Token};
+use crate::pathcache::SharedPathCache;
+use crate::style::{self, Role, Stream};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{CompletionType, Context, Helper};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::process::Command as ProcessCommand;
+use std::rc::Rc;
+
+// 插件提供的补全：参数是命令名和正在输入的词，返回补全词
+pub type Provider = Box<dyn Fn(&str, &str) -> Vec<String>>;
+
+// 补全注册表：为每个命令记录可补全的选项
+#[derive(Default)]
+pub struct CompletionRegistry {
+    rules: BTreeMap<String, BTreeSet<String>>,
+    // 插件注册的补全，每次补全参数时都询问
+    providers: Vec<Provider>,
+}
+
+impl fmt::Debug for CompletionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompletionRegistry")
+            .field("rules", &self.rules)
+            .field("providers", &self.providers.len())
+            .finish()
+    }
+}
+
+impl CompletionRegistry {
+    // 为命令添加补全词
+    pub fn add<I: IntoIterator<Item = String>>(&mut self, command: &str, words: I) {
+        self.rules.entry(command.to_string()).or_default().extend(words);
+    }
+
+    // 遍历全部规则
+    pub fn rules(&self) -> impl Iterator<Item = (&String, &BTreeSet<String>)> {
+        self.rules.iter()
+    }
+
+    // 注册插件提供的补全
+    pub fn add_provider(&mut self, provider: Provider) {
+        self.providers.push(provider);
+    }
+
+    // 返回命令中以 prefix 开头的补全词，包括插件提供的
+    pub fn candidates(&self, command: &str, prefix: &str) -> Vec<String> {
+        let mut words: Vec<String> = match self.rules.get(command) {
+            Some(words) => words.iter().filter(|w| w.starts_with(prefix)).cloned().collect(),
+            None => Vec::new(),
+        };
+        for provider in &self.providers {
+            words.extend(provider(command, prefix).into_iter().filter(|w| w.starts_with(prefix)));
+        }
+        words.sort();
+        words.dedup();
+        words
+    }
+}
+
+// 在Shell与行编辑器之间共享的注册表
+pub type SharedRegistry = Rc<RefCell<CompletionRegistry>>;
+
+// 行编辑器的辅助对象：命令名补全为内建命令和 PATH 中的程序，
+// 参数优先使用注册表中的规则，否则补全文件名
+pub struct ShellHelper {
+    registry: SharedRegistry,
+    path_cache: SharedPathCache,
+    filename: FilenameCompleter,
+}
+
+impl ShellHelper {
+    pub fn new(registry: SharedRegistry, path_cache: SharedPathCache) -> Self {
+        ShellHelper {
+            registry,
+            path_cache,
+            filename: FilenameCompleter::new(),
+        }
+    }
+
+    // 以 prefix 开头的内建命令和 PATH 中的程序
+    fn command_names(&self, prefix: &str) -> Vec<String> {
+        let mut names = self.path_cache.borrow_mut().names_with_prefix(prefix);
+        names.extend(BUILTINS.iter().filter(|name| name.starts_with(prefix)).map(|name| name.to_string()));
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        // 空白可能是多字节字符（如全角空格），词从它之后的字符边界开始
+        let start = before
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &before[start..];
+
+        // 当前管道段的命令名；正在输入命令名时补全内建命令和 PATH 中的程序，含有 / 时是路径，补全文件名
+        let segment = before[..start].rsplit('|').next().unwrap_or("");
+        let words = match segment.split_whitespace().next() {
+            Some(command) => self.registry.borrow().candidates(command, word),
+            None if !word.is_empty() && !word.contains('/') => self.command_names(word),
+            None => Vec::new(),
+        };
+        if !words.is_empty() {
+            let pairs = words
+                .into_iter()
+                .map(|w| Pair {
+                    display: w.clone(),
+                    replacement: w,
+                })
+                .collect();
+            return Ok((start, pairs));
+        }
+
+        self.filename.complete(line, pos, ctx)
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+// 提示符和补全列表的颜色由 style 模块决定，终端不支持颜色时不变
+// 不改变输入的显示，只把正在编辑的内容告诉 console，使其他线程的输出可以重新画出输入行
+impl Highlighter for ShellHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        console::record_line(line, pos);
+        Cow::Borrowed(line)
+    }
+
+    // 插入单个字符时行编辑器不重绘整行，也不调用 highlight
+    fn highlight_char(&self, line: &str, pos: usize) -> bool {
+        console::record_line(line, pos);
+        false
+    }
+
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        style::prompt(prompt)
+    }
+
+    fn highlight_candidate<'c>(&self, candidate: &'c str, _completion: CompletionType) -> Cow<'c, str> {
+        if candidate.ends_with('/') {
+            style::paint(candidate, Role::Directory, Stream::Stdout)
+        } else {
+            Cow::Borrowed(candidate)
+        }
+    }
+}
+
+// 引号没有闭合时按回车只换行，可以接着输入同一条命令
+impl Validator for ShellHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if has_unclosed_quote(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ShellHelper {}
+
+// 内建命令 compgen-from：compgen-from --help <命令>
+// 运行 `<命令> --help`，从帮助文本中提取选项作为该命令的补全规则
+pub fn run_compgen_from(out: &mut impl Write, registry: &mut CompletionRegistry, args: &[String]) -> Result<(), ShellError> {
+    let command = match args {
+        [flag, command] if flag == "--help" => command,
+        _ => return Err(ShellError::CommandError("用法: compgen-from --help <命令>".to_string())),
+    };
+
+    let output = ProcessCommand::new(command)
+        .arg("--help")
+        .output()
+        .map_err(|e| ShellError::SpawnError(command.clone(), e))?;
+
+    // 有些程序把帮助信息写到标准错误
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    let options = parse_help_options(&text);
+    if options.is_empty() {
+        return Err(ShellError::CommandError(format!("未能从 '{} --help' 中找到选项", command)));
+    }
+
+    writeln!(out, "已为 '{}' 添加 {} 条补全规则", command, options.len())?;
+    registry.add(command, options);
+    Ok(())
+}
+
+// 从帮助文本中提取选项：只看以 '-' 开头的行，描述部分（两个以上空格之后）忽略
+fn parse_help_options(text: &str) -> BTreeSet<String> {
+    let mut options = BTreeSet::new();
+
+    for line in text.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('-') {
+            continue;
+        }
+
+        let spec = match line.find("  ").into_iter().chain(line.find('\t')).min() {
+            Some(end) => &line[..end],
+            None => line,
+        };
+
+        for piece in spec.split(|c: char| c == ',' || c.is_whitespace()) {
+            let option = piece.split(['=', '[']).next().unwrap_or("");
+            let valid = option.starts_with('-')
+                && option.trim_start_matches('-').chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+                && option.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if valid {
+                options.insert(option.to_string());
+            }
+        }
+    }
+
+    options
+}
+
+// 内建命令 complete：complete -W "词 ..." 命令 ...
+// 兼容bash的写法，但只支持 -W 词表；不带参数时列出全部规则
+pub fn run_complete(out: &mut impl Write, registry: &mut CompletionRegistry, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        for (command, words) in registry.rules() {
+            let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+            writeln!(out, "complete -W \"{}\" {}", words.join(" "), command)?;
+        }
+        return Ok(());
+    }
+
+    match parse_complete_args(args)? {
+        Some(spec) => {
+            for command in spec.commands {
+                registry.add(&command, spec.words.iter().cloned());
+            }
+            Ok(())
+        }
+        None => Err(ShellError::CommandError("complete: 只支持 -W 词表形式".to_string())),
+    }
+}
+
+// 内建命令 complete-import：从文件中导入bash的 complete -W 定义，其余形式跳过，无法解析的行同时给出警告
+pub fn run_complete_import(
+    out: &mut impl Write,
+    err: &mut impl Write,
+    registry: &mut CompletionRegistry,
+    args: &[String],
+) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("用法: complete-import <文件> ...".to_string()));
+    }
+
+    for path in args {
+        let text = fs::read_to_string(path)?;
+        let mut imported = 0;
+        let mut skipped = 0;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if !line.starts_with("complete ") {
+                continue;
+            }
+
+            // 无法解析的行（例如引号没有闭合）跳过，文件中其余的定义照常导入
+            let tokens = match tokenize(line) {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    writeln!(err, "complete-import: {}:{}: {}", path, number + 1, e)?;
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let mut words = Vec::new();
+            let mut simple = true;
+            for token in tokens {
+                match token {
+                    Token::Word(word) => words.push(word.text()),
+                    _ => simple = false,
+                }
+            }
+
+            match parse_complete_args(&words[1..]) {
+                Ok(Some(spec)) if simple => {
+                    for command in spec.commands {
+                        registry.add(&command, spec.words.iter().cloned());
+                    }
+                    imported += 1;
+                }
+                _ => skipped += 1,
+            }
+        }
+
+        writeln!(out, "{}: 导入 {} 条，跳过 {} 条", path, imported, skipped)?;
+    }
+
+    Ok(())
+}
+
+// 一条 complete -W 定义
+struct CompleteSpec {
+    commands: Vec<String>,
+    words: Vec<String>,
+}
+
+// 解析 complete 的参数；没有 -W 时返回 None
+fn parse_complete_args(args: &[String]) -> Result<Option<CompleteSpec>, ShellError> {
+    let mut words = None;
+    let mut commands = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-W" => {
+                let list = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("complete: -W 需要参数".to_string()))?;
+                words = Some(list.split_whitespace().map(|w| w.to_string()).collect::<Vec<_>>());
+            }
+            // 带参数的选项，参数一并跳过
+            "-F" | "-C" | "-G" | "-X" | "-P" | "-S" | "-o" | "-A" => {
+                iter.next();
+            }
+            // -p/-r 是打印和删除操作，不是定义
+            "-p" | "-r" => return Ok(None),
+            flag if flag.starts_with('-') => {}
+            command => commands.push(command.to_string()),
+        }
+    }
+
+    match words {
+        Some(words) if !commands.is_empty() => Ok(Some(CompleteSpec { commands, words })),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_path;
+
+    #[test]
+    fn complete_import_skips_unparsable_lines() {
+        let path = temp_path("completion-import");
+        fs::write(&path, "complete -W \"start stop\" svc\ncomplete -W \"a b svc2\ncomplete -W \"x y\" tool\n").unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let mut registry = CompletionRegistry::default();
+        let path_text = path.display().to_string();
+        let result = run_complete_import(&mut out, &mut err, &mut registry, std::slice::from_ref(&path_text));
+        fs::remove_file(&path).unwrap();
+
+        result.unwrap();
+        let commands: Vec<&String> = registry.rules().map(|(command, _)| command).collect();
+        assert_eq!(commands, ["svc", "tool"]);
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}: 导入 2 条，跳过 1 条\n", path_text));
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.starts_with(&format!("complete-import: {}:2: ", path_text)), "stderr: {:?}", err);
+    }
+}
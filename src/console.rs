@@ -0,0 +1,177 @@
+use crate::stdio::Stdio;
+use crate::style;
+use crate::terminal::window_size;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, PipeReader, StderrLock, StdoutLock, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+// 行编辑器的状态：是否正在等待输入，提示符，已经输入的内容和光标位置（字节偏移）
+struct EditState {
+    active: bool,
+    prompt: String,
+    line: String,
+    pos: usize,
+}
+
+static STATE: Mutex<EditState> = Mutex::new(EditState {
+    active: false,
+    prompt: String::new(),
+    line: String::new(),
+    pos: 0,
+});
+
+// Shell的标准输出或标准错误是否连接到终端，后台进程写到这里的输出可能打乱正在编辑的行
+pub fn writes_to_terminal(stdio: &Stdio) -> bool {
+    // SAFETY: isatty 只查询描述符
+    unsafe { libc::isatty(stdio.out) != 0 || libc::isatty(stdio.err) != 0 }
+}
+
+// 开始读取一行输入，initial 是预先填入的内容
+pub fn begin_edit(prompt: &str, initial: &str) {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    state.active = true;
+    state.prompt = prompt.to_string();
+    state.line = initial.to_string();
+    state.pos = initial.len();
+}
+
+pub fn end_edit() {
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).active = false;
+}
+
+// 行编辑器重绘或插入字符时记录正在编辑的内容
+pub fn record_line(line: &str, pos: usize) {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.line != line {
+        state.line = line.to_string();
+    }
+    state.pos = pos;
+}
+
+// 在其他线程中打印一行消息：正在等待输入时先擦掉提示符和输入的内容，
+// 打印消息后重新画出它们，并把光标放回原来的位置；否则直接打印
+pub fn print_line(message: &str) {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.active {
+        redraw_with(&state, message);
+    } else {
+        let mut out = io::stdout().lock();
+        let _ = writeln!(out, "{}", message);
+        let _ = out.flush();
+    }
+}
+
+// 只在等待输入时打印消息，并在打印之前设置 printed；
+// 用于作业结束等通知，不在等待输入时由主线程在下一次出现提示符之前报告
+pub fn print_while_editing(message: &str, printed: &AtomicBool) {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.active {
+        printed.store(true, Ordering::Relaxed);
+        redraw_with(&state, message);
+    }
+}
+
+// 持有输出线程会用到的全部锁：编辑状态、标准输出和标准错误，加锁顺序与 print_line 相同
+pub struct OutputLock {
+    _state: MutexGuard<'static, EditState>,
+    _stdout: StdoutLock<'static>,
+    _stderr: StderrLock<'static>,
+}
+
+// fork 之前调用，直到子进程创建之后才释放：其他线程此时不可能持有这些锁，
+// 子进程不会继承一个永远不会被释放的锁
+pub fn lock_output() -> OutputLock {
+    OutputLock {
+        _state: STATE.lock().unwrap_or_else(|e| e.into_inner()),
+        _stdout: io::stdout().lock(),
+        _stderr: io::stderr().lock(),
+    }
+}
+
+// 把管道中的输出逐行加上前缀打印出来，tee 不为空时同时原样写入其中；
+// 写入端全部关闭时线程退出
+pub fn forward(reader: PipeReader, prefix: String, mut tee: Option<File>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+            if let Some(tee) = &mut tee {
+                let _ = tee.write_all(&line);
+            }
+            let text = String::from_utf8_lossy(&line);
+            print_line(&format!("{}{}", prefix, text.trim_end_matches(['\n', '\r'])));
+            line.clear();
+        }
+    });
+}
+
+// 擦掉提示符和输入的内容，打印消息后重新画出它们
+fn redraw_with(state: &EditState, message: &str) {
+    let mut out = io::stdout().lock();
+
+    let columns = window_size().map_or(80, |(columns, _)| columns as usize);
+    let before = format!("{}{}", state.prompt, state.line.get(..state.pos).unwrap_or(&state.line));
+    let (row, column) = layout(&before, columns);
+
+    let mut screen = String::new();
+    // 回到提示符所在的第一行，擦掉之后的全部内容
+    if row > 0 {
+        screen.push_str(&format!("\x1b[{}A", row));
+    }
+    screen.push_str("\r\x1b[J");
+    screen.push_str(&message.replace('\n', "\r\n"));
+    screen.push_str("\r\n");
+
+    let full = format!("{}{}", state.prompt, state.line);
+    let (end_row, end_column) = layout(&full, columns);
+    screen.push_str(&style::prompt(&state.prompt));
+    screen.push_str(&state.line.replace('\n', "\r\n"));
+    // 恰好写满最后一列时光标还停在这一行，换到下一行使位置和计算的一致
+    if end_column == 0 && !full.is_empty() && !full.ends_with('\n') {
+        screen.push_str("\r\n");
+    }
+    if end_row > row {
+        screen.push_str(&format!("\x1b[{}A", end_row - row));
+    }
+    screen.push('\r');
+    if column > 0 {
+        screen.push_str(&format!("\x1b[{}C", column));
+    }
+    let _ = out.write_all(screen.as_bytes());
+    let _ = out.flush();
+}
+
+// 从第一行的开头写出 text 之后光标所在的行和列（从 0 数），超过终端宽度的行自动折行
+fn layout(text: &str, columns: usize) -> (usize, usize) {
+    let columns = columns.max(1);
+    let mut row = 0;
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let width = display_width(line);
+        if lines.peek().is_none() {
+            return (row + width / columns, width % columns);
+        }
+        row += width.saturating_sub(1) / columns + 1;
+    }
+    (row, 0)
+}
+
+// 文本在终端上占的列数：中日韩等宽字符按两列计算，忽略 ESC [ ... m 形式的颜色代码
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        width += if c as u32 >= 0x1100 { 2 } else { 1 };
+    }
+    width
+}
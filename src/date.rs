@@ -0,0 +1,81 @@
+use crate::error::ShellError;
+use std::ffi::CString;
+use std::io::Write;
+
+// 与 coreutils date 相同的默认格式
+const DEFAULT_FORMAT: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+// 格式化结果的最大长度，防止异常的格式无限扩大缓冲区
+const MAX_OUTPUT: usize = 64 * 1024;
+
+// 内建命令 date：date [-u] [-d @秒数] [+格式]
+// 格式使用 strftime 的转换说明，例如 date +%Y-%m-%d；-u 使用UTC
+pub fn run_date(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut utc = false;
+    let mut time = None;
+    let mut format = DEFAULT_FORMAT;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-u" => utc = true,
+            "-d" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("date: -d 需要参数".to_string()))?;
+                let seconds = value
+                    .strip_prefix('@')
+                    .and_then(|s| s.parse::<libc::time_t>().ok())
+                    .ok_or_else(|| ShellError::CommandError(format!("date: 只支持 @秒数 形式的时间 '{}'", value)))?;
+                time = Some(seconds);
+            }
+            _ => match arg.strip_prefix('+') {
+                Some(f) => format = f,
+                None => return Err(ShellError::CommandError("用法: date [-u] [-d @秒数] [+格式]".to_string())),
+            },
+        }
+    }
+
+    // SAFETY: 传入空指针时 time 只返回当前时间
+    let time = time.unwrap_or_else(|| unsafe { libc::time(std::ptr::null_mut()) });
+    writeln!(out, "{}", format_time(time, format, utc)?)?;
+    Ok(())
+}
+
+// 用 strftime 格式化时间戳，utc 为 false 时使用本地时区
+pub fn format_time(time: libc::time_t, format: &str, utc: bool) -> Result<String, ShellError> {
+    if format.is_empty() {
+        return Ok(String::new());
+    }
+    let c_format = CString::new(format).map_err(|_| ShellError::CommandError("date: 格式中不能包含空字符".to_string()))?;
+
+    // SAFETY: tm 是普通的C结构体，由 localtime_r/gmtime_r 填充
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        let result = if utc {
+            libc::gmtime_r(&time, &mut tm)
+        } else {
+            libc::localtime_r(&time, &mut tm)
+        };
+        if result.is_null() {
+            return Err(ShellError::CommandError(format!("date: 无效的时间 {}", time)));
+        }
+        tm
+    };
+
+    // strftime 在缓冲区不够时返回 0，逐步扩大缓冲区重试
+    let mut size = 256;
+    while size <= MAX_OUTPUT {
+        let mut buf = vec![0u8; size];
+        // SAFETY: buf 有 size 个字节，strftime 最多写入 size 个字节（含结尾的空字符）
+        let len = unsafe { libc::strftime(buf.as_mut_ptr().cast(), size, c_format.as_ptr(), &tm) };
+        if len > 0 {
+            buf.truncate(len);
+            return Ok(String::from_utf8_lossy(&buf).into_owned());
+        }
+        size *= 4;
+    }
+
+    // 例如格式只有 %p 而当前区域设置中它为空
+    Ok(String::new())
+}
@@ -0,0 +1,198 @@
+use crate::command::BUILTINS;
+use crate::error::{ShellError, Span};
+use crate::stdio::FdWriter;
+use crate::style::{self, Role, Stream};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    // 用户自己取消等不算失败的情况
+    Warning,
+}
+
+// 打印到标准错误的一条诊断信息：严重程度、信息、出错的命令和提示
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    // 出错的命令和它的来源，例如 ("命令", "cd /x") 或 ("钩子", ...)
+    pub command: Option<(&'static str, String)>,
+    // 解析错误在命令中的位置：从命令文本开头算起的列和宽度，显示为命令下面的 ^
+    pub caret: Option<(usize, usize)>,
+    pub hints: Vec<String>,
+    // 解析错误在原始输入中的位置
+    span: Option<Span>,
+}
+
+impl Diagnostic {
+    // 根据错误生成诊断信息，按错误的内容附上提示
+    pub fn from_error(error: &ShellError) -> Self {
+        let severity = match error {
+            ShellError::CommandError(message) if message == "已取消执行" => Severity::Warning,
+            _ => Severity::Error,
+        };
+        Diagnostic {
+            severity,
+            message: error.to_string(),
+            command: None,
+            caret: None,
+            hints: hints(error),
+            span: error.span(),
+        }
+    }
+
+    // 附上出错的命令文本；origin 说明它从哪里来，例如 "命令" 或 "钩子"
+    pub fn command(mut self, origin: &'static str, text: &str) -> Self {
+        let text = text.trim();
+        if text.is_empty() {
+            return self;
+        }
+        if (text == "cd" || text.starts_with("cd ")) && self.message.contains("os error 2") {
+            self.hints = vec!["目录不存在；按 Ctrl-P 可以从最近进入的目录和书签中选择".to_string()];
+        }
+        self.command = Some((origin, text.to_string()));
+        self
+    }
+
+    // 附上解析出错的输入；错误带有位置时只显示出错的一行，并在下面标出位置
+    pub fn source(self, origin: &'static str, input: &str) -> Self {
+        let Some(span) = self.span.filter(|span| span.start <= input.len()) else {
+            return self.command(origin, input);
+        };
+        let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[span.start..].find('\n').map_or(input.len(), |i| span.start + i);
+        let line = &input[line_start..line_end];
+        let indent = line.len() - line.trim_start().len();
+
+        let mut diagnostic = self.command(origin, line);
+        if diagnostic.command.is_some() {
+            let column = display_width(&input[line_start + indent..span.start]);
+            let width = display_width(&input[span.start..span.end.clamp(span.start, line_end)]);
+            diagnostic.caret = Some((column, width.max(1)));
+        }
+        diagnostic
+    }
+
+    // 加颜色后的文本，每行以换行结尾；输出到的 stream 不是终端时不加颜色
+    pub fn render(&self, stream: Stream) -> String {
+        let (label, role) = match self.severity {
+            Severity::Error => ("错误:", Role::Error),
+            Severity::Warning => ("警告:", Role::Warning),
+        };
+        let mut text = format!("{} {}\n", style::paint(label, role, stream), self.message);
+        if let Some((origin, command)) = &self.command {
+            text.push_str(&format!("  {}: {}\n", origin, command));
+            if let Some((column, width)) = self.caret {
+                // 对齐到 "  来源: " 之后
+                let indent = display_width(origin) + 4 + column;
+                let caret = "^".repeat(width);
+                text.push_str(&format!("{}{}\n", " ".repeat(indent), style::paint(&caret, Role::Error, stream)));
+            }
+        }
+        for hint in &self.hints {
+            text.push_str(&format!("  {} {}\n", style::paint("提示:", Role::Hint, stream), hint));
+        }
+        text
+    }
+
+    // 写到Shell的标准错误 err
+    pub fn emit(&self, err: &mut FdWriter) {
+        let _ = err.write_all(self.render(Stream::Fd(err.fd())).as_bytes());
+    }
+}
+
+// 打印错误，不带命令文本
+// exit 不是错误，不打印；例如子Shell中的 exit 只决定子Shell的退出码
+pub fn report(err: &mut FdWriter, error: &ShellError) {
+    if !matches!(error, ShellError::Exit(_)) {
+        Diagnostic::from_error(error).emit(err);
+    }
+}
+
+// 打印错误和出错的命令
+pub fn report_command(err: &mut FdWriter, error: &ShellError, command: &str) {
+    if !matches!(error, ShellError::Exit(_)) {
+        Diagnostic::from_error(error).command("命令", command).emit(err);
+    }
+}
+
+// 打印解析错误，在出错的命令下面标出出错的位置
+pub fn report_parse(err: &mut FdWriter, error: &ShellError, input: &str) {
+    Diagnostic::from_error(error).source("命令", input).emit(err);
+}
+
+// 文本在终端上占的列数；中日韩等宽字符按两列计算
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c as u32 >= 0x1100 { 2 } else { 1 }).sum()
+}
+
+// 按错误信息给出的提示，没有合适的提示时为空
+fn hints(error: &ShellError) -> Vec<String> {
+    let mut hints = Vec::new();
+    match error {
+        ShellError::CommandError(_) | ShellError::Exit(_) => {}
+        ShellError::SpawnError(program, e) => hints.extend(spawn_hint(program, e)),
+        ShellError::ParseError(message, _) | ShellError::Incomplete(message, _) if message.starts_with("未闭合的") => {
+            hints.push("检查引号和括号是否成对；要按字面使用这些字符，在前面加反斜杠或放进单引号中".to_string());
+        }
+        ShellError::ParseError(message, _) => {
+            if message.contains("'|'") {
+                hints.push("管道把前一个命令的输出交给后一个命令，两边都要有命令；tutor pipes 有练习".to_string());
+            }
+        }
+        ShellError::Incomplete(message, _) if message.contains('\\') => {
+            hints.push("要使用反斜杠本身，写成 \\\\ 或放进单引号中".to_string());
+        }
+        ShellError::Incomplete(..) => hints.push("交互时可以在下一行接着输入".to_string()),
+        ShellError::Io(e) => match e.kind() {
+            io::ErrorKind::NotFound => hints.push("文件或目录不存在，检查路径的拼写".to_string()),
+            io::ErrorKind::PermissionDenied => hints.push("没有权限，用 ls -l 查看文件的权限".to_string()),
+            _ => {}
+        },
+    }
+    hints
+}
+
+// 启动外部命令失败时的提示
+fn spawn_hint(program: &str, error: &io::Error) -> Option<String> {
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        return Some(format!("没有执行 '{}' 的权限；脚本需要可执行权限，可以用 chmod +x 添加", program));
+    }
+    if error.kind() != io::ErrorKind::NotFound {
+        return None;
+    }
+    if program.contains('/') {
+        return Some(format!("文件 '{}' 不存在", program));
+    }
+    match similar_builtin(program) {
+        Some(builtin) => Some(format!("你是不是想输入 '{}'?", builtin)),
+        None => Some(format!("找不到命令 '{}'，检查拼写或 PATH 中是否包含它所在的目录", program)),
+    }
+}
+
+// 拼写最接近的内建命令，编辑距离不超过 2，也不超过名字长度的一半
+fn similar_builtin(program: &str) -> Option<&'static str> {
+    let limit = 2.min(program.chars().count() / 2);
+    BUILTINS
+        .iter()
+        .map(|&builtin| (edit_distance(program, builtin), builtin))
+        .filter(|&(distance, _)| distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, builtin)| builtin)
+}
+
+// 两个字符串之间的编辑距离（插入、删除、替换各算一次）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,167 @@
+use crate::error::ShellError;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+// 内建命令 dsize：dsize [路径...]，与 du -sh 类似，输出每个路径占用的磁盘空间
+// 不跟随符号链接，硬链接只计算一次；没有路径时统计当前目录
+pub fn run_dsize(out: &mut impl Write, err: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let paths: Vec<&str> = if args.is_empty() {
+        vec!["."]
+    } else {
+        args.iter().map(String::as_str).collect()
+    };
+
+    let mut failed = false;
+    for path in paths {
+        let mut seen = HashSet::new();
+        match disk_usage(err, Path::new(path), &mut seen, &mut failed) {
+            Ok(bytes) => writeln!(out, "{:>7}  {}", format_size(bytes), path)?,
+            Err(e) => {
+                writeln!(err, "dsize: '{}': {}", path, e)?;
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return Err(ShellError::CommandError("dsize: 部分文件无法读取，结果可能偏小".to_string()));
+    }
+    Ok(())
+}
+
+// 递归统计占用的块数；目录中个别条目无法读取时打印错误并继续
+fn disk_usage(err: &mut impl Write, path: &Path, seen: &mut HashSet<(u64, u64)>, failed: &mut bool) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.nlink() > 1 && !meta.is_dir() && !seen.insert((meta.dev(), meta.ino())) {
+        return Ok(0);
+    }
+
+    // st_blocks 的单位固定是 512 字节
+    let mut total = meta.blocks() * 512;
+    if meta.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let child = entry?.path();
+            match disk_usage(err, &child, seen, failed) {
+                Ok(bytes) => total += bytes,
+                Err(e) => {
+                    writeln!(err, "dsize: '{}': {}", child.display(), e)?;
+                    *failed = true;
+                }
+            }
+        }
+    }
+    Ok(total)
+}
+
+// 内建命令 dfree：dfree [路径...]，与 df -h 类似，输出文件系统的容量和使用情况
+// 没有路径时列出 /proc/mounts 中容量不为零的全部文件系统
+pub fn run_dfree(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let targets: Vec<(String, String)> = if args.is_empty() {
+        let mounts = fs::read_to_string("/proc/mounts")
+            .map_err(|e| ShellError::CommandError(format!("dfree: 无法读取 /proc/mounts: {}", e)))?;
+        mounts
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let device = fields.next()?;
+                let mount_point = fields.next()?;
+                Some((device.to_string(), unescape_mount(mount_point)))
+            })
+            .collect()
+    } else {
+        args.iter().map(|path| ("-".to_string(), path.clone())).collect()
+    };
+
+    // 中文标题每个字符占两列，按显示宽度与下面的列对齐
+    writeln!(out, "文件系统                大小    已用    可用 使用%  挂载点")?;
+    for (device, path) in targets {
+        let stat = match statvfs(&path) {
+            Ok(stat) => stat,
+            // 列出全部挂载点时跳过无权访问的
+            Err(_) if args.is_empty() => continue,
+            Err(e) => return Err(ShellError::CommandError(format!("dfree: '{}': {}", path, e))),
+        };
+        if args.is_empty() && stat.f_blocks == 0 {
+            continue;
+        }
+
+        let unit = stat.f_frsize as u64;
+        let size = stat.f_blocks as u64 * unit;
+        let used = (stat.f_blocks - stat.f_bfree) as u64 * unit;
+        let available = stat.f_bavail as u64 * unit;
+        // 与 df 相同，使用率按普通用户可用的空间计算
+        let percent = match used + available {
+            0 => "-".to_string(),
+            total => format!("{}%", (used * 100).div_ceil(total)),
+        };
+        writeln!(
+            out,
+            "{:<20} {:>7} {:>7} {:>7} {:>5}  {}",
+            device,
+            format_size(size),
+            format_size(used),
+            format_size(available),
+            percent,
+            path
+        )?;
+    }
+    Ok(())
+}
+
+fn statvfs(path: &str) -> io::Result<libc::statvfs> {
+    let c_path = CString::new(Path::new(path).as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "路径中不能包含空字符"))?;
+    // SAFETY: statvfs 是普通的C结构体，由 statvfs 填充
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(stat)
+    }
+}
+
+// /proc/mounts 中的空格等字符写成八进制转义，例如 \040
+fn unescape_mount(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(octal) = field.get(i + 1..i + 4)
+            && let Ok(value) = u8::from_str_radix(octal, 8)
+        {
+            out.push(value);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// 按 1024 进位的易读大小，例如 4.0K、1.5G
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,213 @@
+use crate::command::execute_single_command;
+use crate::error::ShellError;
+use crate::parser::{lex, Command, Redirect, RedirectKind, SimpleCommand, Token, Word};
+use crate::shell::Shell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::os::fd::AsFd;
+
+const USAGE: &str = "用法: each [-l | -0] [-n 个数] 命令 [参数...]";
+
+// 为环境变量之外的内容留出的余量，与 xargs 相同
+const HEADROOM: usize = 2048;
+
+// 输入如何分成条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Separator {
+    // 按空白分开，单引号、双引号和反斜杠的规则与Shell相同
+    Words,
+    // -l：每一行是一个条目
+    Lines,
+    // -0：以空字符分隔，配合 find -print0
+    Nul,
+}
+
+// 内建命令 each：each [-l | -0] [-n 个数] 命令 [参数...]
+// 从标准输入读取条目，尽可能多地追加到命令的参数之后执行，参数总长度不超过 ARG_MAX，
+// 用来代替常见的 xargs 用法；参数中有 {} 时每个条目执行一次，条目替换其中的 {}
+// -n 限制每次最多追加的条目数。命令的标准输入是 /dev/null，没有条目时不执行命令；
+// 任何一次执行的状态码非零时 each 的状态码为 123，与 xargs 相同
+pub fn run_each(shell: &mut Shell, args: &[String]) -> Result<i32, ShellError> {
+    let mut separator = Separator::Words;
+    let mut max_items = None;
+    let mut rest = args;
+    while let Some((flag, tail)) = rest.split_first() {
+        match flag.as_str() {
+            "-l" => separator = Separator::Lines,
+            "-0" => separator = Separator::Nul,
+            "-n" => {
+                let (count, tail) = tail.split_first().ok_or_else(usage)?;
+                let count = count
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| ShellError::CommandError("each: -n 需要一个正整数".to_string()))?;
+                max_items = Some(count);
+                rest = tail;
+                continue;
+            }
+            "--" => {
+                rest = tail;
+                break;
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("each: 未知的选项 '{}'", flag)));
+            }
+            _ => break,
+        }
+        rest = tail;
+    }
+    let (program, fixed) = rest.split_first().ok_or_else(usage)?;
+
+    let mut batch = Batch {
+        program,
+        fixed,
+        placeholder: fixed.iter().any(|arg| arg.contains("{}")),
+        max_items,
+        limit: argument_limit(shell),
+        items: Vec::new(),
+        size: 0,
+        failed: false,
+    };
+    if batch.base_size() >= batch.limit {
+        return Err(ShellError::CommandError("each: 命令本身已经超过参数长度的上限".to_string()));
+    }
+
+    // 读取复制的描述符而不是 io::stdin()，避免重定向的输入留在进程共用的缓冲区中
+    let input = File::from(io::stdin().as_fd().try_clone_to_owned()?);
+    let mut reader = BufReader::new(input);
+    let delimiter = if separator == Separator::Nul { b'\0' } else { b'\n' };
+    let mut record = Vec::new();
+    while reader.read_until(delimiter, &mut record)? > 0 {
+        let text = String::from_utf8_lossy(&record);
+        let text = text.strip_suffix(delimiter as char).unwrap_or(&text);
+        let items = match separator {
+            Separator::Words => split_words(text)?,
+            Separator::Lines => Some(text.trim_end_matches('\r'))
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .into_iter()
+                .collect(),
+            Separator::Nul => vec![text.to_string()],
+        };
+        for item in items {
+            batch.push(shell, item)?;
+        }
+        record.clear();
+    }
+    batch.run(shell)?;
+
+    Ok(if batch.failed { 123 } else { 0 })
+}
+
+fn usage() -> ShellError {
+    ShellError::CommandError(USAGE.to_string())
+}
+
+// 等待执行的一批条目
+struct Batch<'a> {
+    program: &'a String,
+    fixed: &'a [String],
+    // 参数中有 {} 时每个条目单独执行
+    placeholder: bool,
+    max_items: Option<usize>,
+    // 参数（包括命令名）可以占用的字节数
+    limit: usize,
+    items: Vec<String>,
+    // items 占用的字节数
+    size: usize,
+    failed: bool,
+}
+
+impl Batch<'_> {
+    // 命令名和固定参数占用的字节数
+    fn base_size(&self) -> usize {
+        arg_size(self.program) + self.fixed.iter().map(|arg| arg_size(arg)).sum::<usize>()
+    }
+
+    // 加入一个条目，放不下时先执行已有的条目
+    fn push(&mut self, shell: &mut Shell, item: String) -> Result<(), ShellError> {
+        let size = arg_size(&item);
+        if self.base_size() + size > self.limit {
+            return Err(ShellError::CommandError(format!(
+                "each: 条目太长，超过参数长度的上限: {}…",
+                item.chars().take(40).collect::<String>()
+            )));
+        }
+        let full = self.placeholder
+            || self.max_items.is_some_and(|max| self.items.len() >= max)
+            || self.base_size() + self.size + size > self.limit;
+        if full {
+            self.run(shell)?;
+        }
+        self.size += size;
+        self.items.push(item);
+        Ok(())
+    }
+
+    // 执行已有的条目
+    fn run(&mut self, shell: &mut Shell) -> Result<(), ShellError> {
+        if self.items.is_empty() {
+            return Ok(());
+        }
+        let items = std::mem::take(&mut self.items);
+        self.size = 0;
+        let args = if self.placeholder {
+            self.fixed.iter().map(|arg| arg.replace("{}", &items[0])).collect()
+        } else {
+            self.fixed.iter().cloned().chain(items).collect()
+        };
+        let cmd = Command::Simple(SimpleCommand {
+            program: self.program.clone(),
+            args,
+            redirects: vec![Redirect {
+                fd: libc::STDIN_FILENO,
+                kind: RedirectKind::Input,
+                target: Word::literal("/dev/null"),
+            }],
+            ..SimpleCommand::default()
+        });
+        if execute_single_command(shell, &cmd)? != 0 {
+            self.failed = true;
+        }
+        Ok(())
+    }
+}
+
+// 一个参数在新程序的参数区中占用的字节数：字符串、结尾的空字符和指针
+fn arg_size(arg: &str) -> usize {
+    arg.len() + 1 + size_of::<usize>()
+}
+
+// 参数可以占用的字节数：ARG_MAX 减去导出的环境变量和余量
+fn argument_limit(shell: &Shell) -> usize {
+    // SAFETY: sysconf 只查询系统限制
+    let arg_max = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    let arg_max = if arg_max > 0 { arg_max as usize } else { 128 * 1024 };
+    let environment: usize = shell
+        .vars
+        .exported()
+        .map(|(name, value)| name.len() + 1 + arg_size(value))
+        .sum();
+    arg_max.saturating_sub(environment + HEADROOM)
+}
+
+// 用Shell的词法分析把一行分成若干词，引号和反斜杠的规则与命令行相同，词的内容是去掉引号后的文本；
+// 不展开变量和通配符，变量引用保留为 ${NAME}。| ; > 等符号不分隔条目，与紧挨着的词连成一个条目
+fn split_words(line: &str) -> Result<Vec<String>, ShellError> {
+    let tokens = lex(line).map_err(|e| ShellError::CommandError(format!("each: {}: {}", e, line)))?;
+    let mut words: Vec<String> = Vec::new();
+    let mut end = None;
+    for (token, span) in tokens {
+        let text = match token {
+            Token::Word(word) => word.text(),
+            _ => line[span.start..span.end].to_string(),
+        };
+        match words.last_mut() {
+            Some(word) if end == Some(span.start) => word.push_str(&text),
+            _ => words.push(text),
+        }
+        end = Some(span.end);
+    }
+    Ok(words)
+}
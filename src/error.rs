@@ -1,19 +1,68 @@
 use std::fmt;
 use std::io;
 
+// 输入中的一段，按字节偏移计算，不包含 end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug)]
 pub enum ShellError {
     Io(io::Error),
-    ParseError(String),
+    // 解析错误和输入中出错的位置
+    ParseError(String, Option<Span>),
     CommandError(String),
+    // 无法启动外部命令：命令名和 spawn 返回的错误
+    SpawnError(String, io::Error),
+    // 输入在中途结束，例如以反斜杠结尾；交互时可以接着读取下一行
+    Incomplete(String, Option<Span>),
+    // exit 内建命令：不是错误，沿调用链一直返回到读取命令的地方（交互循环、脚本或 -c），
+    // 在那里以这个状态码结束Shell；子Shell中直接以它为退出码
+    Exit(i32),
+}
+
+impl ShellError {
+    // 解析错误在输入中的位置
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ShellError::ParseError(_, span) | ShellError::Incomplete(_, span) => *span,
+            _ => None,
+        }
+    }
+
+    // 把解析错误的位置换成 span，用于嵌套的文本（例如 $(...) 中的命令）中的错误
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            ShellError::ParseError(message, _) => ShellError::ParseError(message, Some(span)),
+            ShellError::Incomplete(message, _) => ShellError::Incomplete(message, Some(span)),
+            e => e,
+        }
+    }
+
+    // 出错时的状态码：找不到命令为 127，找到了但无法执行（例如没有执行权限）为 126，
+    // 语法错误为 2，其他错误为 1
+    pub fn status(&self) -> i32 {
+        match self {
+            ShellError::SpawnError(_, e) if e.kind() == io::ErrorKind::NotFound => 127,
+            ShellError::SpawnError(..) => 126,
+            ShellError::ParseError(..) | ShellError::Incomplete(..) => 2,
+            ShellError::Exit(status) => *status,
+            _ => 1,
+        }
+    }
 }
 
 impl fmt::Display for ShellError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ShellError::Io(err) => write!(f, "IO错误: {}", err),
-            ShellError::ParseError(err) => write!(f, "解析错误: {}", err),
+            ShellError::ParseError(err, _) => write!(f, "解析错误: {}", err),
             ShellError::CommandError(err) => write!(f, "命令错误: {}", err),
+            ShellError::SpawnError(program, err) => write!(f, "命令错误: 无法执行命令 '{}': {}", program, err),
+            ShellError::Incomplete(err, _) => write!(f, "输入不完整: {}", err),
+            ShellError::Exit(status) => write!(f, "exit {}", status),
         }
     }
 }
@@ -0,0 +1,583 @@
+use crate::alias::AliasTable;
+use crate::arith;
+use crate::brace::expand_braces;
+use crate::command::command_substitution;
+use crate::diagnostic;
+use crate::error::{ShellError, Span};
+use crate::glob;
+use crate::parser::{is_assignment, lex, ParamOp, Redirect, Segment, SimpleCommand, Token, Word};
+use crate::pathglob;
+use crate::procsubst::process_substitution;
+use crate::shell::Shell;
+use crate::vars::is_valid_name;
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::mem;
+
+// 别名嵌套展开的最大层数
+const MAX_ALIAS_DEPTH: usize = 32;
+
+// 展开别名：命令位置的普通别名和任意位置的全局别名
+// 含有引号的词不参与展开；展开结果会继续展开，但正在展开的别名按字面处理，
+// 因此 alias ls='ls --color' 以及互相引用的别名都不会无限展开；
+// 展开得到的词法单元的位置都是别名所在的位置
+pub fn expand_aliases(tokens: Vec<(Token, Span)>, aliases: &AliasTable) -> Result<Vec<(Token, Span)>, ShellError> {
+    let mut expanded = Vec::with_capacity(tokens.len());
+    let mut command_position = true;
+    expand_into(tokens, aliases, &mut Vec::new(), &mut command_position, &mut expanded)?;
+    Ok(expanded)
+}
+
+fn expand_into(
+    tokens: Vec<(Token, Span)>,
+    aliases: &AliasTable,
+    active: &mut Vec<String>,
+    command_position: &mut bool,
+    out: &mut Vec<(Token, Span)>,
+) -> Result<(), ShellError> {
+    for (token, span) in tokens {
+        match token {
+            // 重定向的目标文件名不展开，也不改变命令位置
+            Token::Word(word) if matches!(out.last(), Some((Token::Redirect(..), _))) => {
+                out.push((Token::Word(word), span));
+            }
+            Token::Word(word) => {
+                let alias = word.as_plain().and_then(|name| {
+                    let value = if *command_position {
+                        aliases.get(name).or_else(|| aliases.get_global(name))
+                    } else {
+                        aliases.get_global(name)
+                    };
+                    value.map(|value| (name.to_string(), value))
+                });
+
+                match alias {
+                    Some((name, value)) if !active.contains(&name) => {
+                        if active.len() >= MAX_ALIAS_DEPTH {
+                            return Err(ShellError::ParseError(
+                                format!("别名展开超过 {} 层: '{}'", MAX_ALIAS_DEPTH, name),
+                                Some(span),
+                            ));
+                        }
+
+                        // 展开结果以管道、分号、&&、||、& 或 '(' 结尾时，下一个词重新处于命令位置
+                        let replacement = lex(value)
+                            .map_err(|e| e.at(span))?
+                            .into_iter()
+                            .map(|(token, _)| (token, span))
+                            .collect();
+                        active.push(name);
+                        expand_into(replacement, aliases, active, command_position, out)?;
+                        active.pop();
+                    }
+                    _ => {
+                        // { 和命令前的 NAME=value 之后仍然是命令位置
+                        *command_position =
+                            *command_position && (word.as_plain() == Some("{") || is_assignment(&word));
+                        out.push((Token::Word(word), span));
+                    }
+                }
+            }
+            Token::Pipe | Token::Semicolon | Token::And | Token::Or | Token::Background | Token::LParen => {
+                *command_position = true;
+                out.push((token, span));
+            }
+            // ')' 之后只能是重定向或连接符号
+            Token::RParen => {
+                *command_position = false;
+                out.push((token, span));
+            }
+            Token::Redirect(fd, kind) => out.push((Token::Redirect(fd, kind), span)),
+        }
+    }
+
+    Ok(())
+}
+
+// 执行前展开命令中的变量引用和命令替换，得到 program、args、赋值的值和重定向的目标
+// 依次进行花括号展开、波浪号展开、变量和命令替换、拆分和通配符展开，赋值的值不做花括号展开
+// 未加引号的变量按空白拆分为多个参数，双引号中的不拆分，单引号中的不展开
+pub fn expand_command(shell: &mut Shell, cmd: &SimpleCommand) -> Result<SimpleCommand, ShellError> {
+    let mut expanded = cmd.clone();
+
+    if !cmd.words.is_empty() {
+        let mut fields = Vec::new();
+        for word in cmd.words.iter().flat_map(expand_braces) {
+            fields.extend(expand_word(shell, &word)?);
+        }
+        let mut fields = fields.into_iter();
+        // 全部展开为空时（例如只有 $EMPTY）不执行任何程序
+        expanded.program = fields.next().unwrap_or_default();
+        expanded.args = fields.collect();
+        expanded.words.clear();
+    }
+
+    // 赋值的值不拆分
+    for (_, value) in &mut expanded.assignments {
+        *value = Word::literal(&expand_string(shell, value)?);
+    }
+
+    for redirect in &mut expanded.redirects {
+        let mut fields = Vec::new();
+        for word in expand_braces(&redirect.target) {
+            fields.extend(expand_word(shell, &word)?);
+        }
+        let target = match fields.as_slice() {
+            [target] => target,
+            _ => {
+                return Err(ShellError::CommandError(format!(
+                    "重定向目标 '{}' 不明确",
+                    redirect.target.source()
+                )))
+            }
+        };
+        *redirect = Redirect {
+            target: Word::literal(target),
+            ..redirect.clone()
+        };
+    }
+
+    Ok(expanded)
+}
+
+// 展开一个词，未加引号的变量值和命令输出按空白拆分，可能得到零个或多个参数
+// 含有未加引号的通配符的参数再按当前目录展开为匹配的文件名，没有匹配时保持原样
+// 算术表达式出错和 ${NAME:?} 报错时整个命令失败
+pub fn expand_word(shell: &mut Shell, word: &Word) -> Result<Vec<String>, ShellError> {
+    let word = expand_tilde(shell, word);
+    let mut fields = Vec::new();
+    let mut current = Field::default();
+
+    for segment in &word.segments {
+        expand_segment(shell, segment, &mut current, &mut fields)?;
+    }
+
+    current.finish(&mut fields);
+    Ok(fields)
+}
+
+fn expand_segment(
+    shell: &mut Shell,
+    segment: &Segment,
+    current: &mut Field,
+    fields: &mut Vec<String>,
+) -> Result<(), ShellError> {
+    match segment {
+        Segment::Plain(s) => current.push_unquoted(s),
+        Segment::Single(s) | Segment::Double(s) => current.push_quoted(s),
+        // "$@" 中的每个位置参数都是单独的参数，没有位置参数时不产生参数
+        Segment::Var { name, quoted: true } if name == "@" => {
+            for (i, arg) in arguments(shell).iter().enumerate() {
+                if i > 0 {
+                    current.finish(fields);
+                }
+                current.push_quoted(arg);
+            }
+        }
+        // "${NAME[@]}" 与 "$@" 相同，每个元素都是单独的参数
+        Segment::Param { name, op: ParamOp::Element, word, quoted: true } if word.text() == "@" => {
+            for (i, element) in array(shell, name).iter().enumerate() {
+                if i > 0 {
+                    current.finish(fields);
+                }
+                current.push_quoted(element);
+            }
+        }
+        Segment::Var { name, quoted: true } => current.push_quoted(&variable(shell, name)),
+        Segment::Var { name, quoted: false } => current.push_split(&variable(shell, name), fields),
+        // 使用 word 时就地展开它的各段，其中的引号照常起作用：${X:-a b} 是两个参数，${X:-"a b"} 是一个
+        Segment::Param { name, op, word, quoted } => match parameter(shell, name, *op, word)? {
+            Parameter::Word => {
+                let word = if *quoted { Cow::Borrowed(word) } else { expand_tilde(shell, word) };
+                for segment in &word.segments {
+                    match segment {
+                        Segment::Plain(s) => current.push_split(s, fields),
+                        segment => expand_segment(shell, segment, current, fields)?,
+                    }
+                }
+            }
+            Parameter::Value(value) if *quoted => current.push_quoted(&value),
+            Parameter::Value(value) => current.push_split(&value, fields),
+        },
+        Segment::Subst { command, quoted: true } => current.push_quoted(&substitute(shell, command)),
+        Segment::Subst { command, quoted: false } => current.push_split(&substitute(shell, command), fields),
+        Segment::Arith(expr) => current.push_quoted(&arith::evaluate(expr, &mut shell.vars)?.to_string()),
+        Segment::ProcSubst { command, output } => current.push_quoted(&process_substitution(shell, command, *output)?),
+    }
+    Ok(())
+}
+
+// ${...} 的结果：变量的值（或由它得到的值），或者操作符之后的 word
+enum Parameter {
+    Value(String),
+    Word,
+}
+
+fn parameter(shell: &mut Shell, name: &str, op: ParamOp, word: &Word) -> Result<Parameter, ShellError> {
+    let value = lookup(shell, name).map(Cow::into_owned);
+    let unset = |null: bool| value.as_deref().is_none_or(|value| null && value.is_empty());
+    let value_or_empty = || Parameter::Value(value.clone().unwrap_or_default());
+
+    let result = match op {
+        ParamOp::Length => Parameter::Value(value.as_deref().unwrap_or("").chars().count().to_string()),
+        ParamOp::Default(null) if unset(null) => Parameter::Word,
+        ParamOp::Alternative(null) if unset(null) => Parameter::Value(String::new()),
+        ParamOp::Alternative(_) => Parameter::Word,
+        ParamOp::Assign(null) if unset(null) => {
+            if !is_valid_name(name) {
+                return Err(ShellError::CommandError(format!("${}: 无法这样赋值", name)));
+            }
+            let assigned = expand_string(shell, word)?;
+            shell.vars.set(name, &assigned);
+            Parameter::Value(assigned)
+        }
+        ParamOp::Error(null) if unset(null) => {
+            let message = expand_string(shell, word)?;
+            let message = if message.is_empty() { "参数为空或未设置".to_string() } else { message };
+            return Err(ShellError::CommandError(format!("{}: {}", name, message)));
+        }
+        ParamOp::Default(_) | ParamOp::Assign(_) | ParamOp::Error(_) => value_or_empty(),
+        ParamOp::RemovePrefix { longest } => {
+            let pattern = expand_pattern(shell, word)?;
+            Parameter::Value(remove_prefix(value.as_deref().unwrap_or(""), &pattern, longest).to_string())
+        }
+        ParamOp::RemoveSuffix { longest } => {
+            let pattern = expand_pattern(shell, word)?;
+            Parameter::Value(remove_suffix(value.as_deref().unwrap_or(""), &pattern, longest).to_string())
+        }
+        ParamOp::Element => Parameter::Value(element(shell, name, word)?),
+        ParamOp::Count => Parameter::Value(array(shell, name).len().to_string()),
+    };
+    Ok(result)
+}
+
+// ${NAME[index]} 的值：下标按算术表达式求值，负数从末尾数起，越界时为空；
+// 下标为 @ 或 * 时是以空格连接的全部元素
+fn element(shell: &mut Shell, name: &str, index: &Word) -> Result<String, ShellError> {
+    let elements = array(shell, name);
+    if matches!(index.text().as_str(), "@" | "*") {
+        return Ok(elements.join(" "));
+    }
+    let index = arith::evaluate(&expand_string(shell, index)?, &mut shell.vars)?;
+    let index = if index < 0 { index + elements.len() as i64 } else { index };
+    Ok(usize::try_from(index)
+        .ok()
+        .and_then(|index| elements.get(index))
+        .cloned()
+        .unwrap_or_default())
+}
+
+// 数组变量的全部元素；普通变量看作只有一个元素的数组，未设置时没有元素
+fn array(shell: &Shell, name: &str) -> Vec<String> {
+    match shell.vars.get_array(name) {
+        Some(elements) => elements.to_vec(),
+        None => shell.vars.get(name).map(|value| vec![value.to_string()]).unwrap_or_default(),
+    }
+}
+
+// 把 ${NAME#pattern} 等中的 pattern 展开为通配符模式，引号中的部分按字面匹配
+fn expand_pattern(shell: &mut Shell, word: &Word) -> Result<String, ShellError> {
+    let mut pattern = String::new();
+    for segment in &word.segments {
+        let unquoted = matches!(
+            segment,
+            Segment::Plain(_)
+                | Segment::Var { quoted: false, .. }
+                | Segment::Param { quoted: false, .. }
+                | Segment::Subst { quoted: false, .. }
+                | Segment::Arith(_)
+        );
+        let text = expand_string(shell, &Word { segments: vec![segment.clone()] })?;
+        if unquoted {
+            pattern.push_str(&text);
+        } else {
+            pattern.push_str(&glob::escape(&text));
+        }
+    }
+    Ok(pattern)
+}
+
+// 去掉匹配 pattern 的最短（longest 时最长）前缀，没有匹配时原样返回
+fn remove_prefix<'a>(value: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut ends: Vec<usize> = value.char_indices().map(|(i, _)| i).chain([value.len()]).collect();
+    if longest {
+        ends.reverse();
+    }
+    match ends.into_iter().find(|&end| glob::matches(pattern, &value[..end])) {
+        Some(end) => &value[end..],
+        None => value,
+    }
+}
+
+// 去掉匹配 pattern 的最短（longest 时最长）后缀
+fn remove_suffix<'a>(value: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut starts: Vec<usize> = value.char_indices().map(|(i, _)| i).chain([value.len()]).collect();
+    if !longest {
+        starts.reverse();
+    }
+    match starts.into_iter().find(|&start| glob::matches(pattern, &value[start..])) {
+        Some(start) => &value[..start],
+        None => value,
+    }
+}
+
+// 展开中的一个参数：text 是字面文本，pattern 是引号中的字符已转义的通配符模式
+#[derive(Default)]
+struct Field {
+    text: String,
+    pattern: String,
+    // 是否含有未加引号的通配符
+    glob: bool,
+    // 参数是否已经存在：引号（即使为空）或非空文本都会产生一个参数
+    present: bool,
+}
+
+impl Field {
+    fn push_unquoted(&mut self, s: &str) {
+        self.text.push_str(s);
+        self.pattern.push_str(s);
+        self.glob |= glob::has_wildcards(s);
+        self.present = true;
+    }
+
+    fn push_quoted(&mut self, s: &str) {
+        self.text.push_str(s);
+        self.pattern.push_str(&glob::escape(s));
+        self.present = true;
+    }
+
+    // 追加未加引号的展开结果，按空白拆分，两端的空白也结束当前参数
+    fn push_split(&mut self, value: &str, fields: &mut Vec<String>) {
+        if value.starts_with(char::is_whitespace) {
+            self.finish(fields);
+        }
+        for (i, piece) in value.split_whitespace().enumerate() {
+            if i > 0 {
+                self.finish(fields);
+            }
+            self.push_unquoted(piece);
+        }
+        if value.ends_with(char::is_whitespace) {
+            self.finish(fields);
+        }
+    }
+
+    // 结束当前参数，放入 fields 并开始下一个
+    fn finish(&mut self, fields: &mut Vec<String>) {
+        let field = mem::take(self);
+        if !field.present {
+            return;
+        }
+        if field.glob {
+            let matches = pathglob::expand(&field.pattern);
+            if !matches.is_empty() {
+                fields.extend(matches);
+                return;
+            }
+        }
+        fields.push(field.text);
+    }
+}
+
+// 展开一个词但不拆分，用于赋值的值等只需要一个字符串的地方
+pub fn expand_string(shell: &mut Shell, word: &Word) -> Result<String, ShellError> {
+    let word = expand_tilde(shell, word);
+    let mut text = String::new();
+    for segment in &word.segments {
+        match segment {
+            Segment::Plain(s) | Segment::Single(s) | Segment::Double(s) => text.push_str(s),
+            Segment::Var { name, .. } => text.push_str(&variable(shell, name)),
+            Segment::Param { name, op, word, .. } => match parameter(shell, name, *op, word)? {
+                Parameter::Word => text.push_str(&expand_string(shell, word)?),
+                Parameter::Value(value) => text.push_str(&value),
+            },
+            Segment::Subst { command, .. } => text.push_str(&substitute(shell, command)),
+            Segment::Arith(expr) => text.push_str(&arith::evaluate(expr, &mut shell.vars)?.to_string()),
+            Segment::ProcSubst { command, output } => text.push_str(&process_substitution(shell, command, *output)?),
+        }
+    }
+    Ok(text)
+}
+
+// 命令替换的结果，执行失败时报告错误并展开为空
+fn substitute(shell: &mut Shell, command: &str) -> String {
+    command_substitution(shell, command).unwrap_or_else(|e| {
+        diagnostic::report(&mut shell.stdio.err(), &e);
+        String::new()
+    })
+}
+
+// 波浪号展开：词开头未加引号的 ~ 替换为 $HOME，~user 替换为该用户的主目录
+// 波浪号前缀到第一个 '/' 或词尾为止，其中含有引号或变量时（如 ~"x"、~$USER）不展开，
+// 用户不存在时保持原样；展开结果作为引号中的文本，不再拆分
+fn expand_tilde<'a>(shell: &Shell, word: &'a Word) -> Cow<'a, Word> {
+    let Some(Segment::Plain(first)) = word.segments.first() else {
+        return Cow::Borrowed(word);
+    };
+    let Some(rest) = first.strip_prefix('~') else {
+        return Cow::Borrowed(word);
+    };
+    let (user, rest) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None if word.segments.len() == 1 => (rest, ""),
+        None => return Cow::Borrowed(word),
+    };
+    let Some(home) = home_dir(shell, user) else {
+        return Cow::Borrowed(word);
+    };
+
+    let mut segments = vec![Segment::Single(home)];
+    if !rest.is_empty() {
+        segments.push(Segment::Plain(rest.to_string()));
+    }
+    segments.extend(word.segments[1..].iter().cloned());
+    Cow::Owned(Word { segments })
+}
+
+// 用户的主目录：空用户名表示当前用户，优先使用 $HOME，其次查询 passwd
+fn home_dir(shell: &Shell, user: &str) -> Option<String> {
+    if user.is_empty()
+        && let Some(home) = shell.vars.get("HOME")
+    {
+        return Some(home.to_string());
+    }
+
+    let mut entry: libc::passwd = unsafe { mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: entry 和 buf 在调用期间有效，返回的字符串指针指向 buf
+    let status = unsafe {
+        if user.is_empty() {
+            libc::getpwuid_r(libc::getuid(), &mut entry, buf.as_mut_ptr(), buf.len(), &mut result)
+        } else {
+            let name = CString::new(user).ok()?;
+            libc::getpwnam_r(name.as_ptr(), &mut entry, buf.as_mut_ptr(), buf.len(), &mut result)
+        }
+    };
+    if status != 0 || result.is_null() || entry.pw_dir.is_null() {
+        return None;
+    }
+    // SAFETY: 查询成功时 pw_dir 是 buf 中以空字符结尾的字符串
+    let dir = unsafe { CStr::from_ptr(entry.pw_dir) };
+    Some(dir.to_string_lossy().into_owned())
+}
+
+// 变量的值，未定义的变量展开为空
+fn variable<'a>(shell: &'a Shell, name: &str) -> Cow<'a, str> {
+    lookup(shell, name).unwrap_or_default()
+}
+
+// 变量或特殊参数的值，未设置时为 None：$? 是上一条命令的状态码，$0、$1 …… 是位置参数，
+// $# 是位置参数（不含 $0）的个数，$@ 和 $* 是以空格连接的全部位置参数；数组变量的值是第一个元素
+fn lookup<'a>(shell: &'a Shell, name: &str) -> Option<Cow<'a, str>> {
+    match name {
+        "?" => Some(Cow::Owned(shell.last_status.to_string())),
+        "#" => Some(Cow::Owned(arguments(shell).len().to_string())),
+        "@" | "*" => Some(Cow::Owned(arguments(shell).join(" "))),
+        _ if name.starts_with(|c: char| c.is_ascii_digit()) => {
+            let index: usize = name.parse().ok()?;
+            shell.positional.get(index).map(|arg| Cow::Borrowed(arg.as_str()))
+        }
+        _ => shell
+            .vars
+            .get(name)
+            .or_else(|| shell.vars.get_array(name)?.first().map(String::as_str))
+            .map(Cow::Borrowed),
+    }
+}
+
+// $1 开始的位置参数
+fn arguments(shell: &Shell) -> &[String] {
+    shell.positional.get(1..).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_input, Command};
+    use crate::testutil::temp_path;
+    use std::fs;
+
+    // 解析 input，返回其中的第一个命令
+    fn first_command(input: &str) -> SimpleCommand {
+        let mut lists = parse_input(input, &AliasTable::default()).unwrap();
+        match lists[0].pipelines[0].remove(0) {
+            Command::Simple(command) => command,
+            Command::Compound(_) => panic!("不是简单命令: {}", input),
+        }
+    }
+
+    // 解析 input 并展开第一个命令的参数（不含命令名）
+    fn expand(shell: &mut Shell, input: &str) -> Vec<String> {
+        let command = first_command(&format!("echo {}", input));
+        expand_command(shell, &command).unwrap().args
+    }
+
+    #[test]
+    fn quoted_empty_arguments() {
+        let mut shell = Shell::default();
+        shell.vars.set("EMPTY", "");
+        assert_eq!(expand(&mut shell, "\"\" '' x"), ["", "", "x"]);
+        assert_eq!(expand(&mut shell, "$EMPTY x"), ["x"]);
+        assert_eq!(expand(&mut shell, "\"$EMPTY\" x"), ["", "x"]);
+    }
+
+    #[test]
+    fn single_quotes_suppress_expansion() {
+        let mut shell = Shell::default();
+        shell.vars.set("HOME", "/home/a b");
+        assert_eq!(expand(&mut shell, "'$HOME' \"$HOME\" $HOME"), ["$HOME", "/home/a b", "/home/a", "b"]);
+    }
+
+    #[test]
+    fn field_splitting() {
+        let mut shell = Shell::default();
+        shell.vars.set("V", " a  b ");
+        assert_eq!(expand(&mut shell, "$V"), ["a", "b"]);
+        assert_eq!(expand(&mut shell, "\"$V\""), [" a  b "]);
+        assert_eq!(expand(&mut shell, "x${V}y"), ["x", "a", "b", "y"]);
+        assert_eq!(expand(&mut shell, "${UNSET:-\"p q\"} ${UNSET:-p q}"), ["p q", "p", "q"]);
+    }
+
+    #[test]
+    fn positional_parameters() {
+        let mut shell = Shell::default();
+        shell.positional = ["script", "a b", "c"].map(String::from).to_vec();
+        assert_eq!(expand(&mut shell, "$0 $# ${2}"), ["script", "2", "c"]);
+        assert_eq!(expand(&mut shell, "\"$@\""), ["a b", "c"]);
+        assert_eq!(expand(&mut shell, "x\"$@\"y"), ["xa b", "cy"]);
+        assert_eq!(expand(&mut shell, "$@"), ["a", "b", "c"]);
+        assert_eq!(expand(&mut shell, "\"$*\""), ["a b c"]);
+        assert_eq!(expand(&mut shell, "\"$3\" ${3:-d} ${1:+set} ${#1}"), ["", "d", "set", "3"]);
+    }
+
+    #[test]
+    fn no_positional_parameters() {
+        let mut shell = Shell::default();
+        assert_eq!(expand(&mut shell, "\"$@\" $# x"), ["0", "x"]);
+        assert_eq!(expand(&mut shell, "\"$1\""), [""]);
+    }
+
+    #[test]
+    fn mapfile_array_elements() {
+        let path = temp_path("expand-mapfile");
+        fs::write(&path, "one\ntwo words\nthree\n").unwrap();
+        let mut shell = Shell::default();
+        let status = shell.run_str(&format!("mapfile -t L < {}", path.display())).unwrap().status;
+        fs::remove_file(&path).unwrap();
+        assert_eq!(status, 0);
+        assert_eq!(expand(&mut shell, "${L[0]} \"${L[1]}\" ${L[-1]} x${L[5]}"), ["one", "two words", "three", "x"]);
+        assert_eq!(expand(&mut shell, "${#L[@]} ${#L[*]} $L"), ["3", "3", "one"]);
+        assert_eq!(expand(&mut shell, "\"${L[@]}\""), ["one", "two words", "three"]);
+        assert_eq!(expand(&mut shell, "${L[@]}"), ["one", "two", "words", "three"]);
+        assert_eq!(expand(&mut shell, "\"${L[*]}\""), ["one two words three"]);
+        shell.vars.set("I", "1");
+        assert_eq!(expand(&mut shell, "\"${L[I+1]}\" \"${L[$I]}\""), ["three", "two words"]);
+    }
+
+    #[test]
+    fn cannot_assign_to_positional_parameters() {
+        let mut shell = Shell::default();
+        assert!(expand_command(&mut shell, &first_command("echo ${1:=x}")).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::ShellError;
+use flate2::read::GzDecoder;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+// 压缩包的种类，根据文件开头的魔数判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Tar,
+    TarGz,
+    // 单个文件的 .gz
+    Gzip,
+    Zip,
+}
+
+// 内建命令 extract：extract [-C 目录] 压缩包...
+// 支持 tar、tar.gz/tgz、gz 和 zip，默认解压到当前目录
+pub fn run_extract(args: &[String]) -> Result<(), ShellError> {
+    let mut dest = PathBuf::from(".");
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-C" => {
+                let dir = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("extract: -C 需要参数".to_string()))?;
+                dest = PathBuf::from(dir);
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("extract: 未知的选项 '{}'", flag)));
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return Err(ShellError::CommandError("用法: extract [-C 目录] 压缩包...".to_string()));
+    }
+
+    for path in paths {
+        extract(Path::new(path), &dest).map_err(|e| ShellError::CommandError(format!("extract: '{}': {}", path, e)))?;
+    }
+    Ok(())
+}
+
+fn extract(path: &Path, dest: &Path) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+
+    match detect(path)? {
+        ArchiveKind::Tar => tar::Archive::new(file).unpack(dest).map_err(|e| e.to_string()),
+        ArchiveKind::TarGz => tar::Archive::new(GzDecoder::new(file))
+            .unpack(dest)
+            .map_err(|e| e.to_string()),
+        ArchiveKind::Gzip => {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .and_then(|name| name.strip_suffix(".gz").map(str::to_string))
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| "无法确定解压后的文件名".to_string())?;
+            let target = dest.join(name);
+            // 与 gunzip 一样不覆盖已有的文件
+            let mut output = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&target)
+                .map_err(|e| format!("无法创建 '{}': {}", target.display(), e))?;
+            io::copy(&mut GzDecoder::new(file), &mut output).map_err(|e| e.to_string())?;
+            Ok(())
+        }
+        ArchiveKind::Zip => zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(dest))
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn detect(path: &Path) -> Result<ArchiveKind, String> {
+    let mut header = Vec::with_capacity(512);
+    File::open(path)
+        .and_then(|file| file.take(512).read_to_end(&mut header))
+        .map_err(|e| e.to_string())?;
+
+    if header.starts_with(&[0x1f, 0x8b]) {
+        // 压缩后看不出是不是 tar，按文件名区分，无法判断时当作 tar.gz
+        let name = path.to_string_lossy();
+        let single = name.ends_with(".gz") && !name.ends_with(".tar.gz");
+        return Ok(if single { ArchiveKind::Gzip } else { ArchiveKind::TarGz });
+    }
+    // 本地文件头，或者空 zip 文件只有的目录结束记录
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        return Ok(ArchiveKind::Zip);
+    }
+    if header.get(257..262) == Some(b"ustar") {
+        return Ok(ArchiveKind::Tar);
+    }
+    Err("无法识别的压缩包格式（支持 tar、tar.gz、gz、zip）".to_string())
+}
@@ -0,0 +1,72 @@
+use crate::error::ShellError;
+use std::fs::File;
+use std::io::{self, Write};
+
+// 内建命令 fetch：fetch [-H '名字: 值']... [-o 文件] URL
+// 发送 GET 请求，响应正文写到标准输出或 -o 指定的文件；非 2xx 状态码视为失败
+pub fn run_fetch(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut headers = Vec::new();
+    let mut output = None;
+    let mut url = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-H" => {
+                let header = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("fetch: -H 需要参数".to_string()))?;
+                let (name, value) = header
+                    .split_once(':')
+                    .ok_or_else(|| ShellError::CommandError(format!("fetch: 无效的请求头 '{}'，应为 '名字: 值'", header)))?;
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+            "-o" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("fetch: -o 需要参数".to_string()))?;
+                output = Some(path);
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("fetch: 未知的选项 '{}'", flag)));
+            }
+            _ if url.is_none() => url = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+    let url = url.ok_or_else(usage)?;
+
+    let mut request = ureq::get(url);
+    for (name, value) in &headers {
+        request = request.set(name, value);
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            return Err(ShellError::CommandError(format!(
+                "fetch: {}: HTTP {} {}",
+                url,
+                code,
+                response.status_text()
+            )))
+        }
+        Err(e) => return Err(ShellError::CommandError(format!("fetch: {}: {}", url, e))),
+    };
+
+    let mut body = response.into_reader();
+    match output {
+        Some(path) => {
+            let mut file = File::create(path)
+                .map_err(|e| ShellError::CommandError(format!("fetch: 无法创建 '{}': {}", path, e)))?;
+            io::copy(&mut body, &mut file)?;
+        }
+        None => {
+            io::copy(&mut body, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn usage() -> ShellError {
+    ShellError::CommandError("用法: fetch [-H '名字: 值']... [-o 文件] URL".to_string())
+}
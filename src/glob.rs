@@ -0,0 +1,170 @@
+// 通配符匹配，支持 *、?、[...]（含 ! 或 ^ 取反和 a-z 范围）以及反斜杠转义
+
+// 判断 text 是否完整匹配 pattern
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut pi, mut ti) = (0, 0);
+    // 最近一个 * 的位置，以及它当前吞下的文本结束位置，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < text.len() {
+        if pi < pattern.len() {
+            if pattern[pi] == '*' {
+                star = Some((pi, ti));
+                pi += 1;
+                continue;
+            }
+            if let Some(next) = match_one(&pattern, pi, text[ti]) {
+                pi = next;
+                ti += 1;
+                continue;
+            }
+        }
+
+        // 不匹配时让上一个 * 多吞一个字符
+        match star {
+            Some((star_pi, star_ti)) => {
+                pi = star_pi + 1;
+                ti = star_ti + 1;
+                star = Some((star_pi, star_ti + 1));
+            }
+            None => return false,
+        }
+    }
+
+    while pi < pattern.len() && pattern[pi] == '*' {
+        pi += 1;
+    }
+    pi == pattern.len()
+}
+
+// 按路径匹配：pattern 和 path 以 '/' 分段逐段匹配，通配符不匹配 '/'，
+// 单独成段的 ** 匹配零个或多个目录，例如 src/**/*.rs 匹配 src/main.rs 和 src/a/b.rs
+pub fn matches_path(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    matches_components(&pattern, &path)
+}
+
+fn matches_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_components(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path_rest)) => matches(first, name) && matches_components(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+// 判断字符串中是否含有通配符
+pub fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+// 转义通配符和反斜杠，使其按字面匹配
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// 匹配 pattern[pi] 处的单个元素（非 *），成功时返回下一个元素的位置
+fn match_one(pattern: &[char], pi: usize, c: char) -> Option<usize> {
+    match pattern[pi] {
+        '?' => Some(pi + 1),
+        '[' => match match_class(pattern, pi, c) {
+            Some((true, next)) => Some(next),
+            Some((false, _)) => None,
+            // 没有闭合的 ] 时按字面字符处理
+            None => (c == '[').then_some(pi + 1),
+        },
+        '\\' if pi + 1 < pattern.len() => (pattern[pi + 1] == c).then_some(pi + 2),
+        p => (p == c).then_some(pi + 1),
+    }
+}
+
+// 匹配字符类 [...]，返回 (是否匹配, 类之后的位置)；类未闭合时返回 None
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let ch = pattern[i];
+        // 紧跟在 [ 之后的 ] 是字面字符
+        if ch == ']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            if ch <= c && c <= pattern[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if ch == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_and_question_mark() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*.rs", ".rs"));
+        assert!(!matches("*.rs", "main.rsx"));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(matches("*", ""));
+        assert!(matches("?.txt", "a.txt"));
+        assert!(!matches("?.txt", ".txt"));
+        assert!(!matches("?.txt", "ab.txt"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(matches("[a-z]1", "q1"));
+        assert!(!matches("[a-z]1", "Q1"));
+        assert!(matches("[!x]y", "ay"));
+        assert!(!matches("[!x]y", "xy"));
+        assert!(matches("[^x]y", "ay"));
+        assert!(matches("[]a]", "]"));
+        // 没有闭合的 [ 按字面匹配
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn escaped_wildcards_match_literally() {
+        assert!(matches(&escape("a*b?"), "a*b?"));
+        assert!(!matches(&escape("a*b"), "aXb"));
+        assert!(matches("\\[x]", "[x]"));
+    }
+
+    #[test]
+    fn globstar_paths() {
+        assert!(matches_path("src/**/*.rs", "src/main.rs"));
+        assert!(matches_path("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(!matches_path("src/*.rs", "src/a/b.rs"));
+        assert!(!matches_path("src/**/*.rs", "lib/main.rs"));
+    }
+}
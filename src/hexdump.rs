@@ -0,0 +1,108 @@
+use crate::error::ShellError;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::FromRawFd;
+
+// 每行显示的字节数
+const BYTES_PER_LINE: usize = 16;
+
+// 内建命令 hexdump：hexdump [-n 长度] [-s 偏移] [文件]
+// 按 xxd 的格式输出偏移、十六进制和可打印字符，没有文件时读取标准输入
+//   00000000: 6865 6c6c 6f0a                           hello.
+pub fn run_hexdump(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut length = None;
+    let mut skip = 0;
+    let mut path = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-n" | "-s" => {
+                let value = iter
+                    .next()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .ok_or_else(|| ShellError::CommandError(format!("hexdump: {} 需要一个非负整数", arg)))?;
+                if arg == "-n" {
+                    length = Some(value);
+                } else {
+                    skip = value;
+                }
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("hexdump: 未知的选项 '{}'", flag)));
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(ShellError::CommandError("用法: hexdump [-n 长度] [-s 偏移] [文件]".to_string())),
+        }
+    }
+
+    match path {
+        Some(path) => {
+            let mut file = File::open(path)
+                .map_err(|e| ShellError::CommandError(format!("hexdump: 无法打开 '{}': {}", path, e)))?;
+            file.seek(SeekFrom::Start(skip))?;
+            dump(out, &mut file, skip, length)
+        }
+        None => {
+            // 直接读取描述符 0，不经过标准库的缓冲，-n 之后的输入留给后面的命令
+            // SAFETY: ManuallyDrop 保证不会关闭Shell的标准输入
+            let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(libc::STDIN_FILENO) });
+            io::copy(&mut (&*stdin).take(skip), &mut io::sink())?;
+            dump(out, &mut *stdin, skip, length)
+        }
+    }
+}
+
+// 从 input 读取至多 length 个字节并逐行输出，偏移从 start 开始计算
+fn dump(out: &mut impl Write, input: &mut impl Read, start: u64, length: Option<u64>) -> Result<(), ShellError> {
+    let mut input = input.take(length.unwrap_or(u64::MAX));
+    let mut out = BufWriter::new(out);
+    let mut offset = start;
+    let mut line = [0u8; BYTES_PER_LINE];
+
+    loop {
+        let n = read_line(&mut input, &mut line)?;
+        if n == 0 {
+            break;
+        }
+        writeln!(out, "{}", format_line(offset, &line[..n]))?;
+        offset += n as u64;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+// 尽量读满一行，只有到达输入结尾时才会少于一行
+fn read_line(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn format_line(offset: u64, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 && i % 2 == 0 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{:02x}", byte));
+    }
+
+    let text: String = bytes
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect();
+
+    // 两个字节一组，每组后一个空格，共 40 列
+    let width = BYTES_PER_LINE * 2 + BYTES_PER_LINE / 2 - 1;
+    format!("{:08x}: {:<width$}  {}", offset, hex, text, width = width)
+}
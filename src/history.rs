@@ -0,0 +1,226 @@
+use crate::glob;
+use crate::shell::Shell;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+// 判断一行输入是否应该记入历史
+// 隐私模式（set -o private）下一律不记录；
+// HISTCONTROL 包含 ignorespace 或 ignoreboth 时，以空格开头的行不记录；
+// 与 HISTIGNORE 中任一以冒号分隔的通配符模式（如 ls*:history*）匹配的行不记录
+pub fn should_record(shell: &Shell, line: &str) -> bool {
+    if shell.options.private {
+        return false;
+    }
+
+    if line.starts_with(' ') {
+        let ignore_space = shell
+            .vars
+            .get("HISTCONTROL")
+            .map(|control| {
+                control
+                    .split(':')
+                    .any(|item| item == "ignorespace" || item == "ignoreboth")
+            })
+            .unwrap_or(false);
+        if ignore_space {
+            return false;
+        }
+    }
+
+    if let Some(patterns) = shell.vars.get("HISTIGNORE") {
+        let trimmed = line.trim();
+        if patterns
+            .split(':')
+            .any(|pattern| !pattern.is_empty() && glob::matches(pattern, trimmed))
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+// 按项目目录划分的附加历史（set -o dirhistory）
+// 项目目录是最近的包含 .git 的上级目录，找不到时就是当前目录；
+// 进入一个项目时把它的历史追加到行编辑器的历史末尾，Ctrl-R 会优先搜到这些条目
+#[derive(Debug, Default)]
+pub struct DirHistory {
+    file: Option<PathBuf>,
+}
+
+impl DirHistory {
+    // 切换到 cwd 所在的项目，项目发生变化时返回该项目的历史条目
+    pub fn enter(&mut self, cwd: &Path, home: &str) -> Vec<String> {
+        let file = history_file(&project_root(cwd), home);
+        if self.file.as_ref() == Some(&file) {
+            return Vec::new();
+        }
+
+        let entries = fs::read_to_string(&file).map(|text| read_entries(&text)).unwrap_or_default();
+        self.file = Some(file);
+        entries
+    }
+
+    // 把一条命令追加到当前项目的历史文件，与主历史文件的格式相同，多行的命令仍然是一条
+    pub fn append(&self, line: &str) -> io::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        append_history(file, &[line.to_string()], false)
+    }
+}
+
+fn project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+// 项目历史保存在 ~/.rsh/dir_history/ 下，文件名由项目路径转义而来
+fn history_file(root: &Path, home: &str) -> PathBuf {
+    let name = root.to_string_lossy().replace('%', "%25").replace('/', "%2F");
+    Path::new(home).join(".rsh/dir_history").join(name)
+}
+
+// rustyline 历史文件的第一行
+const HISTORY_HEADER: &str = "#V2\n";
+
+// 把新增的条目追加到历史文件，而不是整个重写
+// 写入期间持有文件的独占锁，多个会话同时写入不会互相覆盖；sync 为真时写入后立即落盘
+pub fn append_history(path: &Path, entries: &[String], sync: bool) -> io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+    let _lock = FileLock::exclusive(&file)?;
+
+    // 使用 rustyline 的格式：文件以 #V2 开头，条目中的反斜杠和换行需要转义，
+    // 多行的条目重新读入时仍然是一条；新文件先写入开头，旧格式的文件先整个转换
+    let mut header = [0u8; HISTORY_HEADER.len()];
+    file.seek(SeekFrom::Start(0))?;
+    let mut text = String::new();
+    if file.read(&mut header)? < header.len() || header != HISTORY_HEADER.as_bytes() {
+        let mut existing = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut existing)?;
+        text.push_str(HISTORY_HEADER);
+        for line in String::from_utf8_lossy(&existing).lines() {
+            text.push_str(&line.replace('\\', "\\\\"));
+            text.push('\n');
+        }
+        file.set_len(0)?;
+    }
+
+    for entry in entries {
+        text.push_str(&entry.replace('\\', "\\\\").replace('\n', "\\n"));
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())?;
+    if sync {
+        file.sync_data()?;
+    }
+    Ok(())
+}
+
+// 历史文件中的条目：#V2 格式的文件去掉开头并还原转义，旧格式的文件每行一条
+fn read_entries(text: &str) -> Vec<String> {
+    let Some(body) = text.strip_prefix(HISTORY_HEADER) else {
+        return text.lines().map(str::to_string).collect();
+    };
+    body.lines().map(unescape).collect()
+}
+
+// append_history 的逆操作：\\ 还原为反斜杠，\n 还原为换行
+fn unescape(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                chars.next();
+                entry.push('\\');
+            }
+            ('\\', Some('n')) => {
+                chars.next();
+                entry.push('\n');
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
+
+// flock 独占锁，离开作用域时释放；必须先于文件本身被丢弃
+struct FileLock {
+    fd: RawFd,
+}
+
+impl FileLock {
+    fn exclusive(file: &fs::File) -> io::Result<Self> {
+        let fd = file.as_raw_fd();
+        // SAFETY: fd 来自一个打开的文件
+        if unsafe { libc::flock(fd, libc::LOCK_EX) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(FileLock { fd })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // SAFETY: 锁在文件关闭之前释放，fd 仍然有效
+        unsafe {
+            libc::flock(self.fd, libc::LOCK_UN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_path;
+
+    #[test]
+    fn new_file_gets_header_and_escaped_entries() {
+        let path = temp_path("history-new");
+        let _ = fs::remove_file(&path);
+        append_history(&path, &["echo a \\\n  b".to_string(), "ls".to_string()], false).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text, "#V2\necho a \\\\\\n  b\nls\n");
+    }
+
+    #[test]
+    fn legacy_file_is_converted() {
+        let path = temp_path("history-legacy");
+        fs::write(&path, "echo a\\b\npwd\n").unwrap();
+        append_history(&path, &["ls".to_string()], false).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text, "#V2\necho a\\\\b\npwd\nls\n");
+    }
+
+    #[test]
+    fn dir_history_keeps_multiline_entries() {
+        let home = temp_path("history-dir-home");
+        let project = temp_path("history-dir-project");
+        fs::create_dir_all(&project).unwrap();
+        let home_text = home.display().to_string();
+        let mut history = DirHistory::default();
+        assert!(history.enter(&project, &home_text).is_empty());
+        history.append("echo a \\\n  b").unwrap();
+        history.append("ls").unwrap();
+        let entries = DirHistory::default().enter(&project, &home_text);
+        fs::remove_dir_all(&home).unwrap();
+        fs::remove_dir_all(&project).unwrap();
+        assert_eq!(entries, ["echo a \\\n  b", "ls"]);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::ShellError;
+use crate::parser::{command_text, quote_word};
+use std::io::Write;
+
+// 钩子类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    // 命令执行之前
+    Preexec,
+    // 每次显示提示符之前
+    Precmd,
+    // 工作目录改变之后
+    Chpwd,
+}
+
+impl HookKind {
+    fn parse(name: &str) -> Result<HookKind, ShellError> {
+        match name {
+            "preexec" => Ok(HookKind::Preexec),
+            "precmd" => Ok(HookKind::Precmd),
+            "chpwd" => Ok(HookKind::Chpwd),
+            _ => Err(ShellError::CommandError(format!("hook: 未知的钩子类型 '{}'", name))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            HookKind::Preexec => "preexec",
+            HookKind::Precmd => "precmd",
+            HookKind::Chpwd => "chpwd",
+        }
+    }
+}
+
+// 已注册的钩子，每个钩子是一条命令行
+#[derive(Debug, Default)]
+pub struct Hooks {
+    preexec: Vec<String>,
+    precmd: Vec<String>,
+    chpwd: Vec<String>,
+}
+
+impl Hooks {
+    // 获取某一类型的全部钩子
+    pub fn get(&self, kind: HookKind) -> &[String] {
+        match kind {
+            HookKind::Preexec => &self.preexec,
+            HookKind::Precmd => &self.precmd,
+            HookKind::Chpwd => &self.chpwd,
+        }
+    }
+
+    fn get_mut(&mut self, kind: HookKind) -> &mut Vec<String> {
+        match kind {
+            HookKind::Preexec => &mut self.preexec,
+            HookKind::Precmd => &mut self.precmd,
+            HookKind::Chpwd => &mut self.chpwd,
+        }
+    }
+}
+
+// 内建命令 hook：
+//   hook add <类型> <命令>   注册钩子，执行时命令文本（chpwd为新目录）和上一次的状态码作为参数追加在后面
+//   hook rm <类型> <序号>    删除钩子
+//   hook clear [类型]        清空钩子
+//   hook [list]              列出钩子
+pub fn run_hook(out: &mut impl Write, hooks: &mut Hooks, args: &[String]) -> Result<(), ShellError> {
+    let sub = args.first().map(|a| a.as_str()).unwrap_or("list");
+
+    match sub {
+        "list" => {
+            for kind in [HookKind::Preexec, HookKind::Precmd, HookKind::Chpwd] {
+                for (i, line) in hooks.get(kind).iter().enumerate() {
+                    writeln!(out, "{} {}: {}", kind.name(), i + 1, line)?;
+                }
+            }
+        }
+        "add" => {
+            if args.len() < 3 {
+                return Err(ShellError::CommandError("用法: hook add <preexec|precmd|chpwd> <命令>".to_string()));
+            }
+            let kind = HookKind::parse(&args[1])?;
+            hooks.get_mut(kind).push(command_text(&args[2..]));
+        }
+        "rm" => {
+            if args.len() != 3 {
+                return Err(ShellError::CommandError("用法: hook rm <preexec|precmd|chpwd> <序号>".to_string()));
+            }
+            let kind = HookKind::parse(&args[1])?;
+            let list = hooks.get_mut(kind);
+            match args[2].parse::<usize>() {
+                Ok(n) if n >= 1 && n <= list.len() => {
+                    list.remove(n - 1);
+                }
+                _ => return Err(ShellError::CommandError(format!("hook: 无效的序号 '{}'", args[2]))),
+            }
+        }
+        "clear" => match args.get(1) {
+            Some(name) => hooks.get_mut(HookKind::parse(name)?).clear(),
+            None => {
+                hooks.preexec.clear();
+                hooks.precmd.clear();
+                hooks.chpwd.clear();
+            }
+        },
+        _ => return Err(ShellError::CommandError(format!("hook: 未知的子命令 '{}'", sub))),
+    }
+
+    Ok(())
+}
+
+// 内建命令 trap：目前只支持 EXIT（或 0）
+//   trap '命令' EXIT   Shell退出时（包括终端断开）执行命令
+//   trap - EXIT        删除
+//   trap               列出
+pub fn run_trap(out: &mut impl Write, exit_trap: &mut Option<String>, args: &[String]) -> Result<(), ShellError> {
+    match args {
+        [] => {
+            if let Some(command) = exit_trap {
+                writeln!(out, "trap -- {} EXIT", quote_word(command))?;
+            }
+            Ok(())
+        }
+        [action, signals @ ..] if !signals.is_empty() => {
+            for signal in signals {
+                if signal != "EXIT" && signal != "0" {
+                    return Err(ShellError::CommandError(format!("trap: 不支持的信号 '{}'", signal)));
+                }
+            }
+            *exit_trap = if action == "-" {
+                None
+            } else {
+                Some(action.clone())
+            };
+            Ok(())
+        }
+        _ => Err(ShellError::CommandError("用法: trap <命令|-> EXIT".to_string())),
+    }
+}
@@ -0,0 +1,368 @@
+use crate::disk::format_size;
+use crate::console;
+use crate::error::ShellError;
+use crate::procs::list_processes;
+use crate::signals::{describe_signal, fork_child};
+use crate::stdio::Stdio;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::FileExt;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+// jobs --tail 不指定行数时显示的行数
+const TAIL_LINES: usize = 10;
+// jobs --tail 最多从输出的末尾读取这么多字节
+const TAIL_BYTES: u64 = 64 * 1024;
+// 保留输出供查看的已结束作业数
+const KEEP_FINISHED: usize = 10;
+
+static OUTPUT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// 一个后台作业：在独立进程组中运行的子Shell
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: usize,
+    pub pid: libc::pid_t,
+    pub command: String,
+    // 作业本来要写到终端的输出，见 output_file
+    pub output: Option<Arc<File>>,
+    // 等待输入期间已经报告过结束，回收时不再报告
+    notified: Arc<AtomicBool>,
+    // 用 disown 标记过，Shell因终端断开而退出时不转发 SIGHUP
+    pub disowned: bool,
+}
+
+impl Job {
+    // 已经记录的输出的字节数
+    fn output_len(&self) -> u64 {
+        output_len(self.output.as_deref())
+    }
+}
+
+fn output_len(output: Option<&File>) -> u64 {
+    output
+        .and_then(|output| output.metadata().ok())
+        .map_or(0, |metadata| metadata.len())
+}
+
+// 后台作业表，作业编号从 1 开始，已经结束的编号可以重新使用
+#[derive(Debug, Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    // 已经结束、有输出的作业，jobs --tail 仍然可以查看，编号被重新使用时丢弃
+    finished: Vec<Job>,
+    // 管道中执行内建命令的子Shell继承的作业表：进程不是子Shell的子进程，只能查看，不回收
+    inherited: bool,
+}
+
+impl Jobs {
+    // 登记一个新作业，返回作业编号
+    pub fn add(&mut self, pid: libc::pid_t, command: String, output: Option<File>) -> usize {
+        let id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        self.finished.retain(|job| job.id != id);
+        let job = Job {
+            id,
+            pid,
+            command,
+            output: output.map(Arc::new),
+            notified: Arc::new(AtomicBool::new(false)),
+            disowned: false,
+        };
+        watch_job(&job);
+        self.jobs.push(job);
+        id
+    }
+
+    // 在 fork 出的子Shell中调用，此后作业表只供查看，作业仍由父Shell回收
+    pub fn inherit(&mut self) {
+        self.inherited = true;
+    }
+
+    // 不阻塞地回收已经结束的作业，返回它们和各自的等待状态；继承的作业表不回收
+    pub fn reap(&mut self) -> Vec<(Job, libc::c_int)> {
+        if self.inherited {
+            return Vec::new();
+        }
+        let mut finished = Vec::new();
+        self.jobs.retain(|job| {
+            let mut status = 0;
+            // SAFETY: 只等待作业表中登记的子进程
+            let ret = unsafe { libc::waitpid(job.pid, &mut status, libc::WNOHANG) };
+            if ret == 0 {
+                return true;
+            }
+            // ret < 0 时进程已经被回收（例如 ECHILD），同样从作业表中移除
+            finished.push((job.clone(), status));
+            false
+        });
+        for (job, _) in &finished {
+            if job.output_len() > 0 {
+                self.finished.push(job.clone());
+            }
+        }
+        let excess = self.finished.len().saturating_sub(KEEP_FINISHED);
+        self.finished.drain(..excess);
+        finished
+    }
+
+    // Shell因终端断开而退出时，把 SIGHUP 转发给所有作业的进程组，disown 过的作业除外
+    pub fn hangup(&self) {
+        for job in self.jobs.iter().filter(|job| !job.disowned) {
+            // SAFETY: 向作业的进程组发送信号；停止的作业需要 SIGCONT 才能处理 SIGHUP
+            unsafe {
+                libc::kill(-job.pid, libc::SIGHUP);
+                libc::kill(-job.pid, libc::SIGCONT);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+}
+
+// 在提示符出现之前报告已经结束的后台作业，等待输入期间已经报告过的除外
+pub fn report_finished(err: &mut impl Write, jobs: &mut Jobs) -> io::Result<()> {
+    for (job, status) in jobs.reap() {
+        if !job.notified.load(Ordering::Relaxed) {
+            writeln!(err, "{}", finished_message(job.id, status, &job.command, job.output_len()))?;
+        }
+    }
+    Ok(())
+}
+
+// 作业结束的通知；有记录下来的输出时提示用 jobs --tail 查看
+fn finished_message(id: usize, status: libc::c_int, command: &str, output_len: u64) -> String {
+    if output_len > 0 {
+        format!("[{}] {}  {}  （有输出，用 jobs --tail %{} 查看）", id, describe_status(status), command, id)
+    } else {
+        format!("[{}] {}  {}", id, describe_status(status), command)
+    }
+}
+
+// 用一个线程等待作业结束（WNOWAIT 只等待、不回收，仍然由 reap 回收），
+// 结束时Shell正在等待输入就立即报告，不必等到下一次出现提示符
+fn watch_job(job: &Job) {
+    let (id, pid, command) = (job.id, job.pid, job.command.clone());
+    let output = job.output.clone();
+    let notified = job.notified.clone();
+    thread::spawn(move || {
+        // SAFETY: siginfo_t 是纯数据结构，waitid 只向其中写入
+        let status = unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            if libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT) != 0 {
+                return;
+            }
+            // 换算成 waitpid 的状态，与 reap 的结果一致
+            match info.si_code {
+                libc::CLD_EXITED => (info.si_status() & 0xff) << 8,
+                _ => info.si_status() & 0x7f,
+            }
+        };
+        let message = finished_message(id, status, &command, output_len(output.as_deref()));
+        console::print_while_editing(&message, &notified);
+    });
+}
+
+// 内建命令 jobs：列出仍在运行的后台作业，-p 只输出进程号，
+// -l 同时显示作业中全部进程的CPU占用和常驻内存之和；
+// jobs --tail [%编号 [行数]] 显示作业输出的最后几行
+pub fn run_jobs(out: &mut impl Write, err: &mut impl Write, jobs: &mut Jobs, args: &[String]) -> Result<(), ShellError> {
+    let (pids_only, usage) = match args {
+        [] => (false, false),
+        [flag] if flag == "-p" => (true, false),
+        [flag] if flag == "-l" => (false, true),
+        [flag, rest @ ..] if flag == "--tail" => return tail(out, err, jobs, rest),
+        _ => return Err(ShellError::CommandError("用法: jobs [-p|-l] 或 jobs --tail [%编号 [行数]]".to_string())),
+    };
+
+    report_finished(err, jobs)?;
+    let procs = if usage { list_processes()? } else { Vec::new() };
+    for job in jobs.iter() {
+        if pids_only {
+            writeln!(out, "{}", job.pid)?;
+        } else if usage {
+            let members = procs.iter().filter(|p| p.pgrp == job.pid);
+            let (cpu, rss) = members.fold((0.0, 0), |(cpu, rss), p| (cpu + p.cpu_percent, rss + p.rss));
+            writeln!(
+                out,
+                "[{}] 运行中  {}  CPU {:.1}%  内存 {}  {}",
+                job.id,
+                job.pid,
+                cpu,
+                format_size(rss),
+                job.command
+            )?;
+        } else {
+            writeln!(out, "[{}] 运行中  {}  {}", job.id, job.pid, job.command)?;
+        }
+    }
+    Ok(())
+}
+
+// 内建命令 disown：disown [-a] [%编号 ...]
+// 标记作业，Shell因终端断开而退出时不向它发送 SIGHUP；不指定编号时为最近启动的作业，-a 为全部作业
+// 作业仍然留在作业表中，结束时照常回收和报告
+pub fn run_disown(jobs: &mut Jobs, args: &[String]) -> Result<(), ShellError> {
+    let usage = || ShellError::CommandError("用法: disown [-a] [%编号 ...]".to_string());
+    match args {
+        [flag] if flag == "-a" => jobs.jobs.iter_mut().for_each(|job| job.disowned = true),
+        [] => match jobs.jobs.last_mut() {
+            Some(job) => job.disowned = true,
+            None => return Err(ShellError::CommandError("disown: 没有后台作业".to_string())),
+        },
+        specs => {
+            for spec in specs {
+                let id = spec.strip_prefix('%').unwrap_or(spec).parse::<usize>().map_err(|_| usage())?;
+                let job = jobs.jobs.iter_mut().find(|job| job.id == id);
+                let job = job.ok_or_else(|| ShellError::CommandError(format!("disown: 没有作业 '{}'", spec)))?;
+                job.disowned = true;
+            }
+        }
+    }
+    Ok(())
+}
+
+// 显示作业输出的最后几行；不指定编号时为最近启动的作业，已经结束的作业也可以查看
+fn tail(out: &mut impl Write, err: &mut impl Write, jobs: &mut Jobs, args: &[String]) -> Result<(), ShellError> {
+    let usage = || ShellError::CommandError("用法: jobs --tail [%编号 [行数]]".to_string());
+    let (spec, count) = match args {
+        [] => (None, TAIL_LINES),
+        [spec] => (Some(spec), TAIL_LINES),
+        [spec, count] => (Some(spec), count.parse().map_err(|_| usage())?),
+        _ => return Err(usage()),
+    };
+
+    // 先回收已经结束的作业，使它们的输出转入 finished
+    report_finished(err, jobs)?;
+    let job = match spec {
+        None => jobs.jobs.last().or(jobs.finished.last()),
+        Some(spec) => {
+            let id = spec.strip_prefix('%').unwrap_or(spec).parse::<usize>().map_err(|_| usage())?;
+            let mut all = jobs.jobs.iter().chain(&jobs.finished);
+            all.find(|job| job.id == id)
+        }
+    };
+    let Some(job) = job else {
+        return Err(ShellError::CommandError(match spec {
+            Some(spec) => format!("jobs: 没有作业 '{}'", spec),
+            None => "jobs: 没有后台作业".to_string(),
+        }));
+    };
+    let Some(output) = &job.output else {
+        return Err(ShellError::CommandError(format!(
+            "jobs: 作业 %{} 的输出没有记录（启动时输出不是终端）",
+            job.id
+        )));
+    };
+
+    let len = job.output_len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    let mut bytes = vec![0; (len - start) as usize];
+    output.read_exact_at(&mut bytes, start)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    for line in &lines[lines.len().saturating_sub(count)..] {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// 后台作业的输出文件：标准输出或标准错误是终端时才创建，作业本来写到终端的内容改为写入其中，
+// 不再和提示符混在一起；文件的目录项立即删除，作业表不再保留这个作业时空间就被释放
+pub fn output_file(stdio: &Stdio) -> Result<Option<File>, ShellError> {
+    if !console::writes_to_terminal(stdio) {
+        return Ok(None);
+    }
+    let n = OUTPUT_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("rsh-job-{}-{}", process::id(), n));
+    let file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    Ok(Some(file))
+}
+
+// 在作业的子进程中把连接到终端的标准输出和标准错误改为输出文件或管道
+pub fn redirect_to_output(output: &impl AsRawFd) {
+    for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        // SAFETY: 只修改子进程自己的描述符
+        unsafe {
+            if libc::isatty(fd) != 0 {
+                libc::dup2(output.as_raw_fd(), fd);
+            }
+        }
+    }
+}
+
+// 作业结束时的状态描述
+fn describe_status(status: libc::c_int) -> String {
+    if libc::WIFEXITED(status) {
+        match libc::WEXITSTATUS(status) {
+            0 => "完成".to_string(),
+            code => format!("退出 {}", code),
+        }
+    } else if libc::WIFSIGNALED(status) {
+        describe_signal(libc::WTERMSIG(status), libc::WCOREDUMP(status))
+    } else {
+        "完成".to_string()
+    }
+}
+
+// 创建运行后台作业的子Shell：子进程进入自己的进程组，标准输入改为 /dev/null；
+// 返回值与 fork 相同，子进程中为 0
+pub fn fork_background() -> Result<libc::pid_t, ShellError> {
+    let pid = fork_child()?;
+
+    // SAFETY: 只修改子进程的进程组和标准输入
+    unsafe {
+        // 父子进程都设置一次进程组，避免之后发送信号时子进程还没来得及设置
+        let child = if pid == 0 { libc::getpid() } else { pid };
+        libc::setpgid(child, child);
+
+        if pid == 0 {
+            let null = libc::open(c"/dev/null".as_ptr(), libc::O_RDONLY);
+            if null >= 0 {
+                libc::dup2(null, libc::STDIN_FILENO);
+                libc::close(null);
+            }
+        }
+        Ok(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inherited_table_is_not_reaped() {
+        // 进程 1 不是测试进程的子进程，与子Shell看到的父Shell的作业一样
+        let mut jobs = Jobs::default();
+        jobs.add(1, "init".to_string(), None);
+        jobs.inherit();
+        assert!(jobs.reap().is_empty());
+        assert_eq!(jobs.iter().count(), 1);
+    }
+
+    #[test]
+    fn disown_marks_jobs() {
+        let mut jobs = Jobs::default();
+        jobs.add(1, "a".to_string(), None);
+        jobs.add(1, "b".to_string(), None);
+        jobs.add(1, "c".to_string(), None);
+        run_disown(&mut jobs, &[]).unwrap();
+        run_disown(&mut jobs, &["%1".to_string()]).unwrap();
+        let disowned: Vec<bool> = jobs.iter().map(|job| job.disowned).collect();
+        assert_eq!(disowned, [true, false, true]);
+        assert!(run_disown(&mut jobs, &["%4".to_string()]).is_err());
+        run_disown(&mut jobs, &["-a".to_string()]).unwrap();
+        assert!(jobs.iter().all(|job| job.disowned));
+    }
+}
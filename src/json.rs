@@ -0,0 +1,121 @@
+// 手写的最小JSON输出工具，避免为少量机器可读输出引入序列化依赖
+
+use crate::parser::{AndOrList, Command, Group, ParamOp, Redirect, Segment, Word};
+
+// 将字符串编码为带引号的JSON字符串
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// 语法树的JSON表示，供 --dump-ast 使用：列表是AndOrList的数组，
+// 管道是命令的数组，命令和词的每一段是带 "type" 字段的对象
+pub fn ast(lists: &[AndOrList]) -> String {
+    array(lists, and_or_list)
+}
+
+fn array<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    let items: Vec<String> = items.iter().map(f).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn and_or_list(list: &AndOrList) -> String {
+    format!(
+        "{{\"pipelines\":{},\"connectors\":{},\"background\":{}}}",
+        array(&list.pipelines, |pipeline| array(pipeline, command)),
+        array(&list.connectors, |connector| quote(connector.symbol())),
+        list.background
+    )
+}
+
+fn command(command: &Command) -> String {
+    let redirects = array(command.redirects(), redirect);
+    match command {
+        Command::Compound(compound) => {
+            let kind = match compound.group {
+                Group::Subshell(_) => "subshell",
+                Group::Brace(_) => "brace",
+            };
+            format!(
+                "{{\"type\":\"{}\",\"body\":{},\"redirects\":{}}}",
+                kind,
+                ast(compound.group.lists()),
+                redirects
+            )
+        }
+        Command::Simple(command) => format!(
+            "{{\"type\":\"simple\",\"assignments\":{},\"words\":{},\"redirects\":{}}}",
+            array(&command.assignments, |(name, value)| {
+                format!("{{\"name\":{},\"value\":{}}}", quote(name), word(value))
+            }),
+            array(&command.words, word),
+            redirects
+        ),
+    }
+}
+
+fn redirect(redirect: &Redirect) -> String {
+    format!(
+        "{{\"fd\":{},\"op\":{},\"target\":{}}}",
+        redirect.fd,
+        quote(redirect.kind.symbol()),
+        word(&redirect.target)
+    )
+}
+
+// 词带有它的原文 text，便于阅读
+fn word(word: &Word) -> String {
+    format!(
+        "{{\"text\":{},\"segments\":{}}}",
+        quote(&word.source()),
+        array(&word.segments, segment)
+    )
+}
+
+fn segment(segment: &Segment) -> String {
+    match segment {
+        Segment::Plain(s) => format!("{{\"type\":\"plain\",\"text\":{}}}", quote(s)),
+        Segment::Single(s) => format!("{{\"type\":\"single\",\"text\":{}}}", quote(s)),
+        Segment::Double(s) => format!("{{\"type\":\"double\",\"text\":{}}}", quote(s)),
+        Segment::Var { name, quoted } => {
+            format!("{{\"type\":\"var\",\"name\":{},\"quoted\":{}}}", quote(name), quoted)
+        }
+        Segment::Param { name, op, word: operand, quoted } => {
+            let op = match op {
+                ParamOp::Length => "#",
+                ParamOp::Element => "[]",
+                ParamOp::Count => "#[]",
+                op => op.symbol(),
+            };
+            format!(
+                "{{\"type\":\"param\",\"name\":{},\"op\":{},\"word\":{},\"quoted\":{}}}",
+                quote(name),
+                quote(op),
+                word(operand),
+                quoted
+            )
+        }
+        Segment::Subst { command, quoted } => {
+            format!("{{\"type\":\"subst\",\"command\":{},\"quoted\":{}}}", quote(command), quoted)
+        }
+        Segment::Arith(expr) => format!("{{\"type\":\"arith\",\"expr\":{}}}", quote(expr)),
+        Segment::ProcSubst { command, output } => format!(
+            "{{\"type\":\"procsubst\",\"command\":{},\"output\":{}}}",
+            quote(command),
+            output
+        ),
+    }
+}
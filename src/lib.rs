@@ -0,0 +1,67 @@
+pub mod alias;
+pub mod arith;
+pub mod batch;
+pub mod bookmark;
+pub mod brace;
+pub mod capture;
+pub mod checksum;
+pub mod cli;
+pub mod command;
+pub mod completion;
+pub mod console;
+pub mod date;
+pub mod diagnostic;
+pub mod disk;
+pub mod each;
+pub mod error;
+pub mod expand;
+#[cfg(feature = "archive")]
+pub mod extract;
+#[cfg(feature = "fetch")]
+pub mod fetch;
+pub mod glob;
+pub mod hexdump;
+pub mod history;
+pub mod hooks;
+pub mod jobs;
+pub mod json;
+pub mod math;
+pub mod mock;
+pub mod options;
+pub mod palette;
+pub mod parser;
+pub mod pathcache;
+pub mod pathglob;
+pub mod pathutil;
+pub mod random;
+pub mod schedule;
+pub mod read;
+pub mod redirect;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod procs;
+pub mod procsubst;
+pub mod rusage;
+pub mod server;
+pub mod shell;
+pub mod shtest;
+pub mod signals;
+pub mod spawn;
+pub mod startup;
+pub mod stdio;
+pub mod strings;
+pub mod style;
+pub mod task;
+pub mod terminal;
+#[cfg(test)]
+mod testutil;
+pub mod trash;
+pub mod tutor;
+pub mod vars;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_plugin;
+
+pub use crate::error::ShellError;
+pub use crate::shell::{Output, Shell};
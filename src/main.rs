@@ -1,22 +1,195 @@
-mod command;
-mod error;
-mod parser;
-
-use crate::command::execute_command;
-use crate::parser::parse_input;
+use lab3::batch::run_json;
+use lab3::cli::parse_args;
+use lab3::completion::ShellHelper;
+use lab3::console;
+use lab3::diagnostic;
+use lab3::error::ShellError;
+use lab3::history::{append_history, should_record, DirHistory};
+use lab3::hooks::HookKind;
+use lab3::jobs::report_finished;
+use lab3::palette::{show_palette, Choice, PaletteKey};
+use lab3::parser::parse_input;
+use lab3::server::serve;
+use lab3::shell::Shell;
+use lab3::signals::{hangup_received, install_hangup_handler, install_resize_handler};
+use lab3::startup::StartupProfile;
+use lab3::style;
+use lab3::terminal::update_window_size;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use rustyline::{Editor, EventHandler, KeyEvent};
 use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// 读取启动文件，文件不存在时只有显式指定的才报错；启动文件中的 exit 结束Shell
+fn source_startup_file(shell: &mut Shell, path: &Path, required: bool) {
+    if !required && !path.exists() {
+        return;
+    }
+    match shell.source_file(path) {
+        Ok(_) => {}
+        Err(ShellError::Exit(status)) => {
+            shell.shutdown();
+            process::exit(status);
+        }
+        Err(e) => eprintln!("无法读取启动文件 {}: {}", path.display(), e),
+    }
+}
+
+// 读取一行输入；按 Ctrl-P 时打开命令面板，选中的条目直接作为输入，
+// 或者插入到正在编辑的行中继续编辑
+fn read_line(rl: &mut Editor<ShellHelper>, prompt: &str, palette_key: &PaletteKey, shell: &Shell) -> rustyline::Result<String> {
+    let mut result = edit(rl, prompt, "");
+    while let Some(editing) = palette_key.take() {
+        let initial = match show_palette(shell) {
+            Ok(Choice::Execute(command)) => {
+                println!("{}{}", prompt, command);
+                return Ok(command);
+            }
+            Ok(Choice::Insert(text)) if editing.is_empty() || editing.ends_with(char::is_whitespace) => editing + &text,
+            Ok(Choice::Insert(text)) => format!("{} {}", editing, text),
+            Ok(Choice::Cancel) => editing,
+            Err(e) => {
+                diagnostic::report(&mut shell.stdio.err(), &e);
+                editing
+            }
+        };
+        result = edit(rl, prompt, &initial);
+    }
+    result
+}
+
+// 用 initial 作为初始内容读取一行；读取期间 console 知道提示符和输入的内容，
+// 后台作业的输出可以打印在输入行之上而不打乱它
+fn edit(rl: &mut Editor<ShellHelper>, prompt: &str, initial: &str) -> rustyline::Result<String> {
+    console::begin_edit(prompt, initial);
+    let result = if initial.is_empty() {
+        rl.readline(prompt)
+    } else {
+        rl.readline_with_initial(prompt, (initial, ""))
+    };
+    console::end_edit();
+    result
+}
+
+// 输入不完整（例如以反斜杠结尾）时用 PS2 提示符（默认为 "> "）接着读取下一行，
+// 各行用换行连接；按 Ctrl-C 放弃整个输入时返回 None，Ctrl-D 时按已有的输入执行
+fn read_continuation(rl: &mut Editor<ShellHelper>, shell: &Shell, mut line: String) -> Option<String> {
+    let prompt = shell.vars.get("PS2").unwrap_or("> ").to_string();
+    while let Err(ShellError::Incomplete(..)) = parse_input(&line, &shell.aliases) {
+        match edit(rl, &prompt, "") {
+            Ok(more) => {
+                line.push('\n');
+                line.push_str(&more);
+            }
+            Err(ReadlineError::Interrupted) => return None,
+            Err(_) => break,
+        }
+    }
+    Some(line)
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut profile = StartupProfile::new();
+    let cli = parse_args();
+    
+    let mut shell = profile.time("环境变量", Shell::new);
+    shell.options.posix = cli.posix;
+    shell.options.noexec = cli.syntax_check;
+    shell.dump_ast = cli.dump_ast;
+    // $0 是脚本名（-c 时为命令字符串之后的第一个参数），其后是位置参数
+    shell.positional.push(cli.script.clone().unwrap_or_else(|| "rsh".to_string()));
+    shell.positional.extend(cli.script_args.iter().cloned());
+    
+    // 加载插件目录中的内建命令
+    #[cfg(any(feature = "plugins", feature = "wasm-plugins"))]
+    profile.time("插件加载", || shell.load_plugins());
+    
+    // 服务器模式：--server <套接字路径>
+    if let Some(path) = &cli.server {
+        serve(&mut shell, path)?;
+        return Ok(());
+    }
+    
+    // 批处理模式：--output json，从标准输入读取命令，每条命令输出一条JSON记录
+    if cli.json_output {
+        let status = run_json(&mut shell, io::stdin().lock())?;
+        process::exit(status);
+    }
+    
+    let home = shell.vars.get("HOME").map(PathBuf::from);
+    
+    // 登录Shell先读取 ~/.rsh_profile
+    if cli.login
+        && let Some(home) = &home
+    {
+        profile.time("登录文件", || {
+            source_startup_file(&mut shell, &home.join(".rsh_profile"), false)
+        });
+    }
+    
+    // 非交互模式没有启动文件和历史记录，在执行命令之前打印
+    if cli.profile_startup && !cli.interactive() {
+        profile.report();
+    }
+    
+    // -c：执行命令字符串后退出，状态码为该命令的状态码
+    if let Some(command) = &cli.command {
+        // 执行了 exit 时 last_status 就是它的状态码
+        let _ = shell.run_line(command);
+        shell.shutdown();
+        process::exit(shell.last_status);
+    }
+    
+    // 执行脚本文件后退出
+    if let Some(script) = &cli.script {
+        match shell.source_file(Path::new(script)) {
+            Ok(_) | Err(ShellError::Exit(_)) => {}
+            Err(e) => {
+                eprintln!("rsh: {}: {}", script, e);
+                process::exit(127);
+            }
+        }
+        shell.shutdown();
+        process::exit(shell.last_status);
+    }
+    
+    // 交互模式读取启动文件：--rcfile 指定的文件，POSIX 模式下为 $ENV，否则为 ~/.rshrc
+    let rcfile = match &cli.rcfile {
+        Some(path) => Some(path.clone()),
+        None if cli.posix => shell.vars.get("ENV").map(PathBuf::from),
+        None => home.as_ref().map(|home| home.join(".rshrc")),
+    };
+    if let Some(rcfile) = rcfile {
+        profile.time("启动文件", || {
+            source_startup_file(&mut shell, &rcfile, cli.rcfile.is_some())
+        });
+    }
+    
     println!("欢迎使用Rust Shell！输入 'exit' 退出。");
     
+    // 终端断开时有序退出，而不是在写入途中被直接终止
+    install_hangup_handler()?;
+    
     // 创建一个readline编辑器
-    let mut rl = Editor::<()>::new();
-    if rl.load_history("history.txt").is_err() {
+    let mut rl = Editor::<ShellHelper>::new();
+    rl.set_helper(Some(ShellHelper::new(shell.completions.clone(), shell.path_cache.clone())));
+    // Ctrl-P 打开命令面板
+    let palette_key = PaletteKey::default();
+    rl.bind_sequence(KeyEvent::ctrl('P'), EventHandler::Conditional(Box::new(palette_key.clone())));
+    // 命令面板在终端大小改变时重画；要在行编辑器安装了自己的处理函数之后安装
+    install_resize_handler()?;
+    // 历史文件位于启动时的目录，之后 cd 不影响它的位置
+    let history_path = env::current_dir()?.join("history.txt");
+    if profile.time("历史记录", || rl.load_history(&history_path)).is_err() {
         println!("没有历史记录。");
     }
     
+    if cli.profile_startup {
+        profile.report();
+    }
+    
     // 获取当前用户名和主机名显示在提示符中
     let username = env::var("USER").unwrap_or_else(|_| "user".to_string());
     let hostname = match std::process::Command::new("hostname").output() {
@@ -24,41 +197,84 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err(_) => "unknown".to_string(),
     };
     
+    // 标准输入是终端时才是交互地输入命令
+    let interactive = io::stdin().is_terminal();
+    
+    // 上一条执行的命令文本，传给precmd钩子
+    let mut last_line = String::new();
+    
+    // 按项目目录划分的历史
+    let mut dir_history = DirHistory::default();
+    
     loop {
+        if hangup_received() {
+            break;
+        }
+        
+        // 终端大小可能在上一条命令执行期间改变
+        update_window_size(&mut shell.vars);
+        
+        let _ = report_finished(&mut shell.stdio.err(), &mut shell.jobs);
+        
+        shell.run_hooks(HookKind::Precmd, &last_line);
+        
         // 获取当前工作目录
         let current_dir = env::current_dir()?;
         let dir_display = current_dir.display();
         
+        // 进入新的项目目录时载入其历史
+        if shell.options.dirhistory {
+            let home = shell.vars.get("HOME").unwrap_or("/").to_string();
+            for entry in dir_history.enter(&current_dir, &home) {
+                rl.add_history_entry(entry);
+            }
+        }
+        
         // 提示符
         let prompt = format!("{}@{}:{} $ ", username, hostname, dir_display);
         
         // 读取一行输入
-        match rl.readline(&prompt) {
+        match read_line(&mut rl, &prompt, &palette_key, &shell) {
             Ok(line) => {
                 if line.trim().is_empty() {
                     continue;
                 }
+                let Some(line) = read_continuation(&mut rl, &shell, line) else {
+                    continue;
+                };
                 
-                rl.add_history_entry(line.as_str());
+                if should_record(&shell, &line) {
+                    rl.add_history_entry(line.as_str());
+                    
+                    // 立即写入历史文件，崩溃或被杀死时也不会丢失
+                    let entry = [line.clone()];
+                    if let Err(e) = append_history(&history_path, &entry, shell.options.histfsync) {
+                        eprintln!("无法写入历史: {}", e);
+                    }
+                    if shell.options.dirhistory
+                        && let Err(e) = dir_history.append(&line)
+                    {
+                        eprintln!("无法写入项目历史: {}", e);
+                    }
+                }
+                last_line = line.clone();
                 
-                if line.trim() == "exit" {
-                    println!("再见！");
-                    break;
+                // 与 bash 一样 set -n 对终端输入的命令不起作用，否则打开之后就无法再关闭；
+                // 从管道读取命令时（echo ... | rsh -n）仍然只检查语法
+                if interactive {
+                    shell.options.noexec = false;
                 }
                 
-                // 解析输入
-                match parse_input(&line) {
-                    Ok(commands) => {
-                        // 执行命令
-                        if let Err(e) = execute_command(commands) {
-                            eprintln!("错误: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("解析错误: {}", e);
-                    }
+                let parsed = parse_input(&line, &shell.aliases);
+                if parsed.is_ok() && !shell.options.noexec && !shell.dump_ast {
+                    shell.run_hooks(HookKind::Preexec, &line);
+                }
+                if let Err(ShellError::Exit(_)) = shell.run_parsed(&line, parsed) {
+                    println!("再见！");
+                    break;
                 }
             }
+            Err(_) if hangup_received() => break,
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
                 continue;
@@ -68,12 +284,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 break;
             }
             Err(err) => {
-                eprintln!("错误: {:?}", err);
+                eprintln!("{} {:?}", style::error("错误:"), err);
                 break;
             }
         }
     }
     
-    rl.save_history("history.txt")?;
-    Ok(())
+    // 终端断开时后台作业也应当收到 SIGHUP
+    if hangup_received() {
+        shell.jobs.hangup();
+    }
+    
+    // exit 和 Ctrl-D 时以最后一条命令的状态码退出
+    shell.shutdown();
+    process::exit(shell.last_status);
 }
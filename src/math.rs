@@ -0,0 +1,327 @@
+use crate::error::ShellError;
+use crate::vars::Variables;
+use std::io::Write;
+
+// 内建命令 math：math "1.5 * sin(0.2)"，多个参数以空格连接后求值
+// 支持 + - * / % ^、括号、常量 pi 和 e、常用函数，其他名字按Shell变量取值
+pub fn run_math(out: &mut impl Write, vars: &Variables, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("用法: math <表达式>".to_string()));
+    }
+
+    let value = evaluate(&args.join(" "), vars)?;
+    writeln!(out, "{}", format_number(value))?;
+    Ok(())
+}
+
+// 对浮点表达式求值
+pub fn evaluate(input: &str, vars: &Variables) -> Result<f64, ShellError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        vars,
+    };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(error(format!("无法识别的字符 '{}'", c)));
+    }
+    if value.is_nan() {
+        return Err(error("结果不是数字".to_string()));
+    }
+    Ok(value)
+}
+
+// 整数值按整数打印，其余使用最短的精确表示
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+fn error(message: String) -> ShellError {
+    ShellError::CommandError(format!("math: {}", message))
+}
+
+// 递归下降求值，优先级从低到高：加减、乘除取余、一元正负、乘方（右结合）
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    vars: &'a Variables,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // 跳过空白后如果下一个字符是 c 则消耗它
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, ShellError> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, ShellError> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err(error("除数为零".to_string()));
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err(error("除数为零".to_string()));
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, ShellError> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, ShellError> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<f64, ShellError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expr()?;
+                if !self.eat(')') {
+                    return Err(error("缺少 ')'".to_string()));
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.identifier();
+                if self.eat('(') {
+                    let args = self.arguments()?;
+                    call(&name, &args)
+                } else {
+                    self.constant(&name)
+                }
+            }
+            Some(c) => Err(error(format!("无法识别的字符 '{}'", c))),
+            None => Err(error("表达式不完整".to_string())),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, ShellError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        // 指数部分，例如 1.5e-3
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some('+') | Some('-')) {
+                self.pos += 1;
+            }
+            if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            } else {
+                self.pos = mark;
+            }
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map_err(|_| error(format!("无效的数字 '{}'", text)))
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // 函数参数列表，开括号已经读过
+    fn arguments(&mut self) -> Result<Vec<f64>, ShellError> {
+        let mut args = Vec::new();
+        if self.eat(')') {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat(')') {
+                return Ok(args);
+            }
+            if !self.eat(',') {
+                return Err(error("函数参数之间需要 ','".to_string()));
+            }
+        }
+    }
+
+    fn constant(&self, name: &str) -> Result<f64, ShellError> {
+        match name {
+            "pi" => Ok(std::f64::consts::PI),
+            "e" => Ok(std::f64::consts::E),
+            _ => {
+                let value = self
+                    .vars
+                    .get(name)
+                    .ok_or_else(|| error(format!("未知的名字 '{}'", name)))?;
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| error(format!("变量 {} 的值 '{}' 不是数字", name, value)))
+            }
+        }
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, ShellError> {
+    let unary: Option<fn(f64) -> f64> = match name {
+        "sin" => Some(f64::sin),
+        "cos" => Some(f64::cos),
+        "tan" => Some(f64::tan),
+        "asin" => Some(f64::asin),
+        "acos" => Some(f64::acos),
+        "atan" => Some(f64::atan),
+        "sqrt" => Some(f64::sqrt),
+        "abs" => Some(f64::abs),
+        "exp" => Some(f64::exp),
+        "ln" => Some(f64::ln),
+        "log" | "log10" => Some(f64::log10),
+        "log2" => Some(f64::log2),
+        "floor" => Some(f64::floor),
+        "ceil" => Some(f64::ceil),
+        "round" => Some(f64::round),
+        _ => None,
+    };
+    if let Some(f) = unary {
+        return match args {
+            [x] => Ok(f(*x)),
+            _ => Err(error(format!("{} 需要 1 个参数", name))),
+        };
+    }
+
+    match (name, args) {
+        ("pow", [x, y]) => Ok(x.powf(*y)),
+        ("atan2", [y, x]) => Ok(y.atan2(*x)),
+        ("min", [first, rest @ ..]) => Ok(rest.iter().fold(*first, |a, b| a.min(*b))),
+        ("max", [first, rest @ ..]) => Ok(rest.iter().fold(*first, |a, b| a.max(*b))),
+        ("pow" | "atan2", _) => Err(error(format!("{} 需要 2 个参数", name))),
+        ("min" | "max", _) => Err(error(format!("{} 至少需要 1 个参数", name))),
+        _ => Err(error(format!("未知的函数 '{}'", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> Result<f64, ShellError> {
+        evaluate(input, &Variables::default())
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(eval("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(eval("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(eval("2 ^ -1").unwrap(), 0.5);
+        assert_eq!(eval("7 % 4 * 2").unwrap(), 6.0);
+        assert_eq!(eval("(1 + 2) / 4").unwrap(), 0.75);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(eval("1.5e-3 * 1000").unwrap(), 1.5);
+        assert_eq!(eval(".5 + 1E2").unwrap(), 100.5);
+        assert!(eval("1.2.3").is_err());
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_eq!(eval("sqrt(16) + abs(-2)").unwrap(), 6.0);
+        assert_eq!(eval("max(1, 5, 3) - min(4)").unwrap(), 1.0);
+        assert_eq!(eval("pow(2, 10)").unwrap(), 1024.0);
+        assert!((eval("sin(pi / 2)").unwrap() - 1.0).abs() < 1e-12);
+        assert!((eval("ln(e)").unwrap() - 1.0).abs() < 1e-12);
+        assert!(eval("sqrt(1, 2)").is_err());
+        assert!(eval("min()").is_err());
+        assert!(eval("nosuch(1)").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 % 0").is_err());
+        assert!(eval("sqrt(-1)").is_err());
+        assert!(eval("1 +").is_err());
+        assert!(eval("(1").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("unknown").is_err());
+    }
+
+    #[test]
+    fn variables() {
+        let mut vars = Variables::default();
+        vars.set("rate", " 0.25 ");
+        vars.set("name", "abc");
+        assert_eq!(evaluate("rate * 8", &vars).unwrap(), 2.0);
+        assert!(evaluate("name + 1", &vars).is_err());
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(f64::INFINITY), "inf");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::diagnostic;
+use crate::error::ShellError;
+use crate::redirect::{install_child_redirects, OpenRedirect};
+use crate::signals::fork_child;
+use crate::stdio::Stdio;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+// 一个假命令：执行时不启动真正的程序，只输出给定的内容并以给定的状态码退出
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mock {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+    // 被调用的次数
+    pub calls: usize,
+}
+
+// 假命令表，只在 shtest 运行测试的Shell中启用
+#[derive(Debug, Default)]
+pub struct Mocks {
+    enabled: bool,
+    commands: BTreeMap<String, Mock>,
+}
+
+impl Mocks {
+    // 测试用的假命令表
+    pub fn enabled() -> Self {
+        Mocks {
+            enabled: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn get_mut(&mut self, program: &str) -> Option<&mut Mock> {
+        self.commands.get_mut(program)
+    }
+}
+
+// 内建命令 mock：mock 命令 [--exit 状态码] [--stdout 文本] [--stderr 文本]
+// 此后执行该外部命令（包括管道中的）时改为输出给定的文本；不带参数时列出假命令和调用次数
+pub fn run_mock(out: &mut impl Write, mocks: &mut Mocks, args: &[String]) -> Result<(), ShellError> {
+    if !mocks.enabled {
+        return Err(ShellError::CommandError("mock: 只能在 shtest 运行的测试中使用".to_string()));
+    }
+
+    let Some((program, options)) = args.split_first() else {
+        for (program, mock) in &mocks.commands {
+            writeln!(out, "{}  状态码 {}  调用 {} 次", program, mock.status, mock.calls)?;
+        }
+        return Ok(());
+    };
+
+    let mut mock = Mock::default();
+    let mut iter = options.iter();
+    while let Some(option) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| ShellError::CommandError(format!("mock: {} 需要参数", option)))?;
+        match option.as_str() {
+            "--exit" => {
+                mock.status = value
+                    .parse()
+                    .map_err(|_| ShellError::CommandError(format!("mock: 无效的状态码 '{}'", value)))?;
+            }
+            "--stdout" => mock.stdout = with_newline(value),
+            "--stderr" => mock.stderr = with_newline(value),
+            _ => return Err(ShellError::CommandError(format!("mock: 未知的选项 '{}'", option))),
+        }
+    }
+    mocks.commands.insert(program.clone(), mock);
+    Ok(())
+}
+
+// 内建命令 unmock：unmock 命令...，恢复执行真正的程序
+pub fn run_unmock(mocks: &mut Mocks, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("用法: unmock 命令...".to_string()));
+    }
+    for program in args {
+        if mocks.commands.remove(program).is_none() {
+            return Err(ShellError::CommandError(format!("unmock: '{}' 不是假命令", program)));
+        }
+    }
+    Ok(())
+}
+
+// 和 echo 一样，非空的输出以换行结尾
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+// 在子进程中执行假命令，标准描述符按管道和重定向设置，返回子进程号
+pub fn spawn_mock(mock: &Mock, mut stdio: Stdio, files: &[OpenRedirect]) -> Result<libc::pid_t, ShellError> {
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    let pid = fork_child()?;
+    if pid == 0 {
+        let status = match install_child_redirects(files, &mut stdio) {
+            Ok(()) => {
+                // 读端已经关闭时写入失败，与真正的程序一样忽略
+                let _ = stdio.out().write_all(mock.stdout.as_bytes());
+                let _ = stdio.err().write_all(mock.stderr.as_bytes());
+                mock.status
+            }
+            Err(e) => {
+                diagnostic::report(&mut stdio.err(), &e);
+                1
+            }
+        };
+        // SAFETY: 子进程直接退出，输出已经写出
+        unsafe { libc::_exit(status) }
+    }
+    Ok(pid)
+}
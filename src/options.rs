@@ -0,0 +1,110 @@
+use crate::error::ShellError;
+use std::io::Write;
+
+// Shell选项，通过 set -o/+o 开关
+#[derive(Debug, Default)]
+pub struct ShellOptions {
+    // 每条前台命令结束后打印资源使用情况
+    pub rusage: bool,
+    // 隐私模式：不记录任何历史
+    pub private: bool,
+    // 额外维护按项目目录划分的历史
+    pub dirhistory: bool,
+    // 每条历史写入后立即 fsync
+    pub histfsync: bool,
+    // POSIX 兼容模式，由 --posix 打开
+    pub posix: bool,
+    // > 不覆盖已有的文件，也可以用 set -C 打开
+    pub noclobber: bool,
+    // 执行前显示展开后的命令并请求确认
+    pub preview: bool,
+    // rm 的通配符展开出很多文件时先确认
+    pub saferm: bool,
+    // > 等重定向创建文件时也创建不存在的父目录
+    pub mkdirs: bool,
+    // 后台作业写到终端的输出逐行加上 [job N] 前缀，打印在输入行之上
+    pub bg_prefix: bool,
+    // 只解析命令、报告语法错误而不执行，也可以用 set -n 或命令行的 --syntax-check 打开；
+    // 交互输入每一行之前关闭
+    pub noexec: bool,
+    // 命令以非零状态结束时打印原因，并且不再执行这一行中之后的列表和脚本中之后的行；
+    // && 和 || 列表中最后一个管道之前的管道失败时不会这样；也可以用 set -e 打开。关闭时非零状态只记录在 $? 中
+    pub errexit: bool,
+}
+
+impl ShellOptions {
+    // 全部选项名，用于列出和查找
+    const NAMES: &'static [&'static str] = &["bg-prefix", "dirhistory", "errexit", "histfsync", "mkdirs", "noclobber", "noexec", "posix", "preview", "private", "rusage", "saferm"];
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "rusage" => Some(&mut self.rusage),
+            "private" => Some(&mut self.private),
+            "dirhistory" => Some(&mut self.dirhistory),
+            "histfsync" => Some(&mut self.histfsync),
+            "posix" => Some(&mut self.posix),
+            "noclobber" => Some(&mut self.noclobber),
+            "preview" => Some(&mut self.preview),
+            "saferm" => Some(&mut self.saferm),
+            "mkdirs" => Some(&mut self.mkdirs),
+            "bg-prefix" => Some(&mut self.bg_prefix),
+            "noexec" => Some(&mut self.noexec),
+            "errexit" => Some(&mut self.errexit),
+            _ => None,
+        }
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        match name {
+            "rusage" => self.rusage,
+            "private" => self.private,
+            "dirhistory" => self.dirhistory,
+            "histfsync" => self.histfsync,
+            "posix" => self.posix,
+            "noclobber" => self.noclobber,
+            "preview" => self.preview,
+            "saferm" => self.saferm,
+            "mkdirs" => self.mkdirs,
+            "bg-prefix" => self.bg_prefix,
+            "noexec" => self.noexec,
+            "errexit" => self.errexit,
+            _ => false,
+        }
+    }
+}
+
+// 内建命令 set：set -o <选项> 打开，set +o <选项> 关闭，set -o 列出全部选项
+// set -C / set +C 是 set -o noclobber / set +o noclobber 的简写，set -n / set +n 是 noexec 的简写，
+// set -e / set +e 是 errexit 的简写
+pub fn run_set(out: &mut impl Write, options: &mut ShellOptions, args: &[String]) -> Result<(), ShellError> {
+    match args {
+        [] => Ok(()),
+        [flag] if flag == "-C" || flag == "+C" => {
+            options.noclobber = flag == "-C";
+            Ok(())
+        }
+        [flag] if flag == "-n" || flag == "+n" => {
+            options.noexec = flag == "-n";
+            Ok(())
+        }
+        [flag] if flag == "-e" || flag == "+e" => {
+            options.errexit = flag == "-e";
+            Ok(())
+        }
+        [flag] if flag == "-o" || flag == "+o" => {
+            for name in ShellOptions::NAMES {
+                let state = if options.flag(name) { "on" } else { "off" };
+                writeln!(out, "{:<15} {}", name, state)?;
+            }
+            Ok(())
+        }
+        [flag, name] if flag == "-o" || flag == "+o" => match options.flag_mut(name) {
+            Some(value) => {
+                *value = flag == "-o";
+                Ok(())
+            }
+            None => Err(ShellError::CommandError(format!("set: 未知的选项 '{}'", name))),
+        },
+        _ => Err(ShellError::CommandError("用法: set -o|+o [选项] 或 set -C|+C、-e|+e、-n|+n".to_string())),
+    }
+}
@@ -0,0 +1,366 @@
+use crate::bookmark::load_bookmarks;
+use crate::command::BUILTINS;
+use crate::error::ShellError;
+use crate::parser::quote_word;
+use crate::read::{read_byte, wait_readable, TerminalMode};
+use crate::shell::Shell;
+use crate::signals::take_resized;
+use crate::style::{self, Role, Stream};
+use crate::terminal::window_size;
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, RepeatCount};
+use std::collections::VecDeque;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// 记住的最近目录数
+const RECENT_DIRS: usize = 20;
+// 面板中同时显示的条目数
+const VISIBLE: usize = 10;
+
+// 最近进入过的目录，最近的在前，由 cd 记录
+#[derive(Debug, Default)]
+pub struct RecentDirs {
+    dirs: VecDeque<PathBuf>,
+}
+
+impl RecentDirs {
+    pub fn visit(&mut self, dir: &Path) {
+        self.dirs.retain(|existing| existing != dir);
+        self.dirs.push_front(dir.to_path_buf());
+        self.dirs.truncate(RECENT_DIRS);
+    }
+}
+
+// 绑定到 Ctrl-P 的按键处理：记下正在编辑的行并结束本次读取，由主循环打开命令面板
+// 行编辑器在编辑期间独占终端，面板只能在 readline 返回之后显示
+#[derive(Debug, Clone, Default)]
+pub struct PaletteKey {
+    pending: Arc<Mutex<Option<String>>>,
+}
+
+impl PaletteKey {
+    // 取出按下 Ctrl-P 时正在编辑的行，没有按下时为 None
+    pub fn take(&self) -> Option<String> {
+        self.pending.lock().ok()?.take()
+    }
+}
+
+impl ConditionalEventHandler for PaletteKey {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(ctx.line().to_string());
+        }
+        Some(Cmd::AcceptLine)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Builtin,
+    Alias,
+    Bookmark,
+    Directory,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Builtin => "内建",
+            Kind::Alias => "别名",
+            Kind::Bookmark => "书签",
+            Kind::Directory => "目录",
+        }
+    }
+}
+
+// 面板中的一个条目：name 用于过滤，detail 只用于显示，command 是插入或执行的文本
+#[derive(Debug)]
+struct Entry {
+    kind: Kind,
+    name: String,
+    detail: String,
+    command: String,
+}
+
+// 在面板中的选择
+#[derive(Debug, PartialEq, Eq)]
+pub enum Choice {
+    // Tab：把文本插入到正在编辑的行
+    Insert(String),
+    // Enter：直接执行
+    Execute(String),
+    Cancel,
+}
+
+// 显示命令面板，列出内建命令、别名、书签和最近目录，输入文字进行模糊过滤：
+// 上下方向键（或 Ctrl-P/Ctrl-N）移动，Enter 执行选中的条目，Tab 把它插入命令行，Esc 或 Ctrl-C 取消
+pub fn show_palette(shell: &Shell) -> Result<Choice, ShellError> {
+    let entries = collect_entries(shell)?;
+    let _mode = TerminalMode::enter(false, false, false)?;
+    let mut stdout = io::stdout();
+    let mut query = String::new();
+    let mut selected = 0;
+
+    loop {
+        let matches = filter(&entries, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        draw(&mut stdout, &query, &matches, selected)?;
+
+        let Some(key) = next_key()? else {
+            continue;
+        };
+        let choice = match key {
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+                continue;
+            }
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+                continue;
+            }
+            Key::Up => {
+                selected = selected.saturating_sub(1);
+                continue;
+            }
+            Key::Down => {
+                if selected + 1 < matches.len() {
+                    selected += 1;
+                }
+                continue;
+            }
+            Key::Other => continue,
+            Key::Cancel => Choice::Cancel,
+            Key::Enter | Key::Tab => match matches.get(selected) {
+                Some(entry) if key == Key::Enter => Choice::Execute(entry.command.clone()),
+                Some(entry) => Choice::Insert(entry.command.clone()),
+                None => Choice::Cancel,
+            },
+        };
+        // 清除面板，光标回到行首
+        write!(stdout, "\r\x1b[J")?;
+        stdout.flush()?;
+        return Ok(choice);
+    }
+}
+
+fn collect_entries(shell: &Shell) -> Result<Vec<Entry>, ShellError> {
+    let mut entries: Vec<Entry> = BUILTINS
+        .iter()
+        .map(|name| Entry {
+            kind: Kind::Builtin,
+            name: name.to_string(),
+            detail: String::new(),
+            command: name.to_string(),
+        })
+        .collect();
+
+    entries.extend(shell.aliases.iter().map(|(name, value)| Entry {
+        kind: Kind::Alias,
+        name: name.to_string(),
+        detail: value.to_string(),
+        command: name.to_string(),
+    }));
+
+    entries.extend(load_bookmarks(&shell.vars)?.into_iter().map(|(name, dir)| Entry {
+        kind: Kind::Bookmark,
+        command: format!("cd {}", quote_word(&dir)),
+        name,
+        detail: dir,
+    }));
+
+    let current = env::current_dir().ok();
+    let home = shell.vars.get("HOME").filter(|home| !home.is_empty()).map(Path::new);
+    for dir in &shell.recent_dirs.dirs {
+        if current.as_ref() == Some(dir) {
+            continue;
+        }
+        let name = match home.and_then(|home| dir.strip_prefix(home).ok()) {
+            Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+            Some(rest) => format!("~/{}", rest.display()),
+            None => dir.display().to_string(),
+        };
+        entries.push(Entry {
+            kind: Kind::Directory,
+            name,
+            detail: String::new(),
+            command: format!("cd {}", quote_word(&dir.to_string_lossy())),
+        });
+    }
+    Ok(entries)
+}
+
+// 按模糊匹配的得分从高到低排列匹配的条目，得分相同时保持原来的顺序
+fn filter<'a>(entries: &'a [Entry], query: &str) -> Vec<&'a Entry> {
+    let mut scored: Vec<(i64, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.name).map(|score| (score, entry)))
+        .collect();
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// query 的字符按顺序（不区分大小写）出现在 text 中时返回得分，否则为 None；
+// 连续匹配和匹配在词首（开头或 / - _ . 空格之后）加分，跳过的字符扣分
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = pos + text[pos..].iter().position(|&c| c == q)?;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 8;
+        } else if found == 0 || matches!(text[found - 1], '/' | '-' | '_' | '.' | ' ') {
+            score += 5;
+        }
+        score -= (found - pos) as i64;
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+// 在当前行画出输入框，下面是匹配的条目；画完后光标停在输入框末尾
+fn draw(out: &mut impl Write, query: &str, matches: &[&Entry], selected: usize) -> io::Result<()> {
+    let columns = window_size().map_or(80, |(columns, _)| columns as usize);
+    let prompt = format!("命令面板> {}", query);
+    let first = selected.saturating_sub(VISIBLE - 1);
+
+    let mut screen = String::from("\r\x1b[J");
+    screen.push_str(&prompt);
+    let mut lines = 0;
+    if matches.is_empty() {
+        screen.push_str("\r\n  （没有匹配的条目）");
+        lines += 1;
+    }
+    for (i, entry) in matches.iter().enumerate().skip(first).take(VISIBLE) {
+        let text = format!("{} {}  {}", entry.kind.label(), entry.name, entry.detail);
+        let text = truncate(text.trim_end(), columns.saturating_sub(3));
+        if i == selected {
+            screen.push_str(&format!("\r\n{}", style::paint(&format!("> {}", text), Role::Selected, Stream::Stdout)));
+        } else {
+            screen.push_str(&format!("\r\n  {}", text));
+        }
+        lines += 1;
+    }
+    if matches.len() > first + VISIBLE {
+        screen.push_str(&format!("\r\n  …还有 {} 项", matches.len() - first - VISIBLE));
+        lines += 1;
+    }
+    // 回到输入框所在的行，重新输出一遍使光标停在末尾
+    if lines > 0 {
+        screen.push_str(&format!("\x1b[{}A\r{}", lines, prompt));
+    }
+    out.write_all(screen.as_bytes())?;
+    out.flush()
+}
+
+// 截断到终端宽度以内，避免折行打乱重绘；中日韩等宽字符按两列计算
+fn truncate(text: &str, columns: usize) -> String {
+    let mut width = 0;
+    let mut result = String::new();
+    for c in text.chars() {
+        width += if c as u32 >= 0x1100 { 2 } else { 1 };
+        if width > columns {
+            break;
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    Enter,
+    Tab,
+    Cancel,
+    Other,
+}
+
+// 等待下一个按键；终端大小改变时返回 None，由调用者按新的宽度重画
+fn next_key() -> Result<Option<Key>, ShellError> {
+    loop {
+        // SIGWINCH 打断 poll；超时只是防止信号恰好在检查之后、poll 之前到达
+        let readable = wait_readable(Duration::from_secs(1))?;
+        if take_resized() {
+            return Ok(None);
+        }
+        if readable {
+            return read_key().map(Some);
+        }
+    }
+}
+
+fn read_key() -> Result<Key, ShellError> {
+    let Some(byte) = read_byte()? else {
+        return Ok(Key::Cancel);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        // Ctrl-C、Ctrl-G
+        0x03 | 0x07 => Key::Cancel,
+        // Ctrl-P、Ctrl-N
+        0x10 => Key::Up,
+        0x0e => Key::Down,
+        0x1b => read_escape()?,
+        0x20..=0x7e => Key::Char(byte as char),
+        0x80.. => read_utf8(byte)?,
+        _ => Key::Other,
+    };
+    Ok(key)
+}
+
+// 方向键等是 Esc 开头的序列，其余字节紧跟着到达；单独的 Esc 表示取消
+fn read_escape() -> Result<Key, ShellError> {
+    if !wait_readable(Duration::from_millis(50))? {
+        return Ok(Key::Cancel);
+    }
+    match read_byte()? {
+        Some(b'[') | Some(b'O') => {
+            // 读到序列的结束字节（0x40 到 0x7e）为止
+            while let Some(byte) = read_byte()? {
+                match byte {
+                    b'A' => return Ok(Key::Up),
+                    b'B' => return Ok(Key::Down),
+                    0x40..=0x7e => break,
+                    _ => {}
+                }
+            }
+            Ok(Key::Other)
+        }
+        _ => Ok(Key::Other),
+    }
+}
+
+// 读取多字节 UTF-8 字符的其余字节
+fn read_utf8(first: u8) -> Result<Key, ShellError> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(Key::Other),
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < len {
+        match read_byte()? {
+            Some(byte) => bytes.push(byte),
+            None => return Ok(Key::Other),
+        }
+    }
+    Ok(match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+        Some(c) => Key::Char(c),
+        None => Key::Other,
+    })
+}
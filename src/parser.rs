@@ -1,127 +1,1562 @@
-use crate::error::ShellError;
+use crate::alias::AliasTable;
+use crate::error::{ShellError, Span};
+use crate::expand::expand_aliases;
+use crate::vars::is_valid_name;
 use std::iter::Peekable;
-use std::str::Chars;
+use std::mem;
 
-// 表示单个命令的结构
+// 解析得到的语法树，command.rs 按树的结构递归执行：
+//   CompoundList     用 ; 分隔、依次执行的 AndOrList
+//   AndOrList        用 && 和 || 连接的 Pipeline，可以整个在后台执行
+//   Pipeline         用 | 连接的 Command
+//   Command          SimpleCommand 或 CompoundCommand
+//   SimpleCommand    简单命令：赋值、词和 Redirect
+//   CompoundCommand  ( ... ) 或 { ...; }（Group，其中又是 CompoundList）和作用于整体的 Redirect
+//   Word             由引号、变量、命令替换等 Segment 组成的词
+pub type CompoundList = Vec<AndOrList>;
+
+// 管道中的一个命令
 #[derive(Debug, Clone)]
-pub struct Command {
+pub enum Command {
+    Simple(SimpleCommand),
+    Compound(CompoundCommand),
+}
+
+// 简单命令：开头的赋值、程序和参数，以及重定向
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
     pub program: String,
     pub args: Vec<String>,
+    // 解析得到的词，执行前展开变量后重新得到 program 和 args；
+    // 为空时（例如内部构造的命令）program 和 args 已经是最终的结果
+    pub words: Vec<Word>,
+    // 命令开头的 NAME=value 赋值：没有程序时（program 为空）赋给Shell变量，
+    // 否则只在这个命令执行期间有效，作为它的环境变量
+    pub assignments: Vec<(String, Word)>,
+    // 按出现顺序排列的重定向
+    pub redirects: Vec<Redirect>,
+}
+
+// 复合命令：括号中的列表，重定向作用于其中的全部命令，例如 { a; b; } > 文件
+#[derive(Debug, Clone)]
+pub struct CompoundCommand {
+    pub group: Group,
+    pub redirects: Vec<Redirect>,
+}
+
+// 复合命令：( ... ) 在子Shell中执行，{ ...; } 在当前Shell中执行
+#[derive(Debug, Clone)]
+pub enum Group {
+    Subshell(CompoundList),
+    Brace(CompoundList),
+}
+
+impl Group {
+    pub fn lists(&self) -> &[AndOrList] {
+        match self {
+            Group::Subshell(lists) | Group::Brace(lists) => lists,
+        }
+    }
+    
+    // 结束复合命令的符号
+    fn closing(&self) -> &'static str {
+        match self {
+            Group::Subshell(_) => ")",
+            Group::Brace(_) => "}",
+        }
+    }
+}
+
+// 重定向的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    // [n]> 文件：截断写入，默认为标准输出；开启 noclobber 时不覆盖已有的文件
+    Output,
+    // [n]>| 文件：与 > 相同，但忽略 noclobber
+    Clobber,
+    // [n]>> 文件：追加写入，默认为标准输出
+    Append,
+    // [n]< 文件：从文件读取，默认为标准输入
+    Input,
+    // [n]>&m：让描述符 n 成为 m 的副本，例如 2>&1；目标为 - 时关闭描述符 n，例如 2>&-
+    Duplicate,
+    // [n]<&m：与 >&m 相同，但默认为标准输入，例如 <&3 从描述符 3 读取
+    DuplicateInput,
+    // &> 文件：标准输出和标准错误都截断写入同一个文件
+    Combined,
+    // &>> 文件：标准输出和标准错误都追加写入同一个文件
+    CombinedAppend,
+    // [n]>&$NAME：把输出存入变量 NAME，不经过临时文件；目标是变量名
+    // 要复制变量中的描述符，给变量加上引号：>&"$FD"
+    Capture,
+    // [n]>>&$NAME：把输出追加到变量 NAME 中
+    CaptureAppend,
+}
+
+impl RedirectKind {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            RedirectKind::Output => ">",
+            RedirectKind::Clobber => ">|",
+            RedirectKind::Append => ">>",
+            RedirectKind::Input => "<",
+            RedirectKind::Duplicate => ">&",
+            RedirectKind::DuplicateInput => "<&",
+            RedirectKind::Combined => "&>",
+            RedirectKind::CombinedAppend => "&>>",
+            RedirectKind::Capture => ">&$",
+            RedirectKind::CaptureAppend => ">>&$",
+        }
+    }
+    
+    // >& 和 <&：复制或关闭描述符
+    pub fn is_duplicate(self) -> bool {
+        matches!(self, RedirectKind::Duplicate | RedirectKind::DuplicateInput)
+    }
+    
+    // 没有写出描述符时作用的描述符
+    pub fn default_fd(self) -> i32 {
+        match self {
+            RedirectKind::Input | RedirectKind::DuplicateInput => 0,
+            _ => 1,
+        }
+    }
+}
+
+// 一个重定向：作用的描述符、类型和目标（文件名；复制描述符时为描述符编号或 -）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub fd: i32,
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+impl Redirect {
+    // 重新组成重定向的文本，描述符是默认值时省略
+    fn text(&self) -> String {
+        let fd = if self.fd == self.kind.default_fd() { String::new() } else { self.fd.to_string() };
+        format!("{}{}{}", fd, self.kind.symbol(), self.target.source())
+    }
+}
+
+// 词的一段：未加引号、单引号内或双引号内的文本，变量引用 $NAME、${NAME} 以及 ${NAME:-默认值} 等，
+// 命令替换 $(...)、`...`，算术展开 $((...))，或者进程替换 <(...)、>(...)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Plain(String),
+    Single(String),
+    Double(String),
+    // quoted 表示在双引号内，此时展开的结果不再按空白拆分
+    Var { name: String, quoted: bool },
+    // 带操作的变量引用 ${NAME:-word}、${#NAME} 等，word 是操作符之后的部分（${#NAME} 时为空）
+    Param { name: String, op: ParamOp, word: Word, quoted: bool },
+    // command 是括号内的命令原文
+    Subst { command: String, quoted: bool },
+    // 双括号内的表达式，结果是一个整数，不需要区分是否在引号内
+    Arith(String),
+    // output 为 true 表示 >(...)，命令从展开得到的文件读取
+    ProcSubst { command: String, output: bool },
+}
+
+// ${...} 中变量名之后的操作；带冒号的形式（参数为 true）把空值也当作未设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamOp {
+    // ${#NAME}：值的字符数
+    Length,
+    // ${NAME:-word}：未设置时使用 word
+    Default(bool),
+    // ${NAME:=word}：未设置时把 word 赋给变量并使用它
+    Assign(bool),
+    // ${NAME:+word}：已设置时使用 word，否则为空
+    Alternative(bool),
+    // ${NAME:?word}：未设置时以 word 为消息报错
+    Error(bool),
+    // ${NAME#pattern}、${NAME##pattern}：去掉匹配的最短/最长前缀
+    RemovePrefix { longest: bool },
+    // ${NAME%pattern}、${NAME%%pattern}：去掉匹配的最短/最长后缀
+    RemoveSuffix { longest: bool },
+    // ${NAME[index]}：数组的元素，word 是下标；下标为 @ 或 * 时是全部元素
+    Element,
+    // ${#NAME[@]}、${#NAME[*]}：数组的元素个数
+    Count,
+}
+
+impl ParamOp {
+    // 操作符的写法，按从长到短排列，解析时依次尝试
+    const ALL: &'static [(&'static str, ParamOp)] = &[
+        (":-", ParamOp::Default(true)),
+        (":=", ParamOp::Assign(true)),
+        (":+", ParamOp::Alternative(true)),
+        (":?", ParamOp::Error(true)),
+        ("-", ParamOp::Default(false)),
+        ("=", ParamOp::Assign(false)),
+        ("+", ParamOp::Alternative(false)),
+        ("?", ParamOp::Error(false)),
+        ("##", ParamOp::RemovePrefix { longest: true }),
+        ("#", ParamOp::RemovePrefix { longest: false }),
+        ("%%", ParamOp::RemoveSuffix { longest: true }),
+        ("%", ParamOp::RemoveSuffix { longest: false }),
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        Self::ALL.iter().find(|(_, op)| *op == self).map_or("", |(symbol, _)| symbol)
+    }
+}
+
+// ${...} 的原文，name 之外的部分按 word 的写法重新组成
+fn param_text(name: &str, op: ParamOp, word: &str) -> String {
+    match op {
+        ParamOp::Length => format!("${{#{}}}", name),
+        ParamOp::Element => format!("${{{}[{}]}}", name, word),
+        ParamOp::Count => format!("${{#{}[@]}}", name),
+        op => format!("${{{}{}{}}}", name, op.symbol(), word),
+    }
+}
+
+// 一个词由相邻的若干段组成，例如 --opt="a b" 由 Plain("--opt=") 和 Double("a b") 组成，
+// "home: $HOME" 由 Double("home: ") 和 Var { name: "HOME", quoted: true } 组成
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Word {
+    pub segments: Vec<Segment>,
+}
+
+impl Word {
+    // 按字面处理的词，展开后的结果用它表示，不会再次展开
+    pub fn literal(text: &str) -> Word {
+        Word {
+            segments: vec![Segment::Single(text.to_string())],
+        }
+    }
+    
+    // 去掉引号后的文本，变量引用保留为 ${NAME}，命令替换保留为 $(...)
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Plain(s) | Segment::Single(s) | Segment::Double(s) => s.clone(),
+                Segment::Var { name, .. } => format!("${{{}}}", name),
+                Segment::Param { name, op, word, .. } => param_text(name, *op, &word.text()),
+                Segment::Subst { command, .. } => format!("$({})", command),
+                Segment::Arith(expr) => format!("$(({}))", expr),
+                Segment::ProcSubst { command, output } => format!("{}({})", if *output { '>' } else { '<' }, command),
+            })
+            .collect()
+    }
+    
+    // 重新组成可以再次解析的原文，保留引号和变量引用
+    pub fn source(&self) -> String {
+        let mut source = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Plain(s) => source.push_str(s),
+                Segment::Single(s) => source.push_str(&quote_word(s)),
+                Segment::Double(s) => source.push_str(&format!("\"{}\"", escape_double(s))),
+                Segment::Var { name, quoted: false } => source.push_str(&format!("${{{}}}", name)),
+                Segment::Var { name, quoted: true } => source.push_str(&format!("\"${{{}}}\"", name)),
+                Segment::Param { name, op, word, quoted: false } => source.push_str(&param_text(name, *op, &word.source())),
+                // 双引号中的 word 按双引号内的规则解析，原文就是去掉引号后的文本
+                Segment::Param { name, op, word, quoted: true } => {
+                    source.push_str(&format!("\"{}\"", param_text(name, *op, &word.text())))
+                }
+                Segment::Subst { command, quoted: false } => source.push_str(&format!("$({})", command)),
+                Segment::Subst { command, quoted: true } => source.push_str(&format!("\"$({})\"", command)),
+                Segment::Arith(expr) => source.push_str(&format!("$(({}))", expr)),
+                Segment::ProcSubst { command, output } => {
+                    source.push_str(&format!("{}({})", if *output { '>' } else { '<' }, command))
+                }
+            }
+        }
+        source
+    }
+    
+    // 不含变量引用、命令替换等需要展开的部分
+    pub fn is_literal(&self) -> bool {
+        !self
+            .segments
+            .iter()
+            .any(|segment| {
+                matches!(
+                    segment,
+                    Segment::Var { .. }
+                        | Segment::Param { .. }
+                        | Segment::Subst { .. }
+                        | Segment::Arith(_)
+                        | Segment::ProcSubst { .. }
+                )
+            })
+    }
+    
+    // 完全没有引号的词返回其文本，只有这样的词参与别名展开
+    pub fn as_plain(&self) -> Option<&str> {
+        match self.segments.as_slice() {
+            [Segment::Plain(s)] => Some(s),
+            _ => None,
+        }
+    }
+    
+    // 追加一个未加引号的字符，与前面的未加引号段合并
+    fn push_plain(&mut self, c: char) {
+        match self.segments.last_mut() {
+            Some(Segment::Plain(s)) => s.push(c),
+            _ => self.segments.push(Segment::Plain(c.to_string())),
+        }
+    }
+    
+    // 追加一个按字面处理的字符（例如反斜杠转义的），与前面的单引号段合并
+    fn push_literal(&mut self, c: char) {
+        match self.segments.last_mut() {
+            Some(Segment::Single(s)) => s.push(c),
+            _ => self.segments.push(Segment::Single(c.to_string())),
+        }
+    }
+}
+
+// 双引号中的文本的原文：用反斜杠转义 $ ` " 和 \，使它们保持字面意义
+fn escape_double(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '$' | '`' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// 词法单元
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    // 一个词，可能由多段组成
+    Word(Word),
+    // 管道符号
+    Pipe,
+    // 重定向符号和它作用的描述符，后面应当跟着目标
+    Redirect(i32, RedirectKind),
+    // 分号，分隔依次执行的管道
+    Semicolon,
+    // &&，前一个管道成功时才执行后一个
+    And,
+    // ||，前一个管道失败时才执行后一个
+    Or,
+    // 末尾的 &，在后台执行前面的列表
+    Background,
+    // 子Shell的左括号和右括号
+    LParen,
+    RParen,
+}
+
+// 由管道连接的若干命令
+pub type Pipeline = Vec<Command>;
+
+// 连接两个管道的短路运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    And,
+    Or,
+}
+
+impl Connector {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Connector::And => "&&",
+            Connector::Or => "||",
+        }
+    }
+}
+
+// 用 && 和 || 连接的管道，例如 make && ./run || echo failed
+// connectors[i] 连接 pipelines[i] 和 pipelines[i + 1]，从左到右依次结合
+#[derive(Debug, Clone, Default)]
+pub struct AndOrList {
+    pub pipelines: Vec<Pipeline>,
+    pub connectors: Vec<Connector>,
+    // 以 & 结尾，整个列表在后台执行
+    pub background: bool,
+}
+
+// 重新组成用 ; 分隔的多个列表的文本
+pub fn lists_text(lists: &[AndOrList]) -> String {
+    lists
+        .iter()
+        .map(|list| if list.background { format!("{} &", list.text()) } else { list.text() })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl AndOrList {
+    // 重新组成命令文本，用于作业列表等显示
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (i, pipeline) in self.pipelines.iter().enumerate() {
+            if i > 0 {
+                text.push_str(&format!(" {} ", self.connectors[i - 1].symbol()));
+            }
+            let commands: Vec<String> = pipeline.iter().map(Command::text).collect();
+            text.push_str(&commands.join(" | "));
+        }
+        text
+    }
+}
+
+impl Command {
+    pub fn redirects(&self) -> &[Redirect] {
+        match self {
+            Command::Simple(simple) => &simple.redirects,
+            Command::Compound(compound) => &compound.redirects,
+        }
+    }
+
+    // 重新组成命令文本，参数按需加引号
+    pub fn text(&self) -> String {
+        match self {
+            Command::Simple(simple) => simple.text(),
+            Command::Compound(compound) => {
+                let mut words = vec![match &compound.group {
+                    Group::Subshell(lists) => format!("({})", lists_text(lists)),
+                    Group::Brace(lists) => format!("{{ {}; }}", lists_text(lists)),
+                }];
+                words.extend(compound.redirects.iter().map(Redirect::text));
+                words.join(" ")
+            }
+        }
+    }
 }
 
-// 解析用户输入的命令字符串
-pub fn parse_input(input: &str) -> Result<Vec<Command>, ShellError> {
+impl SimpleCommand {
+    // 追加一个按字面处理的参数
+    pub fn push_arg(&mut self, arg: &str) {
+        self.args.push(arg.to_string());
+        if !self.words.is_empty() {
+            self.words.push(Word::literal(arg));
+        }
+    }
+    
+    // 重新组成命令文本，参数按需加引号
+    pub fn text(&self) -> String {
+        let mut words: Vec<String> = self
+            .assignments
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.source()))
+            .collect();
+        if !self.words.is_empty() {
+            words.extend(self.words.iter().map(Word::source));
+        } else if !self.program.is_empty() {
+            words.push(quote_word(&self.program));
+            words.extend(self.args.iter().map(|arg| quote_word(arg)));
+        }
+        words.extend(self.redirects.iter().map(Redirect::text));
+        words.join(" ")
+    }
+}
+
+// 解析用户输入的命令字符串，得到用 ; 分隔、依次执行的 && / || 列表
+// 只有注释的输入没有要执行的命令，得到空的列表
+pub fn parse_input(input: &str, aliases: &AliasTable) -> Result<CompoundList, ShellError> {
+    let tokens = expand_aliases(lex(input)?, aliases)?;
+    if tokens.is_empty() && !input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut tokens = tokens.into_iter().peekable();
+    parse_lists(&mut tokens, ListEnd::Input)
+}
+
+// 列表读到哪里结束
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListEnd {
+    // 输入结尾
+    Input,
+    // 子Shell的 ')'，带有开括号的位置
+    Paren(Span),
+    // 命令位置上的 '}'，带有 '{' 的位置
+    Brace(Span),
+}
+
+// 解析若干个列表，读到 end 指定的位置为止，结束的符号也被读掉
+fn parse_lists(
+    tokens: &mut Peekable<impl Iterator<Item = (Token, Span)>>,
+    end: ListEnd,
+) -> Result<CompoundList, ShellError> {
+    let mut lists = Vec::new();
+    // 结束的 ')' 或 '}' 的位置
+    let mut close = None;
+    
+    // 末尾的分号可以省略，也可以保留
+    loop {
+        match tokens.peek() {
+            None => match end {
+                ListEnd::Input => break,
+                ListEnd::Paren(open) => {
+                    return Err(ShellError::ParseError("缺少与 '(' 对应的 ')'".to_string(), Some(open)))
+                }
+                ListEnd::Brace(open) => {
+                    return Err(ShellError::ParseError("缺少与 '{' 对应的 '}'".to_string(), Some(open)))
+                }
+            },
+            Some((Token::RParen, span)) if matches!(end, ListEnd::Paren(_)) => {
+                close = Some(*span);
+                tokens.next();
+                break;
+            }
+            Some((Token::RParen, span)) => return Err(ShellError::ParseError("多余的 ')'".to_string(), Some(*span))),
+            Some((token, span)) if is_reserved(token, "}") => {
+                if !matches!(end, ListEnd::Brace(_)) {
+                    return Err(ShellError::ParseError("多余的 '}'".to_string(), Some(*span)));
+                }
+                close = Some(*span);
+                tokens.next();
+                break;
+            }
+            Some(_) => lists.push(parse_and_or(tokens)?),
+        }
+    }
+    
+    if lists.is_empty() {
+        let (message, open) = match end {
+            ListEnd::Input => ("没有找到有效命令", None),
+            ListEnd::Paren(open) => ("括号 '( )' 中没有命令", Some(open)),
+            ListEnd::Brace(open) => ("'{ }' 中没有命令", Some(open)),
+        };
+        // 复合命令从开头的符号一直标到结尾的符号
+        let span = open.zip(close).map(|(open, close): (Span, Span)| Span { start: open.start, end: close.end });
+        return Err(ShellError::ParseError(message.to_string(), span));
+    }
+    
+    Ok(lists)
+}
+
+// 是否是未加引号的保留字 { 或 }，它们只在命令位置上有特殊含义
+fn is_reserved(token: &Token, word: &str) -> bool {
+    matches!(token, Token::Word(w) if w.as_plain() == Some(word))
+}
+
+// 解析一个 && / || 列表，读到分号、&、')' 或输入结尾为止
+fn parse_and_or(tokens: &mut Peekable<impl Iterator<Item = (Token, Span)>>) -> Result<AndOrList, ShellError> {
+    let mut list = AndOrList::default();
+    
+    loop {
+        list.pipelines.push(parse_pipeline(tokens)?);
+        
+        // ')' 留给 parse_lists 处理
+        let (connector, span) = match tokens.next_if(|(token, _)| *token != Token::RParen) {
+            Some((Token::And, span)) => (Connector::And, span),
+            Some((Token::Or, span)) => (Connector::Or, span),
+            Some((Token::Background, _)) => {
+                list.background = true;
+                return Ok(list);
+            }
+            // 分号、')' 或输入结尾
+            _ => return Ok(list),
+        };
+        // 在输入结尾时交互中可以接着读取下一行，例如 make && 之后换行
+        if tokens.peek().is_none() {
+            return Err(ShellError::Incomplete(format!("'{}' 之后还需要命令", connector.symbol()), Some(span)));
+        }
+        if matches!(
+            tokens.peek(),
+            Some((Token::Semicolon, _)) | Some((Token::Background, _)) | Some((Token::RParen, _))
+        ) || tokens.peek().is_some_and(|(token, _)| is_reserved(token, "}"))
+        {
+            return Err(ShellError::ParseError(format!("'{}' 后没有命令", connector.symbol()), Some(span)));
+        }
+        list.connectors.push(connector);
+    }
+}
+
+// 解析一个管道，读到分号、&&、||、&、')' 或输入结尾为止，结束它的符号留给调用者
+fn parse_pipeline(tokens: &mut Peekable<impl Iterator<Item = (Token, Span)>>) -> Result<Pipeline, ShellError> {
     let mut commands = Vec::new();
     let mut current_parts = Vec::new();
+    let mut current_redirects = Vec::new();
+    // 当前命令是复合命令时，其中的列表
+    let mut current_group = None;
+    // 最后一个管道符号的位置
+    let mut last_pipe = None;
     
-    let mut char_iter = input.chars().peekable();
-    
-    while let Some(part) = parse_token(&mut char_iter)? {
-        if part == "|" {
-            // 管道符号，创建新命令
-            if current_parts.is_empty() {
-                return Err(ShellError::ParseError("管道前没有命令".to_string()));
+    while let Some((token, span)) = tokens.next_if(|(token, _)| !ends_pipeline(token)) {
+        match token {
+            Token::Semicolon | Token::And | Token::Or | Token::Background | Token::RParen => unreachable!(),
+            Token::Pipe => {
+                // 管道符号，创建新命令
+                if current_parts.is_empty() && current_redirects.is_empty() && current_group.is_none() {
+                    let message = if commands.is_empty() {
+                        "管道符号 '|' 前没有命令"
+                    } else {
+                        "两个管道符号 '|' 之间没有命令"
+                    };
+                    return Err(ShellError::ParseError(message.to_string(), Some(span)));
+                }
+                
+                let command = finish_command(
+                    &current_parts,
+                    mem::take(&mut current_redirects),
+                    current_group.take(),
+                )?;
+                commands.push(command);
+                current_parts.clear();
+                last_pipe = Some(span);
             }
-            
-            let command = create_command_from_parts(&current_parts)?;
-            commands.push(command);
-            current_parts.clear();
-        } else {
-            current_parts.push(part);
+            // 复合命令只能出现在命令的开头，之后只能跟重定向
+            Token::LParen if current_parts.is_empty() && current_redirects.is_empty() && current_group.is_none() => {
+                current_group = Some(Group::Subshell(parse_lists(tokens, ListEnd::Paren(span))?));
+            }
+            Token::LParen => return Err(ShellError::ParseError("'(' 只能出现在命令的开头".to_string(), Some(span))),
+            Token::Word(word)
+                if word.as_plain() == Some("{")
+                    && current_parts.is_empty()
+                    && current_redirects.is_empty()
+                    && current_group.is_none() =>
+            {
+                current_group = Some(Group::Brace(parse_lists(tokens, ListEnd::Brace(span))?));
+            }
+            Token::Word(word) if let Some(group) = &current_group => {
+                return Err(ShellError::ParseError(
+                    format!("'{}' 后不能跟参数 '{}'", group.closing(), word.text()),
+                    Some(span),
+                ));
+            }
+            Token::Word(word) => current_parts.push(word),
+            Token::Redirect(fd, kind) => match tokens.next() {
+                Some((Token::Word(target), target_span)) => {
+                    // >& 和 >>& 的目标是 $NAME 时捕获到变量中
+                    if matches!(kind, RedirectKind::Duplicate | RedirectKind::CaptureAppend)
+                        && let Some(name) = capture_name(&target)
+                    {
+                        let kind = if kind == RedirectKind::Duplicate { RedirectKind::Capture } else { kind };
+                        current_redirects.push(Redirect { fd, kind, target: Word::literal(name) });
+                        continue;
+                    }
+                    if kind == RedirectKind::CaptureAppend {
+                        return Err(ShellError::ParseError(
+                            "重定向 '>>&' 后应为 $变量名".to_string(),
+                            Some(target_span),
+                        ));
+                    }
+                    // 含有变量的目标要到执行时才知道
+                    if kind.is_duplicate() && target.is_literal() && !is_duplicate_target(&target.text()) {
+                        return Err(ShellError::ParseError(
+                            format!("重定向 '{}' 需要文件描述符或 -，而不是 '{}'", kind.symbol(), target.text()),
+                            Some(target_span),
+                        ));
+                    }
+                    current_redirects.push(Redirect { fd, kind, target });
+                }
+                _ => {
+                    return Err(ShellError::ParseError(
+                        format!("重定向 '{}' 后缺少目标", kind.symbol()),
+                        Some(span),
+                    ))
+                }
+            },
         }
     }
     
     // 处理最后一个命令
-    if !current_parts.is_empty() {
-        let command = create_command_from_parts(&current_parts)?;
+    if !current_parts.is_empty() || !current_redirects.is_empty() || current_group.is_some() {
+        let command = finish_command(&current_parts, current_redirects, current_group)?;
         commands.push(command);
+    } else if !commands.is_empty() && tokens.peek().is_none() {
+        return Err(ShellError::Incomplete("管道符号 '|' 之后还需要命令".to_string(), last_pipe));
+    } else if !commands.is_empty() {
+        return Err(ShellError::ParseError("管道符号 '|' 后没有命令".to_string(), last_pipe));
     }
     
     if commands.is_empty() {
-        return Err(ShellError::ParseError("没有找到有效命令".to_string()));
+        let message = match tokens.peek().map(|(token, _)| token) {
+            Some(Token::And) => "'&&' 前没有命令",
+            Some(Token::Or) => "'||' 前没有命令",
+            Some(Token::Background) => "'&' 前没有命令",
+            Some(Token::RParen) => "')' 前没有命令",
+            _ => "分号 ';' 前没有命令",
+        };
+        let span = tokens.peek().map(|(_, span)| *span);
+        return Err(ShellError::ParseError(message.to_string(), span));
     }
     
     Ok(commands)
 }
 
+// 复制描述符的目标：描述符编号，或者表示关闭的 -
+pub fn is_duplicate_target(target: &str) -> bool {
+    target == "-" || target.parse::<i32>().is_ok()
+}
+
+// >&$NAME 和 >>&$NAME 的目标是未加引号的 $NAME，返回变量名
+fn capture_name(target: &Word) -> Option<&str> {
+    match target.segments.as_slice() {
+        [Segment::Var { name, quoted: false }] if is_valid_name(name) => Some(name),
+        _ => None,
+    }
+}
+
+// 创建一个命令：复合命令，或者由词组成的普通命令
+fn finish_command(parts: &[Word], redirects: Vec<Redirect>, group: Option<Group>) -> Result<Command, ShellError> {
+    match group {
+        Some(group) => Ok(Command::Compound(CompoundCommand { group, redirects })),
+        None => create_command_from_parts(parts, redirects).map(Command::Simple),
+    }
+}
+
+// 结束一个管道的符号
+fn ends_pipeline(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Semicolon | Token::And | Token::Or | Token::Background | Token::RParen
+    )
+}
+
+// 输入是否在引号、$(...)、${...} 或 `...` 中结束，行编辑器据此在同一条命令中换行继续编辑
+pub fn has_unclosed_quote(input: &str) -> bool {
+    matches!(tokenize(input), Err(ShellError::Incomplete(message, _)) if message.starts_with("未闭合"))
+}
+
+// 将输入拆分为词法单元序列
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ShellError> {
+    Ok(lex(input)?.into_iter().map(|(token, _)| token).collect())
+}
+
+// 将输入拆分为词法单元，同时记录每个词法单元在输入中的位置
+pub fn lex(input: &str) -> Result<Vec<(Token, Span)>, ShellError> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    
+    loop {
+        skip_blank(&mut lexer);
+        let start = lexer.pos;
+        match parse_token(&mut lexer)? {
+            Some(token) => tokens.push((token, lexer.span_from(start))),
+            None => return Ok(tokens),
+        }
+    }
+}
+
+// 逐个字符读取输入的词法分析器，记录读到的字节位置，错误信息据此指出出错的地方
+#[derive(Debug, Clone)]
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Lexer { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.peek() {
+            Some(c) if f(&c) => self.next(),
+            _ => None,
+        }
+    }
+
+    fn next_if_eq(&mut self, expected: char) -> Option<char> {
+        self.next_if(|&c| c == expected)
+    }
+
+    // 接下来的字符是否以 prefix 开头，例如 &> 和 &&
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.input[self.pos..].starts_with(prefix)
+    }
+
+    // 从 start 到当前位置
+    fn span_from(&self, start: usize) -> Span {
+        Span { start, end: self.pos }
+    }
+
+    // 从 start 到输入结尾，用于没有闭合的引号和括号
+    fn rest_from(&self, start: usize) -> Span {
+        Span { start, end: self.input.len() }
+    }
+}
+
 // 从命令部分创建命令结构
-fn create_command_from_parts(parts: &[String]) -> Result<Command, ShellError> {
+fn create_command_from_parts(parts: &[Word], redirects: Vec<Redirect>) -> Result<SimpleCommand, ShellError> {
+    if parts.is_empty() && redirects.is_empty() {
+        return Err(ShellError::ParseError("空命令".to_string(), None));
+    }
+    
+    // 开头的 NAME=value 是赋值，之后的同样形式的词是普通参数，例如 make CC=gcc
+    let assignments: Vec<_> = parts.iter().map_while(split_assignment).collect();
+    let parts = &parts[assignments.len()..];
+    // 全部是变量赋值（或只有重定向）时不执行任何程序
     if parts.is_empty() {
-        return Err(ShellError::ParseError("空命令".to_string()));
+        return Ok(SimpleCommand {
+            assignments,
+            redirects,
+            ..SimpleCommand::default()
+        });
     }
     
-    let program = parts[0].clone();
-    let args = parts[1..].to_vec();
+    // 展开之前的文本，执行时会按展开的结果重新计算
+    let program = parts[0].text();
+    let args = parts[1..].iter().map(Word::text).collect();
     
-    Ok(Command { program, args })
+    Ok(SimpleCommand {
+        program,
+        args,
+        words: parts.to_vec(),
+        assignments,
+        redirects,
+    })
 }
 
-// 解析单个词元（token）
-fn parse_token(chars: &mut Peekable<Chars>) -> Result<Option<String>, ShellError> {
-    // 跳过前导空白
-    skip_whitespace(chars);
-    
-    // 检查是否到达输入结尾
-    if chars.peek().is_none() {
-        return Ok(None);
+// 词是否是 NAME=value 形式的赋值
+pub fn is_assignment(word: &Word) -> bool {
+    split_assignment(word).is_some()
+}
+
+// 把形如 NAME=value 的词拆分为变量名和值；变量名部分不能带引号
+fn split_assignment(word: &Word) -> Option<(String, Word)> {
+    let (name, rest) = match word.segments.first() {
+        Some(Segment::Plain(s)) => s.split_once('=')?,
+        _ => return None,
+    };
+    if !is_valid_name(name) {
+        return None;
     }
     
-    let mut token = String::new();
-    let mut in_quotes = false;
-    let mut quote_char = ' ';
-    
-    while let Some(&c) = chars.peek() {
-        if c.is_whitespace() && !in_quotes {
-            // 遇到空白字符且不在引号内，词元结束
+    let mut value = Word::default();
+    if !rest.is_empty() {
+        value.segments.push(Segment::Plain(rest.to_string()));
+    }
+    value.segments.extend(word.segments[1..].iter().cloned());
+    Some((name.to_string(), value))
+}
+
+// 解析单个词元（token）
+// 相邻的未加引号部分和引号部分属于同一个词，例如 foo"bar baz"qux 和 --opt='a b'
+// 前导的空白、续行和注释已经跳过
+fn parse_token(chars: &mut Lexer) -> Result<Option<Token>, ShellError> {
+    // 检查是否到达输入结尾，或是管道、重定向符号
+    match chars.peek() {
+        None => return Ok(None),
+        Some('|') => {
+            chars.next();
+            if chars.next_if_eq('|').is_some() {
+                return Ok(Some(Token::Or));
+            }
+            return Ok(Some(Token::Pipe));
+        }
+        Some('&') if chars.starts_with("&&") => {
             chars.next();
-            break;
-        } else if (c == '"' || c == '\'') && !in_quotes {
-            // 开始引号
-            in_quotes = true;
-            quote_char = c;
             chars.next();
-        } else if c == quote_char && in_quotes {
-            // 结束引号
-            in_quotes = false;
+            return Ok(Some(Token::And));
+        }
+        Some('&') if !chars.starts_with("&>") => {
+            chars.next();
+            return Ok(Some(Token::Background));
+        }
+        Some('(') => {
             chars.next();
-        } else if c == '|' && !in_quotes {
-            // 管道符号且不在引号内
-            if token.is_empty() {
+            return Ok(Some(Token::LParen));
+        }
+        Some(')') => {
+            chars.next();
+            return Ok(Some(Token::RParen));
+        }
+        Some(';') => {
+            chars.next();
+            return Ok(Some(Token::Semicolon));
+        }
+        // <( 和 >( 是进程替换，属于词
+        Some('>') | Some('<') if !chars.starts_with("<(") && !chars.starts_with(">(") => {
+            return Ok(Some(parse_redirect(chars, None)));
+        }
+        Some('&') if chars.starts_with("&>") => {
+            chars.next();
+            chars.next();
+            let kind = if chars.peek() == Some('>') {
                 chars.next();
-                return Ok(Some("|".to_string()));
+                RedirectKind::CombinedAppend
             } else {
-                break;
-            }
-        } else {
-            // 普通字符
-            token.push(c);
+                RedirectKind::Combined
+            };
+            return Ok(Some(Token::Redirect(kind.default_fd(), kind)));
+        }
+        Some(_) => {}
+    }
+    
+    let mut word = Word::default();
+    
+    while let Some(c) = chars.peek() {
+        if matches!(c, '>' | '<') && (chars.starts_with("<(") || chars.starts_with(">(")) {
+            let start = chars.pos;
+            chars.next();
             chars.next();
+            let mut command = String::new();
+            read_parenthesized(chars, &mut command, start)?;
+            check_syntax(&command).map_err(|e| e.at(chars.span_from(start)))?;
+            word.segments.push(Segment::ProcSubst { command, output: c == '>' });
+            continue;
+        }
+        if c.is_whitespace() || matches!(c, '|' | ';' | '&' | '>' | '<' | '(' | ')') {
+            // 引号之外的空白、管道、分号、&、括号和重定向符号结束当前词
+            break;
+        }
+        
+        chars.next();
+        match c {
+            '\'' => word.segments.push(parse_single_quoted(chars)?),
+            '"' => parse_double_quoted(chars, &mut word)?,
+            '$' => parse_dollar(chars, &mut word, false)?,
+            '`' => parse_backquoted(chars, &mut word, false)?,
+            '\\' => parse_escape(chars, &mut word)?,
+            _ => word.push_plain(c),
         }
     }
     
-    if in_quotes {
-        return Err(ShellError::ParseError("未闭合的引号".to_string()));
+    // 紧挨着重定向符号的数字是描述符编号，例如 2> 和 2>&1
+    if matches!(chars.peek(), Some('>') | Some('<'))
+        && let Some(digits) = word.as_plain()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && let Ok(fd) = digits.parse::<i32>()
+    {
+        return Ok(Some(parse_redirect(chars, Some(fd))));
     }
     
-    if token.is_empty() && chars.peek().is_some() && *chars.peek().unwrap() == '|' {
-        chars.next();
-        return Ok(Some("|".to_string()));
+    Ok(Some(Token::Word(word)))
+}
+
+// 读取重定向符号 >、>>、>&、>>&、>|、< 或 <&，fd 为前面写出的描述符编号
+fn parse_redirect(chars: &mut Lexer, fd: Option<i32>) -> Token {
+    let kind = match chars.next() {
+        Some('<') if chars.next_if_eq('&').is_some() => RedirectKind::DuplicateInput,
+        Some('<') => RedirectKind::Input,
+        _ => match chars.peek() {
+            Some('>') => {
+                chars.next();
+                if chars.next_if_eq('&').is_some() {
+                    RedirectKind::CaptureAppend
+                } else {
+                    RedirectKind::Append
+                }
+            }
+            Some('&') => {
+                chars.next();
+                RedirectKind::Duplicate
+            }
+            Some('|') => {
+                chars.next();
+                RedirectKind::Clobber
+            }
+            _ => RedirectKind::Output,
+        },
+    };
+    
+    Token::Redirect(fd.unwrap_or(kind.default_fd()), kind)
+}
+
+// 引号之外的反斜杠，反斜杠已经读过：使下一个字符按字面处理，例如 foo\ bar 和 \|；
+// 反斜杠加换行被删除（续行）；在输入末尾时输入不完整，交互时接着读取下一行
+fn parse_escape(chars: &mut Lexer, word: &mut Word) -> Result<(), ShellError> {
+    match chars.next() {
+        Some('\n') => {}
+        Some(c) => word.push_literal(c),
+        None => {
+            let span = chars.span_from(chars.pos - 1);
+            return Err(ShellError::Incomplete("行尾的 '\\' 之后还需要输入".to_string(), Some(span)));
+        }
+    }
+    Ok(())
+}
+
+// 读取单引号内的一段，开引号已经读过；其中的内容完全按字面处理，空的 '' 也是一段
+fn parse_single_quoted(chars: &mut Lexer) -> Result<Segment, ShellError> {
+    let open = chars.pos - 1;
+    let mut text = String::new();
+    
+    loop {
+        match chars.next() {
+            Some('\'') => return Ok(Segment::Single(text)),
+            Some(c) => text.push(c),
+            None => return Err(ShellError::Incomplete("未闭合的引号".to_string(), Some(chars.rest_from(open)))),
+        }
+    }
+}
+
+// 读取双引号内的部分，开引号已经读过；其中的 $ 和 ` 引用变量或替换命令，空的 "" 也是一段
+// 反斜杠只转义 $ ` " \ 和换行，在其他字符之前按字面保留
+fn parse_double_quoted(chars: &mut Lexer, word: &mut Word) -> Result<(), ShellError> {
+    let open = chars.pos - 1;
+    let mut text = String::new();
+    
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next_if(|c| matches!(c, '$' | '`' | '"' | '\\' | '\n')) {
+                Some('\n') => {}
+                Some(c) => text.push(c),
+                None => text.push('\\'),
+            },
+            Some('$') if starts_variable(chars) => {
+                if !text.is_empty() {
+                    word.segments.push(Segment::Double(mem::take(&mut text)));
+                }
+                parse_dollar(chars, word, true)?;
+            }
+            Some('`') => {
+                if !text.is_empty() {
+                    word.segments.push(Segment::Double(mem::take(&mut text)));
+                }
+                parse_backquoted(chars, word, true)?;
+            }
+            Some(c) => text.push(c),
+            None => return Err(ShellError::Incomplete("未闭合的引号".to_string(), Some(chars.rest_from(open)))),
+        }
     }
     
-    if !token.is_empty() {
-        Ok(Some(token))
+    // 只有变量引用或命令替换时不再追加空段，"" 本身仍然是一段
+    let expansion_only = matches!(
+        word.segments.last(),
+        Some(
+            Segment::Var { quoted: true, .. }
+                | Segment::Param { quoted: true, .. }
+                | Segment::Subst { quoted: true, .. }
+                | Segment::Arith(_)
+        )
+    );
+    if !text.is_empty() || !expansion_only {
+        word.segments.push(Segment::Double(text));
+    }
+    Ok(())
+}
+
+// $ 之后是否是变量引用或命令替换：${、$(、特殊参数、位置参数或者变量名的第一个字符
+fn starts_variable(chars: &Lexer) -> bool {
+    matches!(chars.peek(), Some(c) if c == '{' || c == '(' || c == '_' || is_special(c) || c.is_ascii_alphanumeric())
+}
+
+// 只有一个字符的特殊参数：$? 状态码，$# 位置参数的个数，$@ 和 $* 全部位置参数
+fn is_special(c: char) -> bool {
+    matches!(c, '?' | '#' | '@' | '*')
+}
+
+// ${...} 中可以使用的参数名：变量名、特殊参数，或者数字表示的位置参数（可以多于一位，如 ${10}）
+fn is_parameter_name(name: &str) -> bool {
+    is_valid_name(name)
+        || (!name.is_empty() && name.chars().all(|c| c.is_ascii_digit()))
+        || (name.len() == 1 && name.chars().all(is_special))
+}
+
+// 读取 $NAME、${...}、$(命令) 或 $((表达式))，$ 已经读过；后面不是变量名时 $ 按字面处理
+// 替换之中的错误标在整个替换上
+fn parse_dollar(chars: &mut Lexer, word: &mut Word, quoted: bool) -> Result<(), ShellError> {
+    let start = chars.pos - 1;
+    if chars.next_if_eq('(').is_some() {
+        if chars.next_if_eq('(').is_none() {
+            let mut command = String::new();
+            read_parenthesized(chars, &mut command, start)?;
+            return push_subst(word, command, quoted).map_err(|e| e.at(chars.span_from(start)));
+        }
+        let mut expr = String::new();
+        read_parenthesized(chars, &mut expr, start)?;
+        if chars.next_if_eq(')').is_some() {
+            word.segments.push(Segment::Arith(expr));
+            return Ok(());
+        }
+        // 形如 $((cmd1) && (cmd2)) 的是以子Shell开头的命令替换
+        let mut command = format!("({})", expr);
+        read_parenthesized(chars, &mut command, start)?;
+        return push_subst(word, command, quoted).map_err(|e| e.at(chars.span_from(start)));
+    }
+    if !starts_variable(chars) {
+        if quoted {
+            word.segments.push(Segment::Double("$".to_string()));
+        } else {
+            word.push_plain('$');
+        }
+        return Ok(());
+    }
+    
+    if chars.next_if_eq('{').is_some() {
+        let body = read_braced(chars, start)?;
+        word.segments.push(parse_parameter(&body, quoted).map_err(|e| e.at(chars.span_from(start)))?);
+        return Ok(());
+    }
+    // 特殊参数和位置参数只有一个字符：$10 是 $1 后面跟着 0
+    if let Some(c) = chars.next_if(|c| is_special(*c) || c.is_ascii_digit()) {
+        word.segments.push(Segment::Var { name: c.to_string(), quoted });
+        return Ok(());
+    }
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| *c == '_' || c.is_ascii_alphanumeric()) {
+        name.push(c);
+    }
+    
+    word.segments.push(Segment::Var { name, quoted });
+    Ok(())
+}
+
+// 把 ${ 之后到对应的 } 之前的原文读出来，} 也被读掉
+// 引号中的 } 以及嵌套的 ${...}、$(...) 不结束读取；open 是 ${ 的位置
+fn read_braced(chars: &mut Lexer, open: usize) -> Result<String, ShellError> {
+    let span = chars.rest_from(open);
+    let unclosed = || ShellError::Incomplete("未闭合的 '${'".to_string(), Some(span));
+    let mut text = String::new();
+    let mut depth = 0;
+    loop {
+        let c = chars.next().ok_or_else(unclosed)?;
+        match c {
+            '\\' => {
+                text.push(c);
+                text.extend(chars.next());
+                continue;
+            }
+            '}' if depth == 0 => return Ok(text),
+            '}' => depth -= 1,
+            '$' if chars.next_if_eq('{').is_some() => {
+                text.push_str("${");
+                depth += 1;
+                continue;
+            }
+            '$' if chars.next_if_eq('(').is_some() => {
+                text.push_str("$(");
+                read_parenthesized(chars, &mut text, chars.pos - 2)?;
+                text.push(')');
+                continue;
+            }
+            '\'' | '"' | '`' => {
+                text.push(c);
+                loop {
+                    let inner = chars.next().ok_or_else(unclosed)?;
+                    text.push(inner);
+                    if inner == c {
+                        break;
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        text.push(c);
+    }
+}
+
+// 解析 ${...} 的内容：NAME、#NAME、NAME[index]、#NAME[@]，或者 NAME 之后跟操作符和 word
+fn parse_parameter(body: &str, quoted: bool) -> Result<Segment, ShellError> {
+    let invalid = || ShellError::ParseError(format!("错误的变量替换 '${{{}}}'", body), None);
+    if is_parameter_name(body) {
+        return Ok(Segment::Var { name: body.to_string(), quoted });
+    }
+    if let Some(name) = body.strip_prefix('#').and_then(|rest| {
+        rest.strip_suffix("[@]").or_else(|| rest.strip_suffix("[*]"))
+    }) && is_valid_name(name)
+    {
+        return Ok(Segment::Param {
+            name: name.to_string(),
+            op: ParamOp::Count,
+            word: Word::default(),
+            quoted,
+        });
+    }
+    if let Some((name, index)) = body.strip_suffix(']').and_then(|rest| rest.split_once('['))
+        && is_valid_name(name)
+    {
+        if index.is_empty() {
+            return Err(invalid());
+        }
+        return Ok(Segment::Param {
+            name: name.to_string(),
+            op: ParamOp::Element,
+            word: parse_param_word(index, quoted)?,
+            quoted,
+        });
+    }
+    if let Some(name) = body.strip_prefix('#')
+        && is_parameter_name(name)
+    {
+        return Ok(Segment::Param {
+            name: name.to_string(),
+            op: ParamOp::Length,
+            word: Word::default(),
+            quoted,
+        });
+    }
+
+    let end = match body.chars().next() {
+        Some(c) if is_special(c) => 1,
+        Some(c) if c.is_ascii_digit() => body.find(|c: char| !c.is_ascii_digit()).unwrap_or(body.len()),
+        _ => body
+            .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+            .unwrap_or(body.len()),
+    };
+    let (name, rest) = body.split_at(end);
+    if !is_parameter_name(name) {
+        return Err(invalid());
+    }
+    if rest.is_empty() {
+        return Ok(Segment::Var {
+            name: name.to_string(),
+            quoted,
+        });
+    }
+
+    let (symbol, op) = ParamOp::ALL
+        .iter()
+        .find(|(symbol, _)| rest.starts_with(symbol))
+        .ok_or_else(invalid)?;
+    // 模式即使在双引号中也是模式，其中的引号使字符按字面匹配
+    let pattern = matches!(op, ParamOp::RemovePrefix { .. } | ParamOp::RemoveSuffix { .. });
+    Ok(Segment::Param {
+        name: name.to_string(),
+        op: *op,
+        word: parse_param_word(&rest[symbol.len()..], quoted && !pattern)?,
+        quoted,
+    })
+}
+
+// 解析 ${NAME:-word} 等中的 word；在双引号中时 word 也按双引号内的规则处理，
+// 与 bash 一样其中还可以再用双引号，例如 "${X:-"a b"}"
+fn parse_param_word(text: &str, quoted: bool) -> Result<Word, ShellError> {
+    let mut chars = Lexer::new(text);
+    let mut word = Word::default();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => parse_dollar(&mut chars, &mut word, quoted)?,
+            '`' => parse_backquoted(&mut chars, &mut word, quoted)?,
+            '\'' if !quoted => word.segments.push(parse_single_quoted(&mut chars)?),
+            '"' => parse_double_quoted(&mut chars, &mut word)?,
+            '\\' if !quoted => parse_escape(&mut chars, &mut word)?,
+            '\\' => match chars.next_if(|c| matches!(c, '$' | '`' | '"' | '\\' | '}')) {
+                Some(c) => word.segments.push(Segment::Double(c.to_string())),
+                None => word.segments.push(Segment::Double("\\".to_string())),
+            },
+            c if quoted => match word.segments.last_mut() {
+                Some(Segment::Double(s)) => s.push(c),
+                _ => word.segments.push(Segment::Double(c.to_string())),
+            },
+            c => word.push_plain(c),
+        }
+    }
+    Ok(word)
+}
+
+// 读取 `命令`，开头的 ` 已经读过；其中 \`、\$ 和 \\ 表示字面字符
+fn parse_backquoted(chars: &mut Lexer, word: &mut Word, quoted: bool) -> Result<(), ShellError> {
+    let open = chars.pos - 1;
+    let mut command = String::new();
+    loop {
+        match chars.next() {
+            Some('`') => break,
+            Some('\\') => match chars.next_if(|c| matches!(c, '`' | '$' | '\\')) {
+                Some(c) => command.push(c),
+                None => command.push('\\'),
+            },
+            Some(c) => command.push(c),
+            None => return Err(ShellError::Incomplete("未闭合的 '`'".to_string(), Some(chars.rest_from(open)))),
+        }
+    }
+    push_subst(word, command, quoted).map_err(|e| e.at(chars.span_from(open)))
+}
+
+// 检查命令替换中的命令语法后加入词中，语法错误在解析整行时就报告；$() 展开为空
+fn push_subst(word: &mut Word, command: String, quoted: bool) -> Result<(), ShellError> {
+    check_syntax(&command)?;
+    word.segments.push(Segment::Subst { command, quoted });
+    Ok(())
+}
+
+// 检查替换中的命令的语法，空命令（包括只有注释的）也是允许的
+// 替换在括号处已经结束，其中不完整的命令是语法错误
+fn check_syntax(command: &str) -> Result<(), ShellError> {
+    let complete = |e| match e {
+        ShellError::Incomplete(message, span) => ShellError::ParseError(message, span),
+        e => e,
+    };
+    let mut tokens = lex(command).map_err(complete)?.into_iter().peekable();
+    if tokens.peek().is_some() {
+        parse_lists(&mut tokens, ListEnd::Input).map_err(complete)?;
+    }
+    Ok(())
+}
+
+// 把 $( 之后到对应的 ) 之前的原文读入 text，) 也被读掉
+// 引号中的括号不计入嵌套，双引号中可以再嵌套 $(...)；open 是 $( 的位置
+fn read_parenthesized(chars: &mut Lexer, text: &mut String, open: usize) -> Result<(), ShellError> {
+    let span = chars.rest_from(open);
+    let unclosed = || ShellError::Incomplete("未闭合的 '$('".to_string(), Some(span));
+    let mut depth = 0;
+    loop {
+        let c = chars.next().ok_or_else(unclosed)?;
+        match c {
+            // 转义的字符原样保留，留给执行时再解析
+            '\\' => {
+                text.push(c);
+                text.extend(chars.next());
+                continue;
+            }
+            // 注释中的引号和括号不起作用
+            '#' if text.ends_with(|c: char| c.is_whitespace() || ";|&(".contains(c)) || text.is_empty() => {
+                text.push(c);
+                while let Some(inner) = chars.next_if(|&inner| inner != '\n') {
+                    text.push(inner);
+                }
+                continue;
+            }
+            ')' if depth == 0 => return Ok(()),
+            ')' => depth -= 1,
+            '(' => depth += 1,
+            '\'' | '`' => {
+                text.push(c);
+                loop {
+                    let inner = chars.next().ok_or_else(unclosed)?;
+                    text.push(inner);
+                    if inner == c {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '"' => {
+                text.push(c);
+                loop {
+                    let inner = chars.next().ok_or_else(unclosed)?;
+                    text.push(inner);
+                    match inner {
+                        '"' => break,
+                        '\\' => text.extend(chars.next()),
+                        '$' if chars.next_if_eq('(').is_some() => {
+                            text.push('(');
+                            read_parenthesized(chars, text, chars.pos - 2)?;
+                            text.push(')');
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        text.push(c);
+    }
+}
+
+// 把一个参数转换为可以重新解析的形式：需要时加单引号，使空白和特殊字符原样保留
+pub fn quote_word(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%^".contains(c) || !c.is_ascii());
+    if plain {
+        word.to_string()
     } else {
-        Ok(None)
+        format!("'{}'", word.replace('\'', "'\\''"))
     }
 }
 
-// 跳过空白字符
-fn skip_whitespace(chars: &mut Peekable<Chars>) {
-    while let Some(&c) = chars.peek() {
-        if c.is_whitespace() {
+// 把多个参数连接成一行命令文本，重新解析后得到相同的参数
+pub fn join_words(words: &[String]) -> String {
+    words.iter().map(|w| quote_word(w)).collect::<Vec<_>>().join(" ")
+}
+
+// hook add、schedule every、task start 等接受命令的内建命令把参数变成命令文本：
+// 单个参数视为完整的命令文本，例如 'ls -l | wc'；多个参数按原有的边界重新加引号
+pub fn command_text(words: &[String]) -> String {
+    match words {
+        [line] => line.clone(),
+        words => join_words(words),
+    }
+}
+
+// 跳过空白、续行和注释：引号之外、在词开头的 # 直到行尾是注释，词中间的 # 是普通字符，例如 a#b
+fn skip_blank(chars: &mut Lexer) {
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.starts_with("\\\n") {
+            chars.next();
             chars.next();
+        } else if chars.next_if_eq('#').is_some() {
+            while chars.next_if(|&c| c != '\n').is_some() {}
         } else {
             break;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<Word> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Word(word) => Some(word),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn var(name: &str) -> Segment {
+        Segment::Var {
+            name: name.to_string(),
+            quoted: false,
+        }
+    }
+
+    #[test]
+    fn quoted_empty_arguments() {
+        let words = words("grep \"\" file ''");
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[1].segments, [Segment::Double(String::new())]);
+        assert_eq!(words[3].segments, [Segment::Single(String::new())]);
+        assert_eq!(words[1].text(), "");
+    }
+
+    #[test]
+    fn adjacent_segments_form_one_word() {
+        let words = words("echo foo\"bar baz\"qux --opt='a b'");
+        assert_eq!(words.len(), 3);
+        assert_eq!(
+            words[1].segments,
+            [
+                Segment::Plain("foo".to_string()),
+                Segment::Double("bar baz".to_string()),
+                Segment::Plain("qux".to_string()),
+            ]
+        );
+        assert_eq!(words[2].text(), "--opt=a b");
+    }
+
+    #[test]
+    fn segments_record_quoting() {
+        let words = words("echo '$HOME' \"$HOME\" $HOME");
+        assert_eq!(words[1].segments, [Segment::Single("$HOME".to_string())]);
+        assert_eq!(
+            words[2].segments,
+            [Segment::Var {
+                name: "HOME".to_string(),
+                quoted: true,
+            }]
+        );
+        assert_eq!(words[3].segments, [var("HOME")]);
+    }
+
+    #[test]
+    fn positional_and_special_parameters() {
+        let words = words("echo $0 $1 $10 ${10} $# $@ $* $?");
+        let segments: Vec<&[Segment]> = words[1..].iter().map(|w| w.segments.as_slice()).collect();
+        assert_eq!(segments[0], [var("0")]);
+        assert_eq!(segments[1], [var("1")]);
+        // $10 是 $1 后面跟着 0
+        assert_eq!(segments[2], [var("1"), Segment::Plain("0".to_string())]);
+        assert_eq!(segments[3], [var("10")]);
+        assert_eq!(segments[4], [var("#")]);
+        assert_eq!(segments[5], [var("@")]);
+        assert_eq!(segments[6], [var("*")]);
+        assert_eq!(segments[7], [var("?")]);
+    }
+
+    #[test]
+    fn parameter_operations() {
+        let words = words("echo ${1:-x} ${#1} ${#} ${#NAME} ${@:+y}");
+        let param = |word: &Word| match &word.segments[..] {
+            [Segment::Param { name, op, .. }] => (name.clone(), *op),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(param(&words[1]), ("1".to_string(), ParamOp::Default(true)));
+        assert_eq!(param(&words[2]), ("1".to_string(), ParamOp::Length));
+        assert_eq!(words[3].segments, [var("#")]);
+        assert_eq!(param(&words[4]), ("NAME".to_string(), ParamOp::Length));
+        assert_eq!(param(&words[5]), ("@".to_string(), ParamOp::Alternative(true)));
+        assert!(tokenize("echo ${1a}").is_err());
+        assert!(tokenize("echo ${-x}").is_err());
+    }
+
+    #[test]
+    fn array_subscripts() {
+        let words = words("echo ${L[0]} ${L[@]} ${#L[*]}");
+        let param = |word: &Word| match &word.segments[..] {
+            [Segment::Param { name, op, .. }] => (name.clone(), *op),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(param(&words[1]), ("L".to_string(), ParamOp::Element));
+        assert_eq!(words[1].source(), "${L[0]}");
+        assert_eq!(param(&words[2]), ("L".to_string(), ParamOp::Element));
+        assert_eq!(param(&words[3]), ("L".to_string(), ParamOp::Count));
+        assert_eq!(words[3].source(), "${#L[@]}");
+        assert!(tokenize("echo ${L[]}").is_err());
+    }
+
+    #[test]
+    fn dollar_without_name_is_literal() {
+        assert_eq!(words("echo a$ $-")[1].text(), "a$");
+        assert_eq!(words("echo a$ $-")[2].text(), "$-");
+    }
+
+    #[test]
+    fn lists_and_connectors() {
+        let lists = parse_input("a | b && c || d; e &", &AliasTable::default()).unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].pipelines.len(), 3);
+        assert_eq!(lists[0].pipelines[0].len(), 2);
+        assert_eq!(lists[0].connectors, [Connector::And, Connector::Or]);
+        assert!(!lists[0].background);
+        assert!(lists[1].background);
+    }
+
+    #[test]
+    fn leading_assignments() {
+        let lists = parse_input("A=1 B=\"x y\" env", &AliasTable::default()).unwrap();
+        let Command::Simple(command) = &lists[0].pipelines[0][0] else {
+            panic!("不是简单命令");
+        };
+        let names: Vec<&str> = command.assignments.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["A", "B"]);
+        assert_eq!(command.assignments[1].1.text(), "x y");
+        assert_eq!(command.words.len(), 1);
+        // 命令名之后的 NAME=value 是普通参数
+        assert_eq!(words("env A=1").len(), 2);
+    }
+
+    #[test]
+    fn incomplete_input() {
+        for input in ["echo \"abc", "echo 'abc", "echo ${x", "echo $(ls", "a &&", "a ||", "a |"] {
+            assert!(
+                matches!(parse_input(input, &AliasTable::default()), Err(ShellError::Incomplete(..))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn double_quoted_source_round_trips() {
+        let original = words(r#"echo "a\"b" "\$HOME" "x\\y" "\`c\`" "$X\$""#);
+        let line = original.iter().map(Word::source).collect::<Vec<_>>().join(" ");
+        assert_eq!(words(&line), original);
+        assert_eq!(original[1].source(), r#""a\"b""#);
+    }
+
+    #[test]
+    fn join_words_round_trips() {
+        let original: Vec<String> = ["echo", "", "a b", "it's", "$HOME", "x|y", "中文"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let line = join_words(&original);
+        let parsed: Vec<String> = words(&line).iter().map(Word::text).collect();
+        assert_eq!(parsed, original);
+    }
+
+    #[test]
+    fn command_text_keeps_argument_boundaries() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        // echo "a  b" 是两个参数，echo a  b 是三个参数
+        assert_eq!(command_text(&args(&["echo", "a  b"])), "echo 'a  b'");
+        assert_eq!(command_text(&args(&["echo", "a", "b"])), "echo a b");
+        // 单个参数是完整的命令文本，原样保留
+        assert_eq!(command_text(&args(&["echo a  b | wc"])), "echo a  b | wc");
+        assert_eq!(quote_word("it's"), "'it'\\''s'");
+    }
+}
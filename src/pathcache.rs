@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// $PATH 中可执行文件的索引：命令名到完整路径，前面的目录优先
+// 第一次查找时才扫描目录；PATH 改变或执行 rehash 之后丢弃，下一次查找时重新扫描
+#[derive(Debug, Default)]
+pub struct PathCache {
+    // 建立索引时使用的 PATH
+    path: String,
+    commands: Option<BTreeMap<String, PathBuf>>,
+}
+
+// 在Shell与行编辑器之间共享的索引，行编辑器用它补全命令名
+pub type SharedPathCache = Rc<RefCell<PathCache>>;
+
+impl PathCache {
+    // PATH 的值改变时丢弃索引
+    pub fn update(&mut self, path: &str) {
+        if self.path != path {
+            self.path = path.to_string();
+            self.commands = None;
+        }
+    }
+
+    // 丢弃索引，例如在 PATH 的目录中安装了新程序之后
+    pub fn clear(&mut self) {
+        self.commands = None;
+    }
+
+    // 命令的完整路径，不在 PATH 中时返回 None
+    pub fn lookup(&mut self, name: &str) -> Option<PathBuf> {
+        self.commands().get(name).cloned()
+    }
+
+    // 以 prefix 开头的命令名
+    pub fn names_with_prefix(&mut self, prefix: &str) -> Vec<String> {
+        self.commands()
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn commands(&mut self) -> &BTreeMap<String, PathBuf> {
+        let path = &self.path;
+        self.commands.get_or_insert_with(|| scan(path))
+    }
+}
+
+// 不经过索引直接在 PATH 的目录中查找命令，与 execvp 一样空的目录项表示当前目录
+pub fn search(path: &str, name: &str) -> Option<PathBuf> {
+    path.split(':')
+        .map(|dir| Path::new(if dir.is_empty() { "." } else { dir }).join(name))
+        .find(|path| is_executable(path))
+}
+
+// 扫描 PATH 中的目录，同名的命令只保留最先找到的；空的目录项表示当前目录，与执行时一样不建立索引
+fn scan(path: &str) -> BTreeMap<String, PathBuf> {
+    let mut commands = BTreeMap::new();
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if commands.contains_key(&name) {
+                continue;
+            }
+            let path = entry.path();
+            if is_executable(&path) {
+                commands.insert(name, path);
+            }
+        }
+    }
+    commands
+}
+
+// 是否是可执行的普通文件（跟随符号链接）
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
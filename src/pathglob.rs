@@ -0,0 +1,152 @@
+use crate::glob;
+use std::fs;
+use std::path::Path;
+
+// 通配符模式中以 '/' 分隔的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    // 不含通配符的段，直接拼接
+    Literal(String),
+    // 含通配符的段，列出目录逐个匹配
+    Pattern(String),
+    // 单独成段的 **，匹配零个或多个目录
+    Globstar,
+}
+
+// 按文件系统展开通配符模式，返回排序后的匹配路径，没有匹配时返回空
+// 以 . 开头的文件只被以 . 开头的段匹配，** 不进入隐藏目录，也不跟随指向目录的符号链接，
+// 例如 src/**/*.rs 匹配 src/main.rs 和 src/a/b.rs，末尾的 ** 匹配其下的全部文件和目录
+pub fn expand(pattern: &str) -> Vec<String> {
+    let (root, relative) = match pattern.strip_prefix('/') {
+        Some(relative) => ("/", relative),
+        None => ("", pattern),
+    };
+    let parts: Vec<Part> = relative.split('/').map(compile).collect();
+
+    let mut paths = Vec::new();
+    walk(root, &parts, &mut paths);
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn compile(component: &str) -> Part {
+    if component == "**" {
+        Part::Globstar
+    } else if glob::has_wildcards(component) || component.contains('\\') {
+        Part::Pattern(component.to_string())
+    } else {
+        Part::Literal(component.to_string())
+    }
+}
+
+// 在 prefix 之下匹配剩余的段，匹配的路径放入 paths
+fn walk(prefix: &str, parts: &[Part], paths: &mut Vec<String>) {
+    let Some((part, rest)) = parts.split_first() else {
+        return;
+    };
+
+    match part {
+        Part::Literal(name) => {
+            let path = join(prefix, name);
+            if rest.is_empty() {
+                if exists(&path) {
+                    paths.push(path);
+                }
+            } else if Path::new(&path).is_dir() {
+                walk(&path, rest, paths);
+            }
+        }
+        Part::Pattern(pattern) => {
+            for name in entries(prefix, pattern.starts_with('.')) {
+                if !glob::matches(pattern, &name) {
+                    continue;
+                }
+                let path = join(prefix, &name);
+                if rest.is_empty() {
+                    paths.push(path);
+                } else if Path::new(&path).is_dir() {
+                    walk(&path, rest, paths);
+                }
+            }
+        }
+        Part::Globstar => {
+            // 匹配零个目录
+            walk(prefix, rest, paths);
+            for name in entries(prefix, false) {
+                let path = join(prefix, &name);
+                if rest.is_empty() {
+                    paths.push(path.clone());
+                }
+                if is_real_dir(&path) {
+                    walk(&path, parts, paths);
+                }
+            }
+        }
+    }
+}
+
+// 目录中的文件名，hidden 为 false 时跳过以 . 开头的文件
+fn entries(dir: &str, hidden: bool) -> Vec<String> {
+    let dir = if dir.is_empty() { "." } else { dir };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| hidden || !name.starts_with('.'))
+        .collect()
+}
+
+// 不是符号链接的目录，** 只递归进入这样的目录，避免链接成环
+fn is_real_dir(path: &str) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir())
+}
+
+// 路径存在，指向不存在目标的符号链接也算存在
+fn exists(path: &str) -> bool {
+    !path.is_empty() && (Path::new(path).exists() || fs::symlink_metadata(path).is_ok())
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() || prefix.ends_with('/') {
+        format!("{}{}", prefix, name)
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_path;
+
+    #[test]
+    fn expand_in_directory() {
+        let dir = temp_path("pathglob");
+        for path in ["a.rs", "b.txt", ".hidden.rs", "sub/c.rs", "sub/deep/d.rs", ".git/e.rs"] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let root = dir.display().to_string();
+        let expand_in = |pattern: &str| -> Vec<String> {
+            expand(&format!("{}/{}", root, pattern))
+                .into_iter()
+                .map(|path| path[root.len() + 1..].to_string())
+                .collect()
+        };
+
+        let patterns = ["*.rs", ".*.rs", "?.t[a-z]t", "**/*.rs", "*.none"];
+        let results: Vec<Vec<String>> = patterns.iter().map(|pattern| expand_in(pattern)).collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // 以 . 开头的文件只被以 . 开头的模式匹配，** 不进入隐藏目录
+        assert_eq!(results[0], ["a.rs"]);
+        assert_eq!(results[1], [".hidden.rs"]);
+        assert_eq!(results[2], ["b.txt"]);
+        assert_eq!(results[3], ["a.rs", "sub/c.rs", "sub/deep/d.rs"]);
+        assert!(results[4].is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::ShellError;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+// 内建命令 basename：basename 名称 [后缀]，或 basename [-a] [-s 后缀] 名称...
+pub fn run_basename(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut multiple = false;
+    let mut suffix = None;
+    let mut names = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-a" => multiple = true,
+            "-s" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError("basename: -s 需要参数".to_string()))?;
+                suffix = Some(value.as_str());
+                multiple = true;
+            }
+            "--" => names.extend(iter.by_ref()),
+            _ => names.push(arg),
+        }
+    }
+
+    // 传统形式：第二个参数是要去掉的后缀
+    if !multiple && names.len() == 2 {
+        suffix = Some(names.pop().map(|s| s.as_str()).unwrap_or(""));
+    }
+    if names.is_empty() || (!multiple && names.len() > 1) {
+        return Err(ShellError::CommandError(
+            "用法: basename 名称 [后缀] 或 basename [-a] [-s 后缀] 名称...".to_string(),
+        ));
+    }
+
+    for name in names {
+        writeln!(out, "{}", basename(name, suffix))?;
+    }
+    Ok(())
+}
+
+// 内建命令 dirname：dirname 名称...
+pub fn run_dirname(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("用法: dirname 名称...".to_string()));
+    }
+
+    for name in args {
+        writeln!(out, "{}", dirname(name))?;
+    }
+    Ok(())
+}
+
+// 内建命令 realpath：realpath [-m] 路径...
+// 默认解析符号链接且路径必须存在；-m 只按字面规范化，路径不必存在
+pub fn run_realpath(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let (lexical, paths) = match args.first() {
+        Some(flag) if flag == "-m" => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if paths.is_empty() {
+        return Err(ShellError::CommandError("用法: realpath [-m] 路径...".to_string()));
+    }
+
+    for path in paths {
+        let resolved = if lexical {
+            normalize(&env::current_dir()?.join(path))
+        } else {
+            fs::canonicalize(path)
+                .map_err(|e| ShellError::CommandError(format!("realpath: '{}': {}", path, e)))?
+        };
+        writeln!(out, "{}", resolved.display())?;
+    }
+    Ok(())
+}
+
+// 按POSIX规则取最后一个路径分量，后缀与整个分量相同时不去掉
+fn basename<'a>(name: &'a str, suffix: Option<&str>) -> &'a str {
+    let trimmed = name.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if name.is_empty() { "" } else { "/" };
+    }
+
+    let base = match trimmed.rfind('/') {
+        Some(i) => &trimmed[i + 1..],
+        None => trimmed,
+    };
+    match suffix {
+        Some(suffix) if !suffix.is_empty() && base != suffix => base.strip_suffix(suffix).unwrap_or(base),
+        _ => base,
+    }
+}
+
+// 按POSIX规则去掉最后一个路径分量
+fn dirname(name: &str) -> &str {
+    let trimmed = name.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if name.is_empty() { "." } else { "/" };
+    }
+
+    match trimmed.rfind('/') {
+        Some(i) => {
+            let parent = trimmed[..i].trim_end_matches('/');
+            if parent.is_empty() { "/" } else { parent }
+        }
+        None => ".",
+    }
+}
+
+// 按字面处理 . 和 ..，不访问文件系统
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
@@ -0,0 +1,175 @@
+// 动态插件：从插件目录加载共享库，为Shell提供额外的内建命令和补全
+//
+// 插件需要导出以下C ABI符号：
+//   u32 rsh_plugin_abi_version(void);
+//       返回插件遵循的ABI版本，必须等于 PLUGIN_ABI_VERSION
+//   const char *const *rsh_plugin_builtins(void);
+//       返回以NULL结尾的内建命令名数组，数组在插件生命周期内有效
+//   int rsh_plugin_call(const char *name, int argc, const char *const *argv);
+//       执行名为name的内建命令，argv不含命令名本身，返回状态码
+// 可选导出：
+//   void rsh_plugin_complete(const char *command, const char *prefix,
+//                            void (*add)(void *context, const char *word), void *context);
+//       补全命令command的参数，对每个补全词调用一次 add(context, word)，word 只需在调用期间有效
+use crate::completion::CompletionRegistry;
+use crate::error::ShellError;
+use libloading::Library;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type BuiltinsFn = unsafe extern "C" fn() -> *const *const c_char;
+type CallFn = unsafe extern "C" fn(*const c_char, c_int, *const *const c_char) -> c_int;
+type AddFn = unsafe extern "C" fn(*mut c_void, *const c_char);
+type CompleteFn = unsafe extern "C" fn(*const c_char, *const c_char, AddFn, *mut c_void);
+
+// 插件提供的单个内建命令
+struct PluginBuiltin {
+    call: CallFn,
+    // 保持共享库已加载，call 指针才有效
+    _library: Rc<Library>,
+}
+
+// 已加载插件提供的内建命令表
+#[derive(Default)]
+pub struct Plugins {
+    builtins: HashMap<String, PluginBuiltin>,
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.builtins.keys()).finish()
+    }
+}
+
+impl Plugins {
+    // 加载目录下所有共享库，单个插件失败只打印错误
+    pub fn load_dir(&mut self, dir: &Path, registry: &mut CompletionRegistry) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(std::env::consts::DLL_EXTENSION) {
+                continue;
+            }
+            if let Err(e) = self.load(&path, registry) {
+                eprintln!("插件加载失败 '{}': {}", path.display(), e);
+            }
+        }
+    }
+
+    // 加载单个插件并注册其内建命令，插件导出了 rsh_plugin_complete 时同时注册补全
+    pub fn load(&mut self, path: &Path, registry: &mut CompletionRegistry) -> Result<(), ShellError> {
+        let plugin_error = |e: libloading::Error| ShellError::CommandError(e.to_string());
+
+        // SAFETY: 加载共享库会运行其初始化代码，插件目录中的库被视为可信代码
+        let library = Rc::new(unsafe { Library::new(path) }.map_err(plugin_error)?);
+
+        // SAFETY: 符号类型与上面约定的ABI一致
+        let (abi_version, builtins, call, complete) = unsafe {
+            let abi_version = *library.get::<AbiVersionFn>(b"rsh_plugin_abi_version\0").map_err(plugin_error)?;
+            let builtins = *library.get::<BuiltinsFn>(b"rsh_plugin_builtins\0").map_err(plugin_error)?;
+            let call = *library.get::<CallFn>(b"rsh_plugin_call\0").map_err(plugin_error)?;
+            let complete = library.get::<CompleteFn>(b"rsh_plugin_complete\0").ok().map(|f| *f);
+            (abi_version, builtins, call, complete)
+        };
+
+        // SAFETY: 见上，函数由插件按约定实现
+        let version = unsafe { abi_version() };
+        if version != PLUGIN_ABI_VERSION {
+            return Err(ShellError::CommandError(format!(
+                "ABI版本不匹配: 需要 {}，插件为 {}",
+                PLUGIN_ABI_VERSION, version
+            )));
+        }
+
+        // SAFETY: 插件保证返回以NULL结尾、在库加载期间有效的字符串数组
+        let mut names = Vec::new();
+        unsafe {
+            let mut cursor = builtins();
+            while !cursor.is_null() && !(*cursor).is_null() {
+                names.push(CStr::from_ptr(*cursor).to_string_lossy().into_owned());
+                cursor = cursor.add(1);
+            }
+        }
+
+        for name in names {
+            self.builtins.insert(
+                name,
+                PluginBuiltin {
+                    call,
+                    _library: Rc::clone(&library),
+                },
+            );
+        }
+        if let Some(complete) = complete {
+            registry.add_provider(Box::new(move |command, prefix| complete_with(&library, complete, command, prefix)));
+        }
+
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.builtins.contains_key(name)
+    }
+
+    // 执行插件内建命令，不是插件命令时返回 None
+    // 插件自己写进程的描述符 1 和 2，不经过Shell的 Stdio，重定向和 run_str 的捕获对它不起作用
+    pub fn call(&self, name: &str, args: &[String]) -> Option<Result<i32, ShellError>> {
+        let builtin = self.builtins.get(name)?;
+        Some(call_builtin(builtin, name, args))
+    }
+}
+
+// 调用插件的 rsh_plugin_complete，收集它通过 add_candidate 传回的补全词
+fn complete_with(_library: &Library, complete: CompleteFn, command: &str, prefix: &str) -> Vec<String> {
+    let (Ok(command), Ok(prefix)) = (CString::new(command), CString::new(prefix)) else {
+        return Vec::new();
+    };
+    let mut words: Vec<String> = Vec::new();
+    // SAFETY: 指针在调用期间有效，库由调用者持有的 _library 保持加载；
+    // context 指向 words，只在 add_candidate 中使用
+    unsafe {
+        complete(
+            command.as_ptr(),
+            prefix.as_ptr(),
+            add_candidate,
+            (&mut words as *mut Vec<String>).cast(),
+        )
+    };
+    words
+}
+
+unsafe extern "C" fn add_candidate(context: *mut c_void, word: *const c_char) {
+    if context.is_null() || word.is_null() {
+        return;
+    }
+    // SAFETY: context 是 complete_with 传入的 Vec<String>，word 是插件传入的以NUL结尾的字符串
+    unsafe {
+        let words = &mut *context.cast::<Vec<String>>();
+        words.push(CStr::from_ptr(word).to_string_lossy().into_owned());
+    }
+}
+
+fn call_builtin(builtin: &PluginBuiltin, name: &str, args: &[String]) -> Result<i32, ShellError> {
+    let to_cstring = |s: &str| {
+        CString::new(s).map_err(|_| ShellError::CommandError(format!("参数包含空字符: '{}'", s)))
+    };
+
+    let name = to_cstring(name)?;
+    let args = args.iter().map(|a| to_cstring(a)).collect::<Result<Vec<_>, _>>()?;
+    let argv: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+
+    // SAFETY: 所有指针在调用期间有效，库由 _library 保持加载
+    let status = unsafe { (builtin.call)(name.as_ptr(), argv.len() as c_int, argv.as_ptr()) };
+    Ok(status)
+}
@@ -0,0 +1,152 @@
+use crate::error::ShellError;
+use crate::disk::format_size;
+use crate::json::quote;
+use std::fs;
+use std::io::{self, Write};
+
+// 从 /proc 读取的一个进程的信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcInfo {
+    pub pid: i32,
+    pub ppid: i32,
+    // 进程组，后台作业的各个进程属于同一个进程组
+    pub pgrp: i32,
+    pub command: String,
+    // 启动以来平均的CPU占用率（百分比）
+    pub cpu_percent: f64,
+    // 常驻内存，单位字节
+    pub rss: u64,
+}
+
+// 内建命令 procs：procs [--json] [PID...]，列出进程的PID、CPU、内存和命令行
+pub fn run_procs(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let mut json = false;
+    let mut pids = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ => {
+                let pid = arg
+                    .parse::<i32>()
+                    .map_err(|_| ShellError::CommandError(format!("procs: 无效的进程号 '{}'\n用法: procs [--json] [PID...]", arg)))?;
+                pids.push(pid);
+            }
+        }
+    }
+
+    let procs = if pids.is_empty() {
+        list_processes()?
+    } else {
+        pids.iter()
+            .map(|&pid| read_process(pid).map_err(|e| ShellError::CommandError(format!("procs: 进程 {}: {}", pid, e))))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    if json {
+        let items: Vec<String> = procs
+            .iter()
+            .map(|p| {
+                format!(
+                    "{{\"pid\":{},\"ppid\":{},\"command\":{},\"cpu\":{:.1},\"rss\":{}}}",
+                    p.pid,
+                    p.ppid,
+                    quote(&p.command),
+                    p.cpu_percent,
+                    p.rss
+                )
+            })
+            .collect();
+        writeln!(out, "[{}]", items.join(","))?;
+    } else {
+        writeln!(out, "{:>7} {:>7} {:>5} {:>7}  命令", "PID", "PPID", "%CPU", "内存")?;
+        for p in &procs {
+            writeln!(out, 
+                "{:>7} {:>7} {:>5.1} {:>7}  {}",
+                p.pid,
+                p.ppid,
+                p.cpu_percent,
+                format_size(p.rss),
+                p.command
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// 按PID顺序列出全部进程，读取时已经退出的进程被跳过
+pub fn list_processes() -> Result<Vec<ProcInfo>, ShellError> {
+    let mut pids: Vec<i32> = fs::read_dir("/proc")
+        .map_err(|e| ShellError::CommandError(format!("无法读取 /proc: {}", e)))?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    pids.sort_unstable();
+    Ok(pids.into_iter().filter_map(|pid| read_process(pid).ok()).collect())
+}
+
+// 读取 /proc/PID/stat 和 cmdline
+pub fn read_process(pid: i32) -> io::Result<ProcInfo> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    // 进程名在括号中，可能含有空格和括号，以最后一个 ')' 为界
+    let open = stat.find('(').ok_or_else(invalid_stat)?;
+    let close = stat.rfind(')').ok_or_else(invalid_stat)?;
+    let name = &stat[open + 1..close];
+    // fields[0] 是状态，对应 proc(5) 中的第 3 个字段
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    let field = |n: usize| -> io::Result<u64> {
+        fields
+            .get(n - 3)
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(invalid_stat)
+    };
+
+    let ticks = clock_ticks();
+    let cpu_seconds = (field(14)? + field(15)?) as f64 / ticks;
+    let elapsed = uptime()? - field(22)? as f64 / ticks;
+    let cpu_percent = if elapsed > 0.0 { cpu_seconds / elapsed * 100.0 } else { 0.0 };
+
+    // cmdline 以空字符分隔参数，内核线程的 cmdline 为空，显示 [进程名]
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+    let command = if cmdline.is_empty() {
+        format!("[{}]", name)
+    } else {
+        cmdline
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    Ok(ProcInfo {
+        pid,
+        ppid: field(4)? as i32,
+        pgrp: field(5)? as i32,
+        command,
+        cpu_percent,
+        rss: field(24)? * page_size(),
+    })
+}
+
+fn invalid_stat() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "无法解析 /proc 中的进程状态")
+}
+
+fn uptime() -> io::Result<f64> {
+    let text = fs::read_to_string("/proc/uptime")?;
+    text.split_whitespace()
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid_stat)
+}
+
+fn clock_ticks() -> f64 {
+    // SAFETY: sysconf 只读取系统配置
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as f64 } else { 100.0 }
+}
+
+fn page_size() -> u64 {
+    // SAFETY: sysconf 只读取系统配置
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if size > 0 { size as u64 } else { 4096 }
+}
@@ -0,0 +1,73 @@
+use crate::command::exit_child;
+use crate::error::ShellError;
+use crate::parser::parse_input;
+use crate::rusage::wait_with_rusage;
+use crate::shell::Shell;
+use crate::signals::fork_child;
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+
+// 进程替换中Shell持有的管道一端和运行命令的子Shell
+#[derive(Debug)]
+struct Active {
+    fd: OwnedFd,
+    pid: libc::pid_t,
+}
+
+// 正在进行的进程替换，在使用它们的管道结束后清理
+#[derive(Debug, Default)]
+pub struct ProcessSubstitutions {
+    active: Vec<Active>,
+}
+
+impl ProcessSubstitutions {
+    // 当前的数量，传给 finish 只清理此后开始的
+    pub fn mark(&self) -> usize {
+        self.active.len()
+    }
+
+    // 关闭 mark 之后开始的进程替换的管道并等待其子Shell退出：
+    // <(...) 的命令此时如果还在写入会收到 SIGPIPE，>(...) 的命令读到文件结尾
+    pub fn finish(&mut self, mark: usize) {
+        for active in self.active.drain(mark.min(self.active.len())..) {
+            drop(active.fd);
+            let _ = wait_with_rusage(active.pid);
+        }
+    }
+}
+
+// 进程替换 <(命令) 和 >(命令)：在子Shell中执行命令，它的标准输出（<）或标准输入（>）连接到管道，
+// 返回Shell一端的路径 /dev/fd/N；这一端在执行命令时保持打开并被继承，直到所在的管道结束
+pub fn process_substitution(shell: &mut Shell, command: &str, output: bool) -> Result<String, ShellError> {
+    let lists = parse_input(command, &shell.aliases)?;
+    let (reader, writer) = io::pipe()?;
+    let (kept, given): (OwnedFd, OwnedFd) = if output {
+        (writer.into(), reader.into())
+    } else {
+        (reader.into(), writer.into())
+    };
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    let pid = fork_child()?;
+    if pid == 0 {
+        drop(kept);
+        // 其他进程替换的管道不应留在这个子Shell中，否则 >(...) 读不到文件结尾
+        shell.process_substitutions = ProcessSubstitutions::default();
+        let target = if output { libc::STDIN_FILENO } else { libc::STDOUT_FILENO };
+        // SAFETY: 子Shell的标准输入或输出改为管道的一端
+        unsafe { libc::dup2(given.as_raw_fd(), target) };
+        drop(given);
+        exit_child(shell, lists);
+    }
+    drop(given);
+    let fd = kept.as_raw_fd();
+    shell.process_substitutions.active.push(Active { fd: kept, pid });
+
+    // 管道默认在 exec 时关闭，去掉这个标志使命令能通过 /dev/fd 打开它
+    // SAFETY: fd 由刚登记的 OwnedFd 持有，仍然有效
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, 0) } < 0 {
+        return Err(ShellError::Io(io::Error::last_os_error()));
+    }
+    Ok(format!("/dev/fd/{}", fd))
+}
@@ -0,0 +1,80 @@
+use crate::error::ShellError;
+use std::fs::File;
+use std::io::{Read, Write};
+
+// 没有给出范围时 rand 的最大值，与 bash 的 $RANDOM 相同
+const DEFAULT_MAX: i64 = 32767;
+
+// 内建命令 rand：rand [最小值 最大值]，输出闭区间内均匀分布的随机整数
+pub fn run_rand(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let (min, max) = match args {
+        [] => (0, DEFAULT_MAX),
+        [min, max] => (parse_bound(min)?, parse_bound(max)?),
+        _ => return Err(ShellError::CommandError("用法: rand [最小值 最大值]".to_string())),
+    };
+    if min > max {
+        return Err(ShellError::CommandError(format!("rand: 最小值 {} 大于最大值 {}", min, max)));
+    }
+
+    // 区间长度减一，用 u64 表示才不会在 i64 全范围时溢出
+    let span = max.wrapping_sub(min) as u64;
+    let offset = if span == u64::MAX {
+        random_u64()?
+    } else {
+        // 拒绝落在最后一个不完整区间中的值，避免取模带来的偏差
+        let range = span + 1;
+        let limit = u64::MAX - u64::MAX % range;
+        loop {
+            let value = random_u64()?;
+            if value < limit {
+                break value % range;
+            }
+        }
+    };
+
+    writeln!(out, "{}", min.wrapping_add(offset as i64))?;
+    Ok(())
+}
+
+// 内建命令 uuid：输出一个随机生成的（第4版）UUID
+pub fn run_uuid(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    if !args.is_empty() {
+        return Err(ShellError::CommandError("用法: uuid".to_string()));
+    }
+
+    let mut bytes = [0u8; 16];
+    fill_random(&mut bytes)?;
+    // 版本号 4，变体为 RFC 4122
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    writeln!(out, 
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )?;
+    Ok(())
+}
+
+fn parse_bound(value: &str) -> Result<i64, ShellError> {
+    value
+        .parse()
+        .map_err(|_| ShellError::CommandError(format!("rand: 无效的整数 '{}'", value)))
+}
+
+fn random_u64() -> Result<u64, ShellError> {
+    let mut bytes = [0u8; 8];
+    fill_random(&mut bytes)?;
+    Ok(u64::from_ne_bytes(bytes))
+}
+
+// 从操作系统的随机数源读取
+fn fill_random(buf: &mut [u8]) -> Result<(), ShellError> {
+    File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(buf))
+        .map_err(|e| ShellError::CommandError(format!("无法读取系统随机数: {}", e)))
+}
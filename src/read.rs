@@ -0,0 +1,346 @@
+use crate::error::ShellError;
+use crate::vars::{is_valid_name, Variables};
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// read 的选项
+#[derive(Debug, Default)]
+struct ReadOptions {
+    // -r：反斜杠不作转义
+    raw: bool,
+    // -s：不回显输入
+    silent: bool,
+    // -p：读取前在标准错误上打印的提示
+    prompt: Option<String>,
+    // -t：等待输入的最长时间
+    timeout: Option<Duration>,
+    // -n：读到这么多个字符就返回，不必等回车
+    count: Option<usize>,
+    names: Vec<String>,
+}
+
+// 读取结束的原因
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Done,
+    Eof,
+    Timeout,
+}
+
+// 内建命令 read：read [-r] [-s] [-p 提示] [-t 秒数] [-n 字符数] [变量名...]
+// 从标准输入读取一行，按空白拆分后依次赋给变量，最后一个变量得到剩余部分；
+// 没有变量名时整行存入 REPLY。超时或遇到输入结尾时与 false 一样只返回状态码 1，
+// 因此 while read line 循环在输入结束时安静地退出；已读到的内容仍会赋值
+pub fn run_read(err: &mut impl Write, vars: &mut Variables, args: &[String]) -> Result<i32, ShellError> {
+    let options = parse_read_args(args)?;
+
+    if let Some(prompt) = &options.prompt {
+        write!(err, "{}", prompt)?;
+    }
+
+    // -n 时关闭行缓冲，按键无需回车即可读到；-s 时关闭回显
+    let guard = TerminalMode::enter(options.count.is_none(), !options.silent, true)?;
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let result = read_input(&options, deadline);
+    drop(guard);
+    let (bytes, outcome) = result?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    if options.names.is_empty() {
+        vars.set("REPLY", &text);
+    } else {
+        assign_fields(vars, &options.names, &text);
+    }
+
+    match outcome {
+        Outcome::Done => Ok(0),
+        Outcome::Eof | Outcome::Timeout => Ok(1),
+    }
+}
+
+fn parse_read_args(args: &[String]) -> Result<ReadOptions, ShellError> {
+    let mut options = ReadOptions::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-r" => options.raw = true,
+            "-s" => options.silent = true,
+            "-p" | "-t" | "-n" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| ShellError::CommandError(format!("read: {} 需要参数", arg)))?;
+                match arg.as_str() {
+                    "-p" => options.prompt = Some(value.clone()),
+                    "-t" => {
+                        // 负数、NaN 以及超出 Duration 范围的数值（如 1e300）都是无效的
+                        let timeout = value
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                            .ok_or_else(|| ShellError::CommandError(format!("read: 无效的超时时间 '{}'", value)))?;
+                        options.timeout = Some(timeout);
+                    }
+                    _ => {
+                        let count = value
+                            .parse::<usize>()
+                            .map_err(|_| ShellError::CommandError(format!("read: 无效的字符数 '{}'", value)))?;
+                        options.count = Some(count);
+                    }
+                }
+            }
+            "--" => {
+                options.names.extend(iter.by_ref().cloned());
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("read: 未知的选项 '{}'", flag)));
+            }
+            name => options.names.push(name.to_string()),
+        }
+    }
+
+    if let Some(name) = options.names.iter().find(|name| !is_valid_name(name)) {
+        return Err(ShellError::CommandError(format!("read: 无效的变量名 '{}'", name)));
+    }
+
+    Ok(options)
+}
+
+// 逐字节读取标准输入，不经过缓冲，之后的命令和行编辑器能读到剩下的输入
+fn read_input(options: &ReadOptions, deadline: Option<Instant>) -> Result<(Vec<u8>, Outcome), ShellError> {
+    let mut bytes = Vec::new();
+    let mut escaped = false;
+
+    loop {
+        if let Some(count) = options.count
+            && let Ok(text) = std::str::from_utf8(&bytes)
+            && text.chars().count() >= count
+        {
+            return Ok((bytes, Outcome::Done));
+        }
+
+        if let Some(deadline) = deadline
+            && !wait_readable(deadline.saturating_duration_since(Instant::now()))?
+        {
+            return Ok((bytes, Outcome::Timeout));
+        }
+
+        let byte = match read_byte()? {
+            Some(byte) => byte,
+            None => return Ok((bytes, Outcome::Eof)),
+        };
+
+        if escaped {
+            // 反斜杠加换行是续行，其他字符按字面保留
+            escaped = false;
+            if byte != b'\n' {
+                bytes.push(byte);
+            }
+        } else if byte == b'\n' {
+            return Ok((bytes, Outcome::Done));
+        } else if byte == b'\\' && !options.raw {
+            escaped = true;
+        } else {
+            bytes.push(byte);
+        }
+    }
+}
+
+pub(crate) fn read_byte() -> Result<Option<u8>, ShellError> {
+    let mut byte = 0u8;
+    loop {
+        // SAFETY: 向一个字节的缓冲区读取至多一个字节
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+        match n {
+            1 => return Ok(Some(byte)),
+            0 => return Ok(None),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(ShellError::Io(err));
+                }
+            }
+        }
+    }
+}
+
+// 等待标准输入可读，超时返回 false
+pub(crate) fn wait_readable(timeout: Duration) -> Result<bool, ShellError> {
+    let mut fds = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+
+    // SAFETY: fds 是一个有效的 pollfd
+    let ret = unsafe { libc::poll(&mut fds, 1, millis) };
+    if ret < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::Interrupted {
+            return Ok(true);
+        }
+        return Err(ShellError::Io(err));
+    }
+    Ok(ret > 0)
+}
+
+// 按空白拆分输入，最后一个变量得到剩余的全部内容
+fn assign_fields(vars: &mut Variables, names: &[String], text: &str) {
+    let mut rest = text.trim_start();
+    for (i, name) in names.iter().enumerate() {
+        if i == names.len() - 1 {
+            vars.set(name, rest.trim_end());
+            break;
+        }
+        let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        vars.set(name, field);
+        rest = tail.trim_start();
+    }
+}
+
+// 内建命令 mapfile/readarray：mapfile [-t] [-n 行数] [-s 跳过行数] [数组名] [文件]
+// 把标准输入（或文件）的每一行依次存入数组，默认数组名为 MAPFILE；-t 去掉行尾的换行，
+// -n 最多读取这么多行（0 表示全部），-s 丢弃开头的若干行
+pub fn run_mapfile(vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    let mut trim = false;
+    let mut count = None;
+    let mut skip = 0;
+    let mut operands = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-t" => trim = true,
+            "-n" | "-s" => {
+                let value = iter
+                    .next()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .ok_or_else(|| ShellError::CommandError(format!("mapfile: {} 需要一个非负整数", arg)))?;
+                if arg == "-n" {
+                    count = Some(value).filter(|&n| n > 0);
+                } else {
+                    skip = value;
+                }
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(ShellError::CommandError(format!("mapfile: 未知的选项 '{}'", flag)));
+            }
+            operand => operands.push(operand),
+        }
+    }
+
+    let (name, path) = match operands.as_slice() {
+        [] => ("MAPFILE", None),
+        [name] => (*name, None),
+        [name, path] => (*name, Some(*path)),
+        _ => {
+            return Err(ShellError::CommandError(
+                "用法: mapfile [-t] [-n 行数] [-s 跳过行数] [数组名] [文件]".to_string(),
+            ))
+        }
+    };
+    if !is_valid_name(name) {
+        return Err(ShellError::CommandError(format!("mapfile: 无效的变量名 '{}'", name)));
+    }
+
+    let mut lines = Vec::new();
+    let mut seen = 0;
+    // 保存一行，返回是否还需要继续读取
+    let mut keep = |line: &[u8]| {
+        seen += 1;
+        if seen > skip {
+            let line = if trim { line.strip_suffix(b"\n").unwrap_or(line) } else { line };
+            lines.push(String::from_utf8_lossy(line).into_owned());
+        }
+        count.is_none_or(|count| lines.len() < count)
+    };
+
+    match path {
+        Some(path) => {
+            let bytes = fs::read(path)
+                .map_err(|e| ShellError::CommandError(format!("mapfile: 无法读取 '{}': {}", path, e)))?;
+            for line in bytes.split_inclusive(|&b| b == b'\n') {
+                if !keep(line) {
+                    break;
+                }
+            }
+        }
+        None => {
+            while let Some(line) = read_stdin_line()? {
+                if !keep(&line) {
+                    break;
+                }
+            }
+        }
+    }
+
+    vars.set_array(name, lines);
+    Ok(())
+}
+
+// 从标准输入无缓冲地读取一行（包括换行符），输入结束时返回 None
+pub fn read_stdin_line() -> Result<Option<Vec<u8>>, ShellError> {
+    let mut line = Vec::new();
+    while let Some(byte) = read_byte()? {
+        line.push(byte);
+        if byte == b'\n' {
+            break;
+        }
+    }
+    Ok(if line.is_empty() { None } else { Some(line) })
+}
+
+// 临时修改终端模式，离开作用域时恢复；标准输入不是终端时什么也不做
+// signals 为 false 时 Ctrl-C 等按键作为普通字节读入，而不是产生信号
+pub(crate) struct TerminalMode {
+    saved: libc::termios,
+}
+
+impl TerminalMode {
+    pub(crate) fn enter(canonical: bool, echo: bool, signals: bool) -> Result<Option<TerminalMode>, ShellError> {
+        if canonical && echo && signals {
+            return Ok(None);
+        }
+
+        // SAFETY: termios 是普通的C结构体，只对标准输入调用 tcgetattr/tcsetattr
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 {
+                return Ok(None);
+            }
+
+            let mut saved: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) < 0 {
+                return Err(ShellError::Io(io::Error::last_os_error()));
+            }
+
+            let mut mode = saved;
+            if !canonical {
+                mode.c_lflag &= !libc::ICANON;
+                mode.c_cc[libc::VMIN] = 1;
+                mode.c_cc[libc::VTIME] = 0;
+            }
+            if !echo {
+                mode.c_lflag &= !libc::ECHO;
+            }
+            if !signals {
+                mode.c_lflag &= !libc::ISIG;
+            }
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode) < 0 {
+                return Err(ShellError::Io(io::Error::last_os_error()));
+            }
+
+            Ok(Some(TerminalMode { saved }))
+        }
+    }
+}
+
+impl Drop for TerminalMode {
+    fn drop(&mut self) {
+        // SAFETY: 恢复 enter 时保存的终端设置
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
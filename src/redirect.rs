@@ -0,0 +1,513 @@
+use crate::capture::VarCapture;
+use crate::error::ShellError;
+use crate::options::ShellOptions;
+use crate::parser::{Redirect, RedirectKind};
+use crate::stdio::Stdio;
+use crate::vars::Variables;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
+
+// 重定向打开的文件和 FdGuard 保存的原描述符使用的最小编号，与 bash 相同，
+// 这样它们不会占用 3> 之类的重定向要设置的描述符
+pub(crate) const SAVED_FD_MIN: RawFd = 10;
+
+// 已打开的重定向：目标描述符和文件，文件为 None 时关闭这个描述符（n>&-）
+pub type OpenRedirect = (RawFd, Option<File>);
+
+// 重定向创建和打开文件的方式
+#[derive(Debug, Clone, Copy)]
+pub struct CreateOptions {
+    // >&$NAME 最多保存的字节数
+    pub capture_limit: usize,
+    // > 和 &> 不覆盖已有的普通文件
+    pub noclobber: bool,
+    // 新建文件的权限，实际的权限还要去掉 umask 中的位
+    pub mode: u32,
+    // 创建不存在的父目录
+    pub mkdirs: bool,
+}
+
+impl CreateOptions {
+    // 来自 set -o noclobber、set -o mkdirs，变量 REDIRECT_MODE（八进制，未设置或无效时为 666）
+    // 和 CAPTURE_LIMIT（字节数，默认 16 MiB）
+    pub fn new(options: &ShellOptions, vars: &Variables) -> Self {
+        let capture_limit = vars
+            .get("CAPTURE_LIMIT")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(16 << 20);
+        let mode = vars
+            .get("REDIRECT_MODE")
+            .and_then(|value| u32::from_str_radix(value, 8).ok())
+            .filter(|&mode| mode <= 0o7777)
+            .unwrap_or(0o666);
+        CreateOptions {
+            capture_limit,
+            noclobber: options.noclobber,
+            mode,
+            mkdirs: options.mkdirs,
+        }
+    }
+}
+
+// 在 files（例如管道的两端）之后按顺序打开命令的重定向
+// 顺序决定含义：> out 2>&1 让标准错误也写入 out，而 2>&1 > out 的标准错误仍是原来的标准输出
+// >&$NAME 开始的捕获加入 captures，命令结束、返回的文件都关闭后用 finish_captures 存入变量
+// 2>&1 等复制的是 files 中的文件，没有时是 stdio 中Shell的描述符
+pub fn open_redirects(
+    mut files: Vec<OpenRedirect>,
+    redirects: &[Redirect],
+    create: &CreateOptions,
+    stdio: &Stdio,
+    captures: &mut Vec<VarCapture>,
+) -> Result<Vec<OpenRedirect>, ShellError> {
+    for redirect in redirects {
+        match redirect.kind {
+            RedirectKind::Capture | RedirectKind::CaptureAppend => {
+                let append = redirect.kind == RedirectKind::CaptureAppend;
+                let (capture, file) = VarCapture::start(&redirect.target.text(), append, create.capture_limit)?;
+                captures.push(capture);
+                files.push((redirect.fd, Some(move_high(file)?)));
+            }
+            RedirectKind::Duplicate | RedirectKind::DuplicateInput => {
+                let file = duplicate(&files, stdio, &redirect.target.text())?;
+                files.push((redirect.fd, file.map(move_high).transpose()?));
+            }
+            // 只打开一次，标准错误使用同一个打开的文件，两者共享写入位置
+            RedirectKind::Combined | RedirectKind::CombinedAppend => {
+                let file = move_high(open_file(redirect, create)?)?;
+                files.push((libc::STDERR_FILENO, Some(move_high(file.try_clone()?)?)));
+                files.push((libc::STDOUT_FILENO, Some(file)));
+            }
+            _ => {
+                let file = open_file(redirect, create)?;
+                files.push((redirect.fd, Some(move_high(file)?)));
+            }
+        }
+    }
+    Ok(files)
+}
+
+// 把打开的文件移到编号不小于 SAVED_FD_MIN 的描述符上，例如 3< 文件 打开的文件本身不会占用描述符 3，
+// 复制到目标描述符时不会与之重合
+fn move_high(file: File) -> io::Result<File> {
+    if file.as_raw_fd() >= SAVED_FD_MIN {
+        return Ok(file);
+    }
+    // SAFETY: F_DUPFD_CLOEXEC 成功时返回一个新的描述符，由返回的 File 独占；原来的描述符随 file 关闭
+    unsafe {
+        let copy = libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, SAVED_FD_MIN);
+        if copy < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(File::from_raw_fd(copy))
+    }
+}
+
+fn open_file(redirect: &Redirect, create: &CreateOptions) -> Result<File, ShellError> {
+    let target = redirect.target.text();
+    if let Some(socket) = open_socket(&target)? {
+        return Ok(socket);
+    }
+    let mut options = OpenOptions::new();
+    if redirect.kind != RedirectKind::Input {
+        options.mode(create.mode);
+        if create.mkdirs
+            && let Some(parent) = Path::new(&target).parent().filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .map_err(|e| ShellError::CommandError(format!("无法创建目录 '{}': {}", parent.display(), e)))?;
+        }
+    }
+    match redirect.kind {
+        RedirectKind::Output | RedirectKind::Combined if create.noclobber => {
+            // 与 bash 相同，/dev/null 等非普通文件仍然可以写入
+            match fs::metadata(&target) {
+                Ok(meta) if meta.is_file() => {
+                    return Err(ShellError::CommandError(format!(
+                        "'{}' 已存在，noclobber 开启时不能覆盖（可以使用 >|）",
+                        target
+                    )))
+                }
+                Ok(_) => options.write(true),
+                Err(_) => options.write(true).create_new(true),
+            }
+        }
+        RedirectKind::Output | RedirectKind::Clobber | RedirectKind::Combined => {
+            options.write(true).create(true).truncate(true)
+        }
+        RedirectKind::Append | RedirectKind::CombinedAppend => options.append(true).create(true),
+        _ => options.read(true),
+    };
+
+    options
+        .open(&target)
+        .map_err(|e| ShellError::CommandError(format!("无法打开 '{}': {}", target, e)))
+}
+
+// 内建命令 umask：umask 显示当前的文件创建掩码，umask -S 用符号形式显示，umask 077 设置新的掩码
+// 掩码对重定向和外部命令创建的文件都有效
+pub fn run_umask(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    // SAFETY: umask 只修改进程的文件创建掩码，读取后立即恢复
+    let current = unsafe {
+        let mask = libc::umask(0);
+        libc::umask(mask);
+        mask
+    };
+    match args {
+        [] => writeln!(out, "{:04o}", current)?,
+        [flag] if flag == "-S" => {
+            let classes: Vec<String> = [("u", 6), ("g", 3), ("o", 0)]
+                .iter()
+                .map(|&(who, shift)| {
+                    let allowed = !current >> shift;
+                    let bits: String = [(4, 'r'), (2, 'w'), (1, 'x')]
+                        .iter()
+                        .filter(|&&(bit, _)| allowed & bit != 0)
+                        .map(|&(_, c)| c)
+                        .collect();
+                    format!("{}={}", who, bits)
+                })
+                .collect();
+            writeln!(out, "{}", classes.join(","))?;
+        }
+        [mode] => {
+            let mask = libc::mode_t::from_str_radix(mode, 8)
+                .ok()
+                .filter(|&mask| mask <= 0o777)
+                .ok_or_else(|| ShellError::CommandError(format!("umask: '{}' 不是有效的八进制掩码", mode)))?;
+            // SAFETY: 同上
+            unsafe { libc::umask(mask) };
+        }
+        _ => return Err(ShellError::CommandError("用法: umask [-S] [掩码]".to_string())),
+    }
+    Ok(())
+}
+
+// 与 bash 相同的伪路径 /dev/tcp/主机/端口 和 /dev/udp/主机/端口：连接到这个地址，
+// 读写都通过这个套接字，例如 echo > /dev/tcp/localhost/8080 检查端口是否打开；其他路径返回 None
+fn open_socket(target: &str) -> Result<Option<File>, ShellError> {
+    let (udp, address) = match (target.strip_prefix("/dev/tcp/"), target.strip_prefix("/dev/udp/")) {
+        (Some(address), _) => (false, address),
+        (_, Some(address)) => (true, address),
+        _ => return Ok(None),
+    };
+    let error = |message: String| ShellError::CommandError(format!("无法连接 '{}': {}", target, message));
+    let (host, port) = address
+        .rsplit_once('/')
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| error("应为 /dev/tcp/主机/端口".to_string()))?;
+    let port: u16 = port.parse().map_err(|_| error(format!("无效的端口 '{}'", port)))?;
+
+    let fd: OwnedFd = if udp {
+        let remote = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| error(e.to_string()))?
+            .next()
+            .ok_or_else(|| error("找不到这个主机".to_string()))?;
+        let local = if remote.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(local).map_err(|e| error(e.to_string()))?;
+        socket.connect(remote).map_err(|e| error(e.to_string()))?;
+        socket.into()
+    } else {
+        TcpStream::connect((host, port)).map_err(|e| error(e.to_string()))?.into()
+    };
+    Ok(Some(File::from(fd)))
+}
+
+// 复制描述符 target 当前指向的文件：先看本命令之前的重定向，否则复制Shell自己的描述符
+// target 为 - 时返回 None，表示关闭
+fn duplicate(files: &[OpenRedirect], stdio: &Stdio, target: &str) -> Result<Option<File>, ShellError> {
+    if target == "-" {
+        return Ok(None);
+    }
+    let fd: RawFd = target
+        .parse()
+        .map_err(|_| ShellError::CommandError(format!("无效的文件描述符 '{}'", target)))?;
+    // 同一描述符有多个重定向时以最后一个为准
+    match files.iter().rev().find(|(target, _)| *target == fd) {
+        Some((_, Some(file))) => return Ok(Some(file.try_clone()?)),
+        Some((_, None)) => {
+            return Err(ShellError::CommandError(format!(
+                "文件描述符 {}: {}",
+                fd,
+                io::Error::from_raw_os_error(libc::EBADF)
+            )))
+        }
+        None => {}
+    }
+
+    // 与其他保存的描述符一样放在 10 以上并设置 close-on-exec，不会泄漏到启动的程序中
+    // SAFETY: F_DUPFD_CLOEXEC 成功时返回一个新的、由返回的 File 独占的描述符
+    unsafe {
+        let copy = libc::fcntl(stdio.fd(fd), libc::F_DUPFD_CLOEXEC, SAVED_FD_MIN);
+        if copy < 0 {
+            return Err(ShellError::CommandError(format!(
+                "文件描述符 {}: {}",
+                fd,
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(Some(File::from_raw_fd(copy)))
+    }
+}
+
+// 外部命令的每个描述符最终指向的文件，同一描述符有多个重定向时以最后一个为准；
+// 简单命令和管道中的每个命令都这样启动，启动程序时把返回的副本依次复制到目标描述符上，None 表示关闭
+// 副本的编号都大于目标描述符，复制到一个目标上时不会覆盖还没有复制的副本
+pub fn redirect_targets(files: &[OpenRedirect]) -> io::Result<Vec<(RawFd, Option<OwnedFd>)>> {
+    let mut targets: Vec<(RawFd, Option<&File>)> = Vec::new();
+    for (fd, file) in files.iter().rev() {
+        if !targets.iter().any(|(target, _)| target == fd) {
+            targets.push((*fd, file.as_ref()));
+        }
+    }
+    let Some(highest) = targets.iter().map(|(fd, _)| *fd).max() else {
+        return Ok(Vec::new());
+    };
+    let mut copies = Vec::new();
+    for (fd, file) in targets {
+        let Some(file) = file else {
+            copies.push((fd, None));
+            continue;
+        };
+        // SAFETY: F_DUPFD_CLOEXEC 成功时返回一个新的描述符，由 OwnedFd 独占
+        let copy = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, highest.max(libc::STDERR_FILENO) + 1) };
+        if copy < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: 同上
+        copies.push((fd, Some(unsafe { OwnedFd::from_raw_fd(copy) })));
+    }
+    Ok(copies)
+}
+
+// 把描述符永久指向重定向的文件，用于子Shell和 exec 等之后不需要恢复的场合
+// 1 和 2 作用在 stdio 中Shell的描述符上；它们是 run_str 换上的描述符时，关闭后记为 -1，
+// 再次重定向时另外复制一个，这些描述符都由换上它们的 Capture 在结束时关闭
+pub fn install_redirects(files: &[OpenRedirect], stdio: &mut Stdio) -> Result<(), ShellError> {
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    for (fd, file) in files {
+        let own = match *fd {
+            libc::STDOUT_FILENO if stdio.out != *fd => Some(&mut stdio.out),
+            libc::STDERR_FILENO if stdio.err != *fd => Some(&mut stdio.err),
+            _ => None,
+        };
+        let target = own.as_ref().map_or(*fd, |own| **own);
+        match file {
+            // SAFETY: 只把已打开文件的描述符复制到目标描述符上，新复制的描述符记录在 stdio 中
+            Some(file) if target < 0 => {
+                let copy = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, SAVED_FD_MIN) };
+                if copy < 0 {
+                    return Err(ShellError::Io(io::Error::last_os_error()));
+                }
+                if let Some(own) = own {
+                    *own = copy;
+                }
+            }
+            // SAFETY: 同上
+            Some(file) => {
+                if unsafe { libc::dup2(file.as_raw_fd(), target) } < 0 {
+                    return Err(ShellError::Io(io::Error::last_os_error()));
+                }
+            }
+            // SAFETY: 关闭 n>&- 指定的描述符，它原来没有打开时不是错误
+            None => {
+                if target >= 0 {
+                    unsafe { libc::close(target) };
+                }
+                if let Some(own) = own {
+                    *own = -1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// 在 fork 出的子进程中安装重定向：先把Shell的标准输出和标准错误复制到进程的 1 和 2 上，
+// 之后子进程中的命令直接使用 1 和 2
+pub fn install_child_redirects(files: &[OpenRedirect], stdio: &mut Stdio) -> Result<(), ShellError> {
+    stdio.install()?;
+    install_redirects(files, stdio)
+}
+
+// exec 在Shell中永久设置的重定向：描述符到重定向的文本，exec 不带参数时列出
+// 外部命令和子Shell继承这些描述符，用 exec n>&- 关闭后移除
+#[derive(Debug, Default)]
+pub struct FdTable {
+    fds: BTreeMap<RawFd, String>,
+}
+
+impl FdTable {
+    // 让 exec 的重定向永久生效，并记录每个描述符现在指向什么
+    pub fn install(&mut self, files: &[OpenRedirect], redirects: &[Redirect], stdio: &mut Stdio) -> Result<(), ShellError> {
+        install_redirects(files, stdio)?;
+        for redirect in redirects {
+            let text = format!("{}{}", redirect.kind.symbol(), redirect.target.text());
+            let fds = match redirect.kind {
+                RedirectKind::Combined | RedirectKind::CombinedAppend => vec![libc::STDOUT_FILENO, libc::STDERR_FILENO],
+                _ => vec![redirect.fd],
+            };
+            for fd in fds {
+                if redirect.kind.is_duplicate() && redirect.target.text() == "-" {
+                    self.fds.remove(&fd);
+                } else {
+                    self.fds.insert(fd, text.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (RawFd, &str)> {
+        self.fds.iter().map(|(fd, text)| (*fd, text.as_str()))
+    }
+}
+
+// 内建命令执行期间把标准描述符指向重定向的文件，离开作用域时恢复
+// 1 和 2 作用在 stdio 中Shell的描述符上，不一定是进程的 1 和 2
+pub struct FdGuard {
+    saved: Vec<(RawFd, RawFd)>,
+}
+
+impl FdGuard {
+    pub fn apply(files: &[OpenRedirect], stdio: &Stdio) -> Result<FdGuard, ShellError> {
+        let mut guard = FdGuard { saved: Vec::new() };
+        if files.is_empty() {
+            return Ok(guard);
+        }
+
+        io::stdout().flush()?;
+        io::stderr().flush()?;
+
+        for (fd, file) in files {
+            let fd = stdio.fd(*fd);
+            // SAFETY: 只复制标准描述符和已打开文件的描述符，保存的副本在 drop 时关闭
+            unsafe {
+                if !guard.saved.iter().any(|(saved_fd, _)| *saved_fd == fd) {
+                    // 原来没有打开的描述符记为 -1，恢复时关闭
+                    // 副本放在 10 以上，避免被同一命令中 3> 之类的重定向覆盖
+                    let saved = libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, SAVED_FD_MIN);
+                    if saved < 0 {
+                        let err = io::Error::last_os_error();
+                        if err.raw_os_error() != Some(libc::EBADF) {
+                            return Err(ShellError::Io(err));
+                        }
+                    }
+                    guard.saved.push((fd, saved));
+                }
+                match file {
+                    Some(file) => {
+                        if libc::dup2(file.as_raw_fd(), fd) < 0 {
+                            return Err(ShellError::Io(io::Error::last_os_error()));
+                        }
+                    }
+                    None => {
+                        libc::close(fd);
+                    }
+                }
+            }
+        }
+
+        Ok(guard)
+    }
+}
+
+impl Drop for FdGuard {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+
+        // SAFETY: saved 中的描述符是 apply 中复制出来的，只在这里恢复并关闭一次
+        unsafe {
+            for (fd, saved) in self.saved.drain(..).rev() {
+                if saved < 0 {
+                    libc::close(fd);
+                } else {
+                    libc::dup2(saved, fd);
+                    libc::close(saved);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::testutil::temp_dir;
+    use std::fs;
+
+
+    #[test]
+    fn order_of_output_and_duplicate() {
+        let dir = temp_dir("redirect-order");
+        let mut shell = Shell::new();
+        let both = shell
+            .run_str(&format!("sh -c 'echo out; echo err >&2' > {}/both 2>&1", dir.display()))
+            .unwrap();
+        let split = shell
+            .run_str(&format!("sh -c 'echo out; echo err >&2' 2>&1 > {}/split", dir.display()))
+            .unwrap();
+        let (both_file, split_file) = (fs::read_to_string(dir.join("both")), fs::read_to_string(dir.join("split")));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(both_file.unwrap(), "out\nerr\n");
+        assert_eq!(both.stderr, "");
+        // 2>&1 在前时标准错误复制的是原来的标准输出
+        assert_eq!(split_file.unwrap(), "out\n");
+        assert_eq!(split.stdout, "err\n");
+    }
+
+    #[test]
+    fn append_and_input() {
+        let dir = temp_dir("redirect-append");
+        let file = dir.join("log");
+        let mut shell = Shell::new();
+        shell.run_str(&format!("echo one > {0}; echo two >> {0}", file.display())).unwrap();
+        let output = shell.run_str(&format!("wc -l < {}", file.display())).unwrap();
+        let text = fs::read_to_string(&file);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(text.unwrap(), "one\ntwo\n");
+        assert_eq!(output.stdout, "2\n");
+    }
+
+    #[test]
+    fn exec_opens_and_closes_descriptor() {
+        let dir = temp_dir("redirect-exec");
+        let file = dir.join("input");
+        fs::write(&file, "from fd 3\n").unwrap();
+        // 在子Shell中执行，exec 不影响测试进程自己的描述符
+        let output = Shell::new()
+            .run_str(&format!("( exec 3< {}; cat <&3; exec 3>&-; cat <&3 )", file.display()))
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(output.stdout, "from fd 3\n");
+        // 关闭之后再复制描述符 3 失败
+        assert_ne!(output.status, 0);
+    }
+
+    #[test]
+    fn redirect_in_middle_of_pipeline() {
+        let dir = temp_dir("redirect-pipeline");
+        let file = dir.join("middle");
+        let output = Shell::new()
+            .run_str(&format!("echo piped | tr a-z A-Z > {} | wc -c", file.display()))
+            .unwrap();
+        let text = fs::read_to_string(&file);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // 中间的命令写到文件，后面的命令从管道读不到任何内容
+        assert_eq!(text.unwrap(), "PIPED\n");
+        assert_eq!(output.stdout, "0\n");
+    }
+}
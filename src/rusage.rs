@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+// 子进程结束时的资源使用情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub user: Duration,
+    pub system: Duration,
+    // 最大常驻内存，单位KB
+    pub max_rss_kb: i64,
+    pub minor_faults: i64,
+    pub major_faults: i64,
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "用户态: {:.3}s  内核态: {:.3}s  最大常驻内存: {} KB  缺页: {} 次(次要) / {} 次(主要)",
+            self.user.as_secs_f64(),
+            self.system.as_secs_f64(),
+            self.max_rss_kb,
+            self.minor_faults,
+            self.major_faults
+        )
+    }
+}
+
+// 通过 wait4 等待子进程，同时取得其资源使用情况
+pub fn wait_with_rusage(pid: libc::pid_t) -> io::Result<(ExitStatus, ResourceUsage)> {
+    let mut status = 0;
+
+    // SAFETY: rusage 是纯数据结构，全零是合法值；pid 属于尚未被回收的子进程
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut usage) };
+        if ret >= 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+
+    let usage = ResourceUsage {
+        user: timeval_to_duration(usage.ru_utime),
+        system: timeval_to_duration(usage.ru_stime),
+        max_rss_kb: usage.ru_maxrss,
+        minor_faults: usage.ru_minflt,
+        major_faults: usage.ru_majflt,
+    };
+    Ok((ExitStatus::from_raw(status), usage))
+}
+
+fn timeval_to_duration(tv: libc::timeval) -> Duration {
+    Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+}
@@ -0,0 +1,176 @@
+use crate::console;
+use crate::error::ShellError;
+use crate::jobs::{fork_background, redirect_to_output, Jobs};
+use crate::parser::{command_text, parse_input};
+use crate::shell::Shell;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+// 一个定时任务：在独立进程组中循环运行的子Shell，每隔 interval 执行一次命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTask {
+    pub id: usize,
+    pub pid: libc::pid_t,
+    // 用户输入的间隔，例如 5m
+    pub every: String,
+    pub command: String,
+}
+
+// 定时任务表，编号从 1 开始
+#[derive(Debug, Default)]
+pub struct Schedules {
+    tasks: Vec<ScheduledTask>,
+}
+
+impl Schedules {
+    // 移除已经自行退出的任务进程
+    fn reap(&mut self) {
+        self.tasks.retain(|task| {
+            let mut status = 0;
+            // SAFETY: 只等待任务表中登记的子进程
+            unsafe { libc::waitpid(task.pid, &mut status, libc::WNOHANG) == 0 }
+        });
+    }
+
+    // 停止一个任务：终止整个进程组（包括正在执行的命令）并回收进程
+    fn cancel(&mut self, id: usize) -> bool {
+        let Some(index) = self.tasks.iter().position(|task| task.id == id) else {
+            return false;
+        };
+        let task = self.tasks.remove(index);
+        // SAFETY: 向任务的进程组发送信号，再等待它退出
+        unsafe {
+            libc::kill(-task.pid, libc::SIGTERM);
+            let mut status = 0;
+            libc::waitpid(task.pid, &mut status, 0);
+        }
+        true
+    }
+
+    // Shell退出时停止全部任务
+    pub fn cancel_all(&mut self) {
+        let ids: Vec<usize> = self.tasks.iter().map(|task| task.id).collect();
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+}
+
+// 内建命令 schedule：
+//   schedule every 间隔 命令...   每隔一段时间在后台执行命令，间隔如 30s、5m、1h、1d
+//   schedule [list]              列出定时任务
+//   schedule rm 编号...          取消定时任务
+pub fn run_schedule(shell: &mut Shell, args: &[String]) -> Result<(), ShellError> {
+    match args.split_first() {
+        None => list(&mut shell.stdio.out(), &mut shell.schedules),
+        Some((sub, [])) if sub == "list" => list(&mut shell.stdio.out(), &mut shell.schedules),
+        Some((sub, ids)) if sub == "rm" && !ids.is_empty() => {
+            for id in ids {
+                let cancelled = id.parse().is_ok_and(|id| shell.schedules.cancel(id));
+                if !cancelled {
+                    return Err(ShellError::CommandError(format!("schedule: 没有编号为 '{}' 的定时任务", id)));
+                }
+            }
+            Ok(())
+        }
+        Some((sub, [every, command @ ..])) if sub == "every" && !command.is_empty() => {
+            let interval = parse_interval(every)?;
+            add(shell, every, interval, command_text(command))
+        }
+        _ => Err(ShellError::CommandError(
+            "用法: schedule every 间隔 命令... | schedule [list] | schedule rm 编号...".to_string(),
+        )),
+    }
+}
+
+fn list(out: &mut impl Write, schedules: &mut Schedules) -> Result<(), ShellError> {
+    schedules.reap();
+    for task in &schedules.tasks {
+        writeln!(out, "[{}] 每 {}  {}  {}", task.id, task.every, task.pid, task.command)?;
+    }
+    Ok(())
+}
+
+// 启动任务进程：先等待一个间隔，然后执行命令，如此循环，直到被取消或Shell退出
+// 命令写到终端的输出经过管道由 console 打印，不会插进正在编辑的行中间
+fn add(shell: &mut Shell, every: &str, interval: Duration, command: String) -> Result<(), ShellError> {
+    // 现在就检查语法，避免在后台反复报同一个错误
+    parse_input(&command, &shell.aliases)?;
+
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    let pipe = if console::writes_to_terminal(&shell.stdio) { Some(io::pipe()?) } else { None };
+    // SAFETY: getpid 总是成功
+    let parent = unsafe { libc::getpid() };
+    let pid = fork_background()?;
+    if pid == 0 {
+        let _ = shell.stdio.install();
+        if let Some((_, writer)) = &pipe {
+            redirect_to_output(writer);
+        }
+        shell.jobs = Jobs::default();
+        shell.schedules = Schedules::default();
+        // SAFETY: 父进程（Shell）退出时本进程收到 SIGTERM；设置之前父进程已经退出则直接结束
+        unsafe {
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+            if libc::getppid() != parent {
+                libc::_exit(0);
+            }
+        }
+        loop {
+            thread::sleep(interval);
+            // 命令中的 exit 结束这个任务
+            if let Err(ShellError::Exit(status)) = shell.run_line(&command) {
+                let _ = io::stdout().flush();
+                let _ = io::stderr().flush();
+                // SAFETY: 任务进程直接退出，缓冲的输出已经写出
+                unsafe { libc::_exit(status) }
+            }
+            // 命令中用 & 启动的作业由任务进程自己回收
+            shell.jobs.reap();
+            let _ = io::stdout().flush();
+            let _ = io::stderr().flush();
+        }
+    }
+
+    if let Some((reader, writer)) = pipe {
+        drop(writer);
+        console::forward(reader, String::new(), None);
+    }
+    shell.schedules.reap();
+    let schedules = &mut shell.schedules;
+    let id = schedules.tasks.iter().map(|task| task.id).max().unwrap_or(0) + 1;
+    writeln!(shell.stdio.err(), "[{}] 每 {}  {}", id, every, command)?;
+    schedules.tasks.push(ScheduledTask {
+        id,
+        pid,
+        every: every.to_string(),
+        command,
+    });
+    Ok(())
+}
+
+// 解析时间间隔：数字加单位 s、m、h、d，不带单位时为秒
+fn parse_interval(text: &str) -> Result<Duration, ShellError> {
+    let invalid = || ShellError::CommandError(format!("schedule: 无效的时间间隔 '{}'（例如 30s、5m、1h）", text));
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => text.split_at(index),
+        None => (text, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let unit: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let seconds = number
+        .checked_mul(unit)
+        .ok_or_else(|| ShellError::CommandError(format!("schedule: 时间间隔太长 '{}'", text)))?;
+    if seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
@@ -0,0 +1,206 @@
+// 控制套接字服务器模式：通过Unix套接字接收命令并返回执行结果
+//
+// 协议：客户端每发送一行命令，服务器在执行期间随时把输出发回，每段标准输出或标准错误是一行JSON，
+// 命令结束后再回复一行它的状态码：
+//   {"stdout":"..."}
+//   {"stderr":"..."}
+//   {"status":0}
+// 命令启动的后台作业在命令结束之后的输出被丢弃
+// 发送 exit 关闭当前连接
+use crate::capture::duplicate;
+use crate::error::ShellError;
+use crate::json::quote;
+use crate::shell::Shell;
+use crate::stdio::Stdio;
+use crate::style;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, PipeReader, PipeWriter, Read, Write};
+use std::os::fd::{AsRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+// 在指定路径监听并依次处理连接，所有连接共享同一个Shell会话
+pub fn serve(shell: &mut Shell, path: &Path) -> Result<(), ShellError> {
+    // 清理上次遗留的套接字文件
+    if let Ok(meta) = fs::symlink_metadata(path)
+        && meta.file_type().is_socket()
+    {
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    println!("正在监听 {}", path.display());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_client(shell, stream) {
+                    eprintln!("{} {}", style::error("连接错误:"), e);
+                }
+            }
+            Err(e) => eprintln!("{} {}", style::error("连接错误:"), e),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_client(shell: &mut Shell, stream: UnixStream) -> Result<(), ShellError> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "exit" {
+            break;
+        }
+
+        let status = run_streaming(shell, line, &writer)?;
+        writer.write_all(format!("{{\"status\":{}}}\n", status).as_bytes())?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+// 执行一行命令，期间Shell的标准输出和标准错误换成两个管道，由另一个线程把读到的内容随时发给客户端；
+// 命令结束后等那个线程发完管道中已有的内容再返回状态码
+fn run_streaming(shell: &mut Shell, line: &str, client: &UnixStream) -> Result<i32, ShellError> {
+    let (out_reader, out_writer) = io::pipe()?;
+    let (err_reader, err_writer) = io::pipe()?;
+    let (done_reader, done_writer) = io::pipe()?;
+    let (drained, wait_drained) = mpsc::channel();
+    let client = client.try_clone()?;
+    thread::spawn(move || forward(client, [out_reader, err_reader], done_reader, drained));
+
+    let saved = shell.stdio;
+    shell.stdio = Stdio {
+        out: move_high(out_writer)?,
+        err: move_high(err_writer)?,
+    };
+    let status = match shell.run_line(line) {
+        Ok(status) | Err(ShellError::Exit(status)) => status,
+        Err(e) => e.status(),
+    };
+    // 与 Capture::finish 一样，关闭的是 stdio 中这时的描述符，exec > 文件 之后也是如此
+    for fd in [shell.stdio.out, shell.stdio.err] {
+        if fd >= 0 {
+            // SAFETY: 上面换上的副本或 exec 另外复制的描述符，只在这里关闭一次
+            unsafe { libc::close(fd) };
+        }
+    }
+    shell.stdio = saved;
+    // 写入一个字节而不是关闭写入端：后台作业的子Shell也持有它的副本
+    (&done_writer).write_all(b"\n")?;
+    let _ = wait_drained.recv();
+    Ok(status)
+}
+
+// 管道的写入端换到 10 以上并设置 close-on-exec，与捕获用的描述符相同
+fn move_high(writer: PipeWriter) -> Result<RawFd, ShellError> {
+    Ok(duplicate(&File::from(OwnedFd::from(writer)))?.into_raw_fd())
+}
+
+// 把两个管道中读到的内容作为 stdout 和 stderr 发给客户端，直到 done 可读（命令结束）；
+// 之后发完管道中已有的内容，通过 drained 通知，再读出并丢弃后台作业的输出，直到它们都关闭写入端
+fn forward(mut client: UnixStream, pipes: [PipeReader; 2], done: PipeReader, drained: Sender<()>) {
+    const NAMES: [&str; 2] = ["stdout", "stderr"];
+    let mut open = [true, true];
+    let mut done = Some(done);
+    let mut drained = Some(drained);
+    // 还不完整的 UTF-8 字符留到下一段
+    let mut pending = [Vec::new(), Vec::new()];
+    let mut buffer = [0; 8192];
+
+    while open.contains(&true) || done.is_some() {
+        let mut fds = [pipes[0].as_raw_fd(), pipes[1].as_raw_fd(), done.as_ref().map_or(-1, |done| done.as_raw_fd())]
+            .map(|fd| libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+        for (fd, open) in fds.iter_mut().zip(open) {
+            if !open {
+                fd.fd = -1;
+            }
+        }
+        // 命令结束后不再等待，没有可读的内容就说明已经发完
+        let timeout = if done.is_none() && drained.is_some() { 0 } else { -1 };
+        // SAFETY: fds 是有效的 pollfd 数组，负数的描述符被忽略
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if ready < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            break;
+        }
+        if ready == 0 {
+            if let Some(drained) = drained.take() {
+                for (name, pending) in NAMES.iter().zip(&mut pending) {
+                    send(&mut client, name, pending, true);
+                }
+                let _ = drained.send(());
+            }
+            continue;
+        }
+        for i in 0..2 {
+            if fds[i].revents == 0 {
+                continue;
+            }
+            match (&pipes[i]).read(&mut buffer) {
+                Ok(0) => open[i] = false,
+                Ok(n) if drained.is_some() => {
+                    pending[i].extend_from_slice(&buffer[..n]);
+                    send(&mut client, NAMES[i], &mut pending[i], false);
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => open[i] = false,
+            }
+        }
+        if fds[2].revents != 0 {
+            done = None;
+        }
+    }
+    if let Some(drained) = drained {
+        let _ = drained.send(());
+    }
+}
+
+// 把 pending 中完整的字符作为一行JSON发给客户端；last 时连同不完整的字符一起发出
+// 客户端已经断开时忽略错误，命令照常执行完
+fn send(client: &mut UnixStream, name: &str, pending: &mut Vec<u8>, last: bool) {
+    let complete = match std::str::from_utf8(pending) {
+        Err(e) if e.error_len().is_none() && !last => e.valid_up_to(),
+        _ => pending.len(),
+    };
+    if complete == 0 {
+        return;
+    }
+    let text = String::from_utf8_lossy(&pending[..complete]).into_owned();
+    pending.drain(..complete);
+    let _ = client.write_all(format!("{{\"{}\":{}}}\n", name, quote(&text)).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_is_sent_before_status() {
+        let (server, client) = UnixStream::pair().unwrap();
+        let mut shell = Shell::new();
+        let status = run_streaming(&mut shell, "echo a; echo b >&2; false", &server).unwrap();
+        drop(server);
+        let lines: Vec<String> = BufReader::new(client).lines().map(Result::unwrap).collect();
+        assert_eq!(status, 1);
+        assert_eq!(lines, [r#"{"stdout":"a\n"}"#, r#"{"stderr":"b\n"}"#]);
+    }
+}
@@ -0,0 +1,372 @@
+use crate::alias::AliasTable;
+use crate::capture::Capture;
+use crate::command::execute_command;
+use crate::completion::SharedRegistry;
+use crate::diagnostic::{self, Diagnostic};
+use crate::error::ShellError;
+use crate::hooks::{HookKind, Hooks};
+use crate::jobs::Jobs;
+use crate::json;
+use crate::mock::Mocks;
+use crate::options::ShellOptions;
+use crate::palette::RecentDirs;
+use crate::pathcache::SharedPathCache;
+use crate::parser::{parse_input, Command, CompoundList};
+use crate::procsubst::ProcessSubstitutions;
+use crate::redirect::FdTable;
+use crate::rusage::ResourceUsage;
+use crate::schedule::Schedules;
+use crate::stdio::Stdio;
+use crate::vars::{EnvSnapshots, Variables};
+use std::fs;
+use std::io::Write;
+use std::mem;
+use std::path::Path;
+#[cfg(feature = "plugins")]
+use crate::plugin::Plugins;
+#[cfg(feature = "wasm-plugins")]
+use crate::wasm_plugin::WasmPlugins;
+
+// run_str 的执行结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Shell会话状态，在各条命令之间保持
+#[derive(Debug, Default)]
+pub struct Shell {
+    pub aliases: AliasTable,
+    pub hooks: Hooks,
+    pub vars: Variables,
+    // env-save 保存的变量快照
+    pub env_snapshots: EnvSnapshots,
+    // pushenv/popenv 使用的环境栈
+    pub env_stack: Vec<Variables>,
+    // 补全规则，与行编辑器共享
+    pub completions: SharedRegistry,
+    // PATH 中可执行文件的索引，与行编辑器共享
+    pub path_cache: SharedPathCache,
+    #[cfg(feature = "plugins")]
+    pub plugins: Plugins,
+    #[cfg(feature = "wasm-plugins")]
+    pub wasm_plugins: WasmPlugins,
+    pub options: ShellOptions,
+    // --dump-ast：只解析命令，输出语法树的JSON表示
+    pub dump_ast: bool,
+    // 上一条命令的状态码
+    pub last_status: i32,
+    // 上一个状态码来自 && / || 列表中最后一个管道之前的管道，errexit 不因它结束执行
+    pub errexit_exempt: bool,
+    // 上一条前台外部命令的资源使用情况
+    pub last_rusage: Option<ResourceUsage>,
+    // trap ... EXIT 设置的退出时命令
+    pub exit_trap: Option<String>,
+    // 位置参数，第一个元素是 $0（Shell或脚本的名字）
+    pub positional: Vec<String>,
+    // 批处理模式中为 Some，记录实际执行的每个命令展开之后的参数
+    pub executed_argv: Option<Vec<Vec<String>>>,
+    // 用 & 启动的后台作业
+    pub jobs: Jobs,
+    // schedule every 启动的定时任务
+    pub schedules: Schedules,
+    // shtest 中用 mock 声明的假命令
+    pub mocks: Mocks,
+    // <(...) 和 >(...) 打开的管道，所在的管道结束后关闭
+    pub process_substitutions: ProcessSubstitutions,
+    // exec 在Shell中永久设置的重定向
+    pub fds: FdTable,
+    // 标准输出和标准错误实际写到的描述符，内建命令的输出写到这里
+    pub stdio: Stdio,
+    // cd 进入过的目录，供命令面板使用
+    pub recent_dirs: RecentDirs,
+    // 正在运行的钩子类型，防止钩子触发自身造成无限递归
+    running_hooks: Vec<HookKind>,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        let shell = Shell {
+            vars: Variables::from_env(),
+            ..Self::default()
+        };
+        shell.refresh_path_cache();
+        shell
+    }
+
+    // 在可能修改了 PATH 的命令之后调用，PATH 改变时丢弃可执行文件的索引
+    pub fn refresh_path_cache(&self) {
+        self.path_cache.borrow_mut().update(self.vars.get("PATH").unwrap_or(""));
+    }
+
+    // 从 ~/.rsh/plugins 加载共享库插件和WASM插件
+    #[cfg(any(feature = "plugins", feature = "wasm-plugins"))]
+    pub fn load_plugins(&mut self) {
+        let dir = match std::env::var("HOME") {
+            Ok(home) => std::path::Path::new(&home).join(".rsh/plugins"),
+            Err(_) => return,
+        };
+
+        #[cfg(feature = "plugins")]
+        self.plugins.load_dir(&dir, &mut self.completions.borrow_mut());
+        #[cfg(feature = "wasm-plugins")]
+        self.wasm_plugins.load_dir(&dir, &mut self.completions.borrow_mut());
+    }
+
+    // 执行一行命令并捕获其标准输出、标准错误和状态码，无需终端
+    // 例如 Shell::new().run_str("ls | wc -l")
+    // 只捕获这个 Shell 的输出，不改变进程的标准输出和标准错误，多个线程中的 Shell 可以同时调用
+    pub fn run_str(&mut self, input: &str) -> Result<Output, ShellError> {
+        let capture = Capture::start(&mut self.stdio)?;
+        let status = match self.run_line(input) {
+            Ok(status) | Err(ShellError::Exit(status)) => status,
+            Err(e) => e.status(),
+        };
+        let (stdout, stderr) = capture.finish(&mut self.stdio)?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+
+    // 解析并执行一行命令，错误打印到标准错误，返回并记录状态码；执行了 exit 时返回 ShellError::Exit，
+    // 调用者应当以其中的状态码结束Shell
+    // set -n 时只解析不执行，没有语法错误时状态码保持不变，因此检查整个脚本后出过错的状态码为 2
+    // --dump-ast 时同样不执行，把语法树输出为一行JSON
+    pub fn run_line(&mut self, line: &str) -> Result<i32, ShellError> {
+        let parsed = parse_input(line, &self.aliases);
+        self.run_parsed(line, parsed)
+    }
+
+    // 执行已经解析的一行，line 是原文，用于报告错误；交互循环先解析以便判断输入是否完整
+    pub fn run_parsed(&mut self, line: &str, parsed: Result<CompoundList, ShellError>) -> Result<i32, ShellError> {
+        if self.options.noexec || self.dump_ast {
+            match parsed {
+                Ok(commands) if self.dump_ast => {
+                    let _ = writeln!(self.stdio.out(), "{}", json::ast(&commands));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    diagnostic::report_parse(&mut self.stdio.err(), &e, line);
+                    self.last_status = 2;
+                }
+            }
+            return Ok(self.last_status);
+        }
+        let status = match parsed {
+            Ok(commands) => match execute_command(self, commands) {
+                Ok(status) => status,
+                Err(ShellError::Exit(status)) => {
+                    self.last_status = status;
+                    return Err(ShellError::Exit(status));
+                }
+                Err(e) => {
+                    diagnostic::report_command(&mut self.stdio.err(), &e, line);
+                    e.status()
+                }
+            },
+            Err(e) => {
+                diagnostic::report_parse(&mut self.stdio.err(), &e, line);
+                2
+            }
+        };
+        self.last_status = status;
+        Ok(status)
+    }
+
+    // 逐行执行文件中的命令（启动文件和脚本），跳过空行和 # 开头的注释行；
+    // 以反斜杠结尾的行与下一行一起执行
+    // 某一行出错不会中断后续的行（开启 errexit 时除外），返回最后一条命令的状态码；
+    // 执行 exit 时停止并返回 ShellError::Exit
+    pub fn source_file(&mut self, path: &Path) -> Result<i32, ShellError> {
+        let text = fs::read_to_string(path)?;
+        self.last_status = 0;
+        let mut pending = String::new();
+        for line in text.lines() {
+            if pending.is_empty() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                pending.push_str(line);
+            } else {
+                pending.push('\n');
+                pending.push_str(line);
+            }
+            // 每一行只解析一次，完整的行直接执行解析的结果
+            let parsed = parse_input(&pending, &self.aliases);
+            if let Err(ShellError::Incomplete(..)) = parsed {
+                continue;
+            }
+            let status = self.run_parsed(&mem::take(&mut pending), parsed)?;
+            if status != 0 && self.options.errexit && !self.errexit_exempt && !self.options.noexec {
+                return Ok(self.last_status);
+            }
+        }
+        // 文件在续行中结束
+        if !pending.is_empty() {
+            self.run_line(&pending)?;
+        }
+        Ok(self.last_status)
+    }
+
+    // Shell退出前的清理：执行 EXIT trap（只执行一次），停止定时任务
+    // EXIT trap 不改变Shell退出时的状态码
+    pub fn shutdown(&mut self) {
+        let status = self.last_status;
+        if let Some(line) = self.exit_trap.take() {
+            let result = parse_input(&line, &self.aliases).and_then(|commands| execute_command(self, commands));
+            if let Err(e) = result {
+                Diagnostic::from_error(&e).command("EXIT trap", &line).emit(&mut self.stdio.err());
+            }
+        }
+        self.last_status = status;
+        self.schedules.cancel_all();
+    }
+
+    // 依次运行某一类型的钩子，命令文本和上一次的状态码作为参数传给钩子
+    // 钩子自身的错误只打印，不影响后续命令，$? 仍是钩子运行之前的状态码
+    pub fn run_hooks(&mut self, kind: HookKind, command_text: &str) {
+        if self.running_hooks.contains(&kind) {
+            return;
+        }
+
+        let lines = self.hooks.get(kind).to_vec();
+        let status = self.last_status;
+        self.running_hooks.push(kind);
+
+        for line in lines {
+            let result = parse_input(&line, &self.aliases).and_then(|mut lists| {
+                // 复合命令没有参数，例如钩子 { a; b; }
+                if let Some(Command::Simple(last)) = lists
+                    .last_mut()
+                    .and_then(|list| list.pipelines.last_mut())
+                    .and_then(|commands| commands.last_mut())
+                {
+                    last.push_arg(command_text);
+                    last.push_arg(&status.to_string());
+                }
+                execute_command(self, lists)
+            });
+            if let Err(e) = result {
+                Diagnostic::from_error(&e).command("钩子", &line).emit(&mut self.stdio.err());
+            }
+        }
+
+        self.running_hooks.pop();
+        self.last_status = status;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+    use std::thread;
+
+    #[test]
+    fn run_str_captures_builtin_output() {
+        let output = Shell::new().run_str("echo hi").unwrap();
+        assert_eq!(output.status, 0);
+        assert_eq!(output.stdout, "hi\n");
+        assert_eq!(output.stderr, "");
+    }
+
+    #[test]
+    fn run_str_reports_status() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.run_str("false").unwrap().status, 1);
+        assert_eq!(shell.run_str("true").unwrap().status, 0);
+        assert_eq!(shell.run_str("true && exit 3").unwrap().status, 3);
+    }
+
+    #[test]
+    fn errexit_ignores_all_but_last_pipeline_of_and_or_list() {
+        let output = Shell::new().run_str("set -e; false && true; echo a; { false && true; }; echo b").unwrap();
+        assert_eq!(output.stdout, "a\nb\n");
+        let output = Shell::new().run_str("set -o errexit; false && true; echo after").unwrap();
+        assert_eq!(output.stdout, "after\n");
+        assert_eq!(output.stderr, "");
+        let output = Shell::new().run_str("set -e; true && false; echo c").unwrap();
+        assert_eq!(output.status, 1);
+        assert_eq!(output.stdout, "");
+        let output = Shell::new().run_str("set -e; false || false; echo d").unwrap();
+        assert_eq!(output.stdout, "");
+    }
+
+    #[test]
+    fn read_at_end_of_input_is_quiet() {
+        let output = Shell::new().run_str("read x < /dev/null").unwrap();
+        assert_eq!(output.status, 1);
+        assert!(!output.stderr.contains("read"), "stderr: {:?}", output.stderr);
+    }
+
+
+    #[test]
+    fn suffix_alias_keeps_redirects() {
+        let dir = temp_dir("shell-suffix-redirect");
+        fs::write(dir.join("note.txt"), "hello\n").unwrap();
+        let input = format!("alias -s txt=cat; {0}/note.txt > {0}/out.txt", dir.display());
+        let output = Shell::new().run_str(&input).unwrap();
+        let out = fs::read_to_string(dir.join("out.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(output.status, 0, "stderr: {:?}", output.stderr);
+        assert_eq!(output.stdout, "");
+        assert_eq!(out.unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn suffix_alias_keeps_assignments() {
+        let dir = temp_dir("shell-suffix-assign");
+        fs::write(dir.join("show.sh"), "echo \"foo=$FOO\"\n").unwrap();
+        let input = format!("alias -s sh=sh; FOO=1 {}/show.sh", dir.display());
+        let output = Shell::new().run_str(&input).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(output.status, 0, "stderr: {:?}", output.stderr);
+        assert_eq!(output.stdout, "foo=1\n");
+    }
+
+    #[test]
+    fn suffix_alias_needs_existing_file() {
+        let dir = temp_dir("shell-suffix-missing");
+        let input = format!("alias -s txt=cat; {}/missing.txt", dir.display());
+        let output = Shell::new().run_str(&input).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(output.status, 127, "stderr: {:?}", output.stderr);
+        assert_eq!(output.stdout, "");
+    }
+
+    #[test]
+    fn run_str_in_parallel_threads() {
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let mut shell = Shell::new();
+                    for _ in 0..50 {
+                        let output = shell.run_str(&format!("echo thread-{0}; sh -c 'echo sh-{0} >&2'", i)).unwrap();
+                        assert_eq!(output.stdout, format!("thread-{}\n", i));
+                        assert_eq!(output.stderr, format!("sh-{}\n", i));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn exec_redirect_ends_with_run_str() {
+        let mut shell = Shell::new();
+        let output = shell.run_str("exec > /dev/null; echo gone; echo err >&2").unwrap();
+        assert_eq!(output.stdout, "");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(shell.run_str("echo back").unwrap().stdout, "back\n");
+    }
+}
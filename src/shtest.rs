@@ -0,0 +1,153 @@
+use crate::error::ShellError;
+use crate::mock::Mocks;
+use crate::shell::Shell;
+use std::fs;
+use std::io::Write;
+
+// 一个测试用例
+#[derive(Debug, Default)]
+struct TestCase {
+    name: String,
+    commands: Vec<String>,
+    // 期望的标准输出，None 表示不检查输出
+    output: Option<Vec<String>>,
+    status: i32,
+}
+
+// 一个测试文件：第一个 @test 之前的命令是准备步骤，之后是各个测试用例
+#[derive(Debug, Default)]
+struct TestFile {
+    path: String,
+    setup: Vec<String>,
+    cases: Vec<TestCase>,
+}
+
+// 内建命令 shtest：shtest 文件...
+// 在新的Shell中运行文件里的测试用例，按 TAP 格式报告结果，有失败时返回非零状态。文件格式：
+//   @test 名字     开始一个测试用例
+//   $ 命令         要执行的命令，可以有多行，状态码取最后一行的
+//   > 文本         期望的一行标准输出；没有 > 行时不检查输出
+//   ? 状态码       期望的状态码，默认为 0
+// 空行和 # 开头的行被忽略；同一个文件中的用例共用一个Shell，按顺序执行，
+// 其中可以用 mock 声明假命令，使测试不依赖真正的程序
+pub fn run_shtest(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        return Err(ShellError::CommandError("用法: shtest 文件...".to_string()));
+    }
+
+    let files = args.iter().map(|path| parse_file(path)).collect::<Result<Vec<_>, _>>()?;
+    let total: usize = files.iter().map(|file| file.cases.len()).sum();
+
+    writeln!(out, "TAP version 13")?;
+    writeln!(out, "1..{}", total)?;
+    let mut number = 0;
+    let mut failed = 0;
+    for file in &files {
+        let mut shell = Shell::new();
+        shell.mocks = Mocks::enabled();
+        for line in &file.setup {
+            shell.run_str(line)?;
+        }
+        for case in &file.cases {
+            number += 1;
+            let problems = run_case(&mut shell, case)?;
+            if problems.is_empty() {
+                writeln!(out, "ok {} - {}", number, case.name)?;
+            } else {
+                failed += 1;
+                writeln!(out, "not ok {} - {}", number, case.name)?;
+                for line in problems {
+                    writeln!(out, "#   {}", line)?;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(ShellError::CommandError(format!(
+            "shtest: {} 个测试中有 {} 个失败",
+            total, failed
+        )));
+    }
+    Ok(())
+}
+
+// 执行一个用例，返回不符合期望的说明，全部符合时为空
+fn run_case(shell: &mut Shell, case: &TestCase) -> Result<Vec<String>, ShellError> {
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut status = 0;
+    for command in &case.commands {
+        let output = shell.run_str(command)?;
+        stdout.push_str(&output.stdout);
+        stderr.push_str(&output.stderr);
+        status = output.status;
+    }
+
+    let mut problems = Vec::new();
+    if status != case.status {
+        problems.push(format!("期望状态码 {}，实际为 {}", case.status, status));
+    }
+    if let Some(expected) = &case.output {
+        let actual: Vec<&str> = stdout.lines().collect();
+        if actual != *expected {
+            problems.push("期望输出:".to_string());
+            problems.extend(expected.iter().map(|line| format!("  {}", line)));
+            problems.push("实际输出:".to_string());
+            problems.extend(actual.iter().map(|line| format!("  {}", line)));
+        }
+    }
+    if !problems.is_empty() && !stderr.is_empty() {
+        problems.push("标准错误:".to_string());
+        problems.extend(stderr.lines().map(|line| format!("  {}", line)));
+    }
+    Ok(problems)
+}
+
+fn parse_file(path: &str) -> Result<TestFile, ShellError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| ShellError::CommandError(format!("shtest: 无法读取 '{}': {}", path, e)))?;
+    let mut file = TestFile {
+        path: path.to_string(),
+        ..TestFile::default()
+    };
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: &str| ShellError::CommandError(format!("shtest: {}:{}: {}", file.path, index + 1, message));
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = trimmed.strip_prefix("@test") {
+            file.cases.push(TestCase {
+                name: name.trim().to_string(),
+                ..TestCase::default()
+            });
+        } else if let Some(command) = trimmed.strip_prefix('$') {
+            let command = command.trim().to_string();
+            match file.cases.last_mut() {
+                Some(case) => case.commands.push(command),
+                None => file.setup.push(command),
+            }
+        } else if let Some(expected) = trimmed.strip_prefix('>') {
+            let case = file.cases.last_mut().ok_or_else(|| error("'>' 必须在 @test 之后"))?;
+            // > 之后的一个空格是分隔符，其余的空白属于期望输出
+            let expected = expected.strip_prefix(' ').unwrap_or(expected);
+            case.output.get_or_insert_with(Vec::new).push(expected.to_string());
+        } else if let Some(status) = trimmed.strip_prefix('?') {
+            let case = file.cases.last_mut().ok_or_else(|| error("'?' 必须在 @test 之后"))?;
+            case.status = status.trim().parse().map_err(|_| error("无效的状态码"))?;
+        } else {
+            return Err(error(&format!("无法识别的行 '{}'", line)));
+        }
+    }
+
+    if let Some(case) = file.cases.iter().find(|case| case.commands.is_empty()) {
+        return Err(ShellError::CommandError(format!(
+            "shtest: {}: 测试 '{}' 没有命令",
+            file.path, case.name
+        )));
+    }
+    Ok(file)
+}
@@ -0,0 +1,175 @@
+use crate::console;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// 收到 SIGHUP（终端断开）后置位
+static HANGUP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_hangup(_signal: libc::c_int) {
+    HANGUP.store(true, Ordering::SeqCst);
+}
+
+// 安装 SIGHUP 处理函数，取代默认的直接终止进程
+// 不设置 SA_RESTART，阻塞中的读取会被打断，主循环得以有序退出
+pub fn install_hangup_handler() -> io::Result<()> {
+    // SAFETY: 处理函数只写入一个原子变量，是异步信号安全的
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGHUP, &action, std::ptr::null_mut()) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// 收到 SIGWINCH（终端大小改变）后置位，由 take_resized 取出
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+// 安装之前的处理函数（行编辑器的），收到信号时先调用它
+static PREVIOUS_RESIZE: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+
+extern "C" fn on_resize(signal: libc::c_int) {
+    RESIZED.store(true, Ordering::SeqCst);
+    let previous = PREVIOUS_RESIZE.load(Ordering::SeqCst);
+    if previous != libc::SIG_DFL && previous != libc::SIG_IGN {
+        // SAFETY: previous 是 sigaction 返回的一个不带 SA_SIGINFO 的处理函数
+        let handler: extern "C" fn(libc::c_int) = unsafe { std::mem::transmute(previous) };
+        handler(signal);
+    }
+}
+
+// 安装 SIGWINCH 处理函数，命令面板等行编辑器之外的界面据此按新的宽度重画
+// 行编辑器在创建时安装自己的处理函数且不再恢复，因此要在创建行编辑器之后调用，由这里转交给它
+// 设置 SA_RESTART，不打断其他读取；poll 不受它影响，仍会被打断
+pub fn install_resize_handler() -> io::Result<()> {
+    // SAFETY: 处理函数只写入原子变量并调用原来的处理函数，是异步信号安全的
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_resize as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGWINCH, std::ptr::null(), &mut previous) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if previous.sa_flags & libc::SA_SIGINFO == 0 {
+            PREVIOUS_RESIZE.store(previous.sa_sigaction, Ordering::SeqCst);
+        }
+        if libc::sigaction(libc::SIGWINCH, &action, std::ptr::null_mut()) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// 上次调用以来终端大小是否改变过
+pub fn take_resized() -> bool {
+    RESIZED.swap(false, Ordering::SeqCst)
+}
+
+// 在 InterruptGuard 存在期间收到 SIGINT（Ctrl-C）后置位
+static INTERRUPT: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPT.store(true, Ordering::SeqCst);
+}
+
+// 长时间运行的内建命令（如 onchange）用来捕获 Ctrl-C，离开作用域时恢复原来的处理方式
+// 前台子进程与Shell在同一个进程组，仍会收到 SIGINT 并按默认方式终止
+pub struct InterruptGuard {
+    previous: libc::sigaction,
+}
+
+impl InterruptGuard {
+    pub fn install() -> io::Result<Self> {
+        INTERRUPT.store(false, Ordering::SeqCst);
+        // SAFETY: 处理函数只写入一个原子变量，是异步信号安全的
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(libc::SIGINT, &action, &mut previous) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(InterruptGuard { previous })
+        }
+    }
+
+    // 安装以来是否收到过 SIGINT
+    pub fn interrupted(&self) -> bool {
+        INTERRUPT.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // SAFETY: previous 是安装时取得的原处理方式
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+    }
+}
+
+// 是否已经收到 SIGHUP
+pub fn hangup_received() -> bool {
+    HANGUP.load(Ordering::SeqCst)
+}
+
+// 创建子进程运行Shell自身的代码（子Shell、后台作业），子进程中恢复默认的 SIGHUP 和 SIGINT 处理；
+// SIGPIPE 也恢复默认，向已关闭的管道写入时像普通程序一样安静地结束，例如 head -1 <(...)
+// 返回值与 fork 相同，子进程中为 0
+pub fn fork_child() -> io::Result<libc::pid_t> {
+    // 子进程只有调用 fork 的线程，其他线程（console::forward、作业的等待线程、捕获变量的读取线程）
+    // 持有的锁在子进程中永远不会被释放；这些线程只会持有编辑状态、标准输出和标准错误的锁，
+    // 以及内存分配器的锁（glibc 在 fork 时处理），所以 fork 期间由本线程持有前三个锁，
+    // 父子进程中都在 fork 之后释放
+    let _output = console::lock_output();
+    // SAFETY: fork 时没有其他线程持有子进程会用到的锁，子进程只继续执行本进程的代码，最后用 _exit 退出
+    unsafe {
+        let pid = libc::fork();
+        if pid < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if pid == 0 {
+            libc::signal(libc::SIGHUP, libc::SIG_DFL);
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+        Ok(pid)
+    }
+}
+
+// 常见信号的名字，其他信号显示编号
+pub fn signal_name(signal: libc::c_int) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGSYS => "SIGSYS",
+        _ => return format!("信号 {}", signal),
+    };
+    name.to_string()
+}
+
+// 进程被信号终止时的描述，例如“被 SIGSEGV 终止（已转储核心）”
+pub fn describe_signal(signal: libc::c_int, core_dumped: bool) -> String {
+    let core = if core_dumped { "（已转储核心）" } else { "" };
+    format!("被 {} 终止{}", signal_name(signal), core)
+}
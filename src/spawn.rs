@@ -0,0 +1,190 @@
+use crate::redirect::{redirect_targets, OpenRedirect};
+use crate::stdio::Stdio;
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+// 用 posix_spawn 启动 path 处的程序，argv[0] 是 name，环境变量是 environ，不等待
+// 与 std::process::Command 相比不需要再复制一遍参数和环境变量，也不需要为了查找 PATH 或 pre_exec
+// 退回 fork + exec；子进程的 1 和 2 先指向 stdio 中Shell的描述符，
+// files 中的重定向再由 posix_spawn 在子进程中复制到目标描述符上
+pub fn spawn(
+    path: &Path,
+    name: &str,
+    args: &[String],
+    environ: &[CString],
+    stdio: &Stdio,
+    files: &[OpenRedirect],
+) -> io::Result<libc::pid_t> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| nul_error())?;
+    let argv = Argv::new(name, args)?;
+    let envp = envp(environ);
+
+    let targets = redirect_targets(files)?;
+    let mut actions = FileActions::new()?;
+    for (from, to) in stdio.targets() {
+        // SAFETY: actions 已初始化，from 是Shell持有的描述符；-1 表示Shell的这个描述符已经关闭
+        check(unsafe {
+            if from < 0 {
+                libc::posix_spawn_file_actions_addclose(&mut actions.0, to)
+            } else {
+                libc::posix_spawn_file_actions_adddup2(&mut actions.0, from, to)
+            }
+        })?;
+    }
+    for (fd, file) in &targets {
+        // SAFETY: actions 已初始化，file 在 posix_spawn 返回之前一直打开
+        check(unsafe {
+            match file {
+                Some(file) => libc::posix_spawn_file_actions_adddup2(&mut actions.0, file.as_raw_fd(), *fd),
+                None => libc::posix_spawn_file_actions_addclose(&mut actions.0, *fd),
+            }
+        })?;
+    }
+    let attributes = Attributes::new()?;
+
+    let mut pid = 0;
+    // SAFETY: 所有指针在调用期间有效，argv 与 envp 以空指针结尾
+    check(unsafe {
+        libc::posix_spawn(
+            &mut pid,
+            path.as_ptr(),
+            &actions.0,
+            &attributes.0,
+            argv.pointers.as_ptr(),
+            envp.as_ptr(),
+        )
+    })?;
+    Ok(pid)
+}
+
+// 用 path 处的程序取代当前进程（exec 命令），只在失败时返回错误
+// 与 spawn 启动的程序一样，SIGPIPE 恢复默认处理
+pub fn exec(path: &Path, name: &str, args: &[String], environ: &[CString]) -> io::Error {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return nul_error();
+    };
+    let argv = match Argv::new(name, args) {
+        Ok(argv) => argv,
+        Err(e) => return e,
+    };
+    let envp = envp(environ);
+    // SAFETY: 指针在调用期间有效，argv 与 envp 以空指针结尾；失败时恢复Shell对 SIGPIPE 的处理
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        libc::execve(path.as_ptr(), argv.pointers.as_ptr().cast(), envp.as_ptr().cast());
+        let error = io::Error::last_os_error();
+        libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+        error
+    }
+}
+
+// 以空指针结尾的环境变量指针数组，指向 environ 中的字符串
+fn envp(environ: &[CString]) -> Vec<*mut libc::c_char> {
+    let mut envp: Vec<*mut libc::c_char> = environ.iter().map(|s| s.as_ptr() as *mut _).collect();
+    envp.push(ptr::null_mut());
+    envp
+}
+
+// 参数都放在同一块缓冲区里，每个参数以空字符结尾，不为每个参数单独分配 CString
+struct Argv {
+    // 持有 pointers 指向的内容
+    _buffer: Vec<u8>,
+    pointers: Vec<*mut libc::c_char>,
+}
+
+impl Argv {
+    fn new(name: &str, args: &[String]) -> io::Result<Self> {
+        let words = || std::iter::once(name).chain(args.iter().map(String::as_str));
+        let mut buffer = Vec::with_capacity(words().map(|word| word.len() + 1).sum());
+        let mut offsets = Vec::with_capacity(args.len() + 1);
+        for word in words() {
+            if word.contains('\0') {
+                return Err(nul_error());
+            }
+            offsets.push(buffer.len());
+            buffer.extend_from_slice(word.as_bytes());
+            buffer.push(0);
+        }
+        // 缓冲区不再增长，指针在它被释放之前一直有效
+        let base = buffer.as_mut_ptr();
+        let mut pointers: Vec<*mut libc::c_char> = offsets
+            .into_iter()
+            // SAFETY: 偏移量都在缓冲区之内
+            .map(|offset| unsafe { base.add(offset) } as *mut libc::c_char)
+            .collect();
+        pointers.push(ptr::null_mut());
+        Ok(Argv {
+            _buffer: buffer,
+            pointers,
+        })
+    }
+}
+
+// posix_spawn_file_actions_t，离开作用域时销毁
+struct FileActions(libc::posix_spawn_file_actions_t);
+
+impl FileActions {
+    fn new() -> io::Result<Self> {
+        let mut actions = MaybeUninit::uninit();
+        // SAFETY: init 成功后 actions 已初始化
+        check(unsafe { libc::posix_spawn_file_actions_init(actions.as_mut_ptr()) })?;
+        Ok(FileActions(unsafe { actions.assume_init() }))
+    }
+}
+
+impl Drop for FileActions {
+    fn drop(&mut self) {
+        // SAFETY: 由 new 初始化，只销毁一次
+        unsafe { libc::posix_spawn_file_actions_destroy(&mut self.0) };
+    }
+}
+
+// posix_spawnattr_t：子进程的信号掩码清空，SIGPIPE 恢复默认处理（Shell自己忽略 SIGPIPE），
+// 与 std::process::Command 一样；其他被忽略的信号保持忽略，例如 task 启动的命令忽略 SIGHUP
+struct Attributes(libc::posix_spawnattr_t);
+
+impl Attributes {
+    fn new() -> io::Result<Self> {
+        let mut attributes = MaybeUninit::uninit();
+        // SAFETY: init 成功后 attributes 已初始化
+        check(unsafe { libc::posix_spawnattr_init(attributes.as_mut_ptr()) })?;
+        let mut attributes = Attributes(unsafe { attributes.assume_init() });
+        // SAFETY: 信号集在使用前由 sigemptyset 初始化
+        unsafe {
+            let mut empty = MaybeUninit::uninit();
+            libc::sigemptyset(empty.as_mut_ptr());
+            let empty = empty.assume_init();
+            let mut default = empty;
+            libc::sigaddset(&mut default, libc::SIGPIPE);
+            check(libc::posix_spawnattr_setsigmask(&mut attributes.0, &empty))?;
+            check(libc::posix_spawnattr_setsigdefault(&mut attributes.0, &default))?;
+            let flags = libc::POSIX_SPAWN_SETSIGMASK | libc::POSIX_SPAWN_SETSIGDEF;
+            check(libc::posix_spawnattr_setflags(&mut attributes.0, flags as libc::c_short))?;
+        }
+        Ok(attributes)
+    }
+}
+
+impl Drop for Attributes {
+    fn drop(&mut self) {
+        // SAFETY: 由 new 初始化，只销毁一次
+        unsafe { libc::posix_spawnattr_destroy(&mut self.0) };
+    }
+}
+
+// posix_spawn 系列函数直接返回错误码而不是设置 errno
+fn check(result: libc::c_int) -> io::Result<()> {
+    match result {
+        0 => Ok(()),
+        code => Err(io::Error::from_raw_os_error(code)),
+    }
+}
+
+fn nul_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "参数中含有空字符")
+}
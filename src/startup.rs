@@ -0,0 +1,51 @@
+use std::time::{Duration, Instant};
+
+// 启动各阶段的耗时记录，--profile-startup 时打印出来
+#[derive(Debug)]
+pub struct StartupProfile {
+    start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl StartupProfile {
+    pub fn new() -> Self {
+        StartupProfile {
+            start: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    // 执行一个启动阶段并记录耗时
+    pub fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+
+    // 把各阶段耗时和总耗时打印到标准错误
+    pub fn report(&self) {
+        let total = self.start.elapsed();
+        let measured: Duration = self.phases.iter().map(|(_, d)| *d).sum();
+
+        eprintln!("启动耗时:");
+        for (phase, duration) in &self.phases {
+            print_phase(phase, *duration);
+        }
+        print_phase("其他", total.saturating_sub(measured));
+        print_phase("合计", total);
+    }
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 按终端显示宽度对齐，中文字符占两列
+fn print_phase(phase: &str, duration: Duration) {
+    let width: usize = phase.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+    let padding = " ".repeat(12usize.saturating_sub(width));
+    eprintln!("  {}{} {:>9.3}ms", phase, padding, duration.as_secs_f64() * 1000.0);
+}
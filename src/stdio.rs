@@ -0,0 +1,102 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::os::unix::io::RawFd;
+
+// Shell的标准输出和标准错误实际写到的描述符。交互时就是进程的 1 和 2；
+// run_str 执行期间换成捕获用的文件，进程的 1 和 2 不变，同一进程中的多个 Shell 互不影响
+// 内建命令通过 out 和 err 写入，外部命令和子Shell启动时把它们复制到子进程的 1 和 2 上；
+// 重定向 > 文件 等作用在这里的描述符上，而不是进程的 1 和 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stdio {
+    pub out: RawFd,
+    pub err: RawFd,
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Stdio {
+            out: libc::STDOUT_FILENO,
+            err: libc::STDERR_FILENO,
+        }
+    }
+}
+
+impl Stdio {
+    // 命令中的描述符 fd 实际对应的描述符：1 和 2 是 out 和 err，其他描述符就是进程自己的
+    pub fn fd(&self, fd: RawFd) -> RawFd {
+        match fd {
+            libc::STDOUT_FILENO => self.out,
+            libc::STDERR_FILENO => self.err,
+            fd => fd,
+        }
+    }
+
+    pub fn out(&self) -> FdWriter {
+        FdWriter(self.out)
+    }
+
+    pub fn err(&self) -> FdWriter {
+        FdWriter(self.err)
+    }
+
+    // 启动子进程时要复制到 1 和 2 上的描述符，与进程自己的相同时省略
+    pub fn targets(&self) -> impl Iterator<Item = (RawFd, RawFd)> {
+        [(self.out, libc::STDOUT_FILENO), (self.err, libc::STDERR_FILENO)]
+            .into_iter()
+            .filter(|(from, to)| from != to)
+    }
+
+    // 在 fork 出的子进程中把 out 和 err 复制到进程的 1 和 2 上，之后子进程直接使用 1 和 2
+    // out 和 err 不是默认值时都是 10 以上的描述符（或者表示已经关闭的 -1），复制时不会覆盖还没有复制的那个
+    pub fn install(&mut self) -> io::Result<()> {
+        for (from, to) in self.targets() {
+            // SAFETY: 只修改子进程自己的标准描述符；-1 表示Shell的这个描述符已经关闭
+            unsafe {
+                if from < 0 {
+                    libc::close(to);
+                } else if libc::dup2(from, to) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        *self = Stdio::default();
+        Ok(())
+    }
+}
+
+// 直接写入描述符，不经过标准输出的缓冲，也不会被测试框架等截获 print! 的程序截获；不拥有描述符
+pub struct FdWriter(RawFd);
+
+impl FdWriter {
+    pub fn fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Write for FdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            // SAFETY: 只从 buf 中读取 buf.len() 个字节
+            let written = unsafe { libc::write(self.0, buf.as_ptr().cast(), buf.len()) };
+            if written >= 0 {
+                return Ok(written as usize);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    // 先格式化完再一次写入，一行输出不会被其他进程的输出从中间打断
+    fn write_fmt(&mut self, args: fmt::Arguments) -> io::Result<()> {
+        match args.as_str() {
+            Some(text) => self.write_all(text.as_bytes()),
+            None => self.write_all(fmt::format(args).as_bytes()),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::error::ShellError;
+use crate::read::read_stdin_line;
+use std::io::{self, Write};
+
+const USAGE: &str = "用法: str upper|lower|trim|len [文本...]
+       str replace <原文> <替换> [文本...]
+       str split <分隔符> [文本...]
+       str join <分隔符> [文本...]";
+
+// 内建命令 str：简单的字符串处理，没有给出文本时逐行处理标准输入
+//   str upper/lower/trim/len   每个文本输出一行结果
+//   str replace 原文 替换      替换全部出现的原文
+//   str split 分隔符           拆分后每段输出一行
+//   str join 分隔符            把全部文本连接成一行
+pub fn run_string(out: &mut impl Write, args: &[String]) -> Result<(), ShellError> {
+    let (sub, rest) = args
+        .split_first()
+        .ok_or_else(|| ShellError::CommandError(USAGE.to_string()))?;
+
+    match sub.as_str() {
+        "upper" => each_line(rest, |s| writeln!(out, "{}", s.to_uppercase())),
+        "lower" => each_line(rest, |s| writeln!(out, "{}", s.to_lowercase())),
+        "trim" => each_line(rest, |s| writeln!(out, "{}", s.trim())),
+        "len" => each_line(rest, |s| writeln!(out, "{}", s.chars().count())),
+        "replace" => match rest {
+            [from, to, texts @ ..] => {
+                if from.is_empty() {
+                    return Err(ShellError::CommandError("str replace: 原文不能为空".to_string()));
+                }
+                each_line(texts, |s| writeln!(out, "{}", s.replace(from.as_str(), to)))
+            }
+            _ => Err(ShellError::CommandError(USAGE.to_string())),
+        },
+        "split" => match rest {
+            [sep, texts @ ..] => each_line(texts, |s| {
+                // 空分隔符按空白拆分
+                if sep.is_empty() {
+                    s.split_whitespace().try_for_each(|piece| writeln!(out, "{}", piece))
+                } else {
+                    s.split(sep.as_str()).try_for_each(|piece| writeln!(out, "{}", piece))
+                }
+            }),
+            _ => Err(ShellError::CommandError(USAGE.to_string())),
+        },
+        "join" => match rest {
+            [sep, texts @ ..] => {
+                let mut pieces = Vec::new();
+                each_line(texts, |s| {
+                    pieces.push(s.to_string());
+                    Ok(())
+                })?;
+                writeln!(out, "{}", pieces.join(sep))?;
+                Ok(())
+            }
+            _ => Err(ShellError::CommandError(USAGE.to_string())),
+        },
+        _ => Err(ShellError::CommandError(format!("str: 未知的子命令 '{}'\n{}", sub, USAGE))),
+    }
+}
+
+// 依次处理每个参数；没有参数时处理标准输入的每一行（不含换行符）
+fn each_line(texts: &[String], mut f: impl FnMut(&str) -> io::Result<()>) -> Result<(), ShellError> {
+    if !texts.is_empty() {
+        texts.iter().try_for_each(|s| f(s))?;
+        return Ok(());
+    }
+
+    while let Some(line) = read_stdin_line()? {
+        let line = String::from_utf8_lossy(&line);
+        f(line.strip_suffix('\n').unwrap_or(&line))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// 终端支持的颜色，从少到多排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    None,
+    // 16 色
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+// 输出的去向，只有连接到终端时才加颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    // Shell的标准输出或标准错误实际写到的描述符，见 Stdio
+    Fd(RawFd),
+}
+
+// 输出中的角色，各自的样式集中在 role_style 中定义
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    // 错误信息的标签
+    Error,
+    // 警告信息的标签
+    Warning,
+    // 错误信息后的提示
+    Hint,
+    // 提示符中的 用户@主机
+    Prompt,
+    // 提示符中的当前目录
+    Path,
+    // 补全列表中的目录
+    Directory,
+    // 命令面板中选中的条目
+    Selected,
+}
+
+struct Style {
+    // 真彩色，256 色时换算为最接近的颜色
+    rgb: Option<(u8, u8, u8)>,
+    // 16 色终端上的前景色代码（30-37、90-97），0 表示不设置
+    basic: u8,
+    bold: bool,
+    reverse: bool,
+}
+
+fn role_style(role: Role) -> Style {
+    match role {
+        Role::Error => Style { rgb: Some((0xff, 0x55, 0x55)), basic: 91, bold: true, reverse: false },
+        Role::Warning => Style { rgb: Some((0xf1, 0xc4, 0x0f)), basic: 93, bold: true, reverse: false },
+        Role::Hint => Style { rgb: Some((0x56, 0xb6, 0xc2)), basic: 96, bold: false, reverse: false },
+        Role::Prompt => Style { rgb: Some((0x50, 0xc8, 0x78)), basic: 92, bold: true, reverse: false },
+        Role::Path => Style { rgb: Some((0x61, 0xaf, 0xef)), basic: 94, bold: true, reverse: false },
+        Role::Directory => Style { rgb: Some((0x61, 0xaf, 0xef)), basic: 94, bold: false, reverse: false },
+        Role::Selected => Style { rgb: None, basic: 0, bold: false, reverse: true },
+    }
+}
+
+// 终端的颜色能力，启动后第一次使用时按环境变量检测：
+// NO_COLOR 非空时不用颜色，COLORTERM 为 truecolor/24bit 时用真彩色，
+// 否则按 TERM 的 terminfo 中的颜色数，找不到 terminfo 时按 TERM 的名字猜测
+pub fn color_level() -> ColorLevel {
+    static LEVEL: OnceLock<ColorLevel> = OnceLock::new();
+    *LEVEL.get_or_init(detect)
+}
+
+fn detect() -> ColorLevel {
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return ColorLevel::None;
+    }
+    if matches!(env::var("COLORTERM").as_deref(), Ok("truecolor") | Ok("24bit")) {
+        return ColorLevel::TrueColor;
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    if term.is_empty() || term == "dumb" {
+        return ColorLevel::None;
+    }
+    match terminfo_colors(&term) {
+        Some(colors) if colors >= 1 << 24 => ColorLevel::TrueColor,
+        Some(colors) if colors >= 256 => ColorLevel::Ansi256,
+        Some(colors) if colors >= 8 => ColorLevel::Basic,
+        Some(_) => ColorLevel::None,
+        None if term.contains("256color") => ColorLevel::Ansi256,
+        None => ColorLevel::Basic,
+    }
+}
+
+// 按角色给文本加上颜色；输出不是终端或终端不支持颜色时原样返回
+pub fn paint(text: &str, role: Role, stream: Stream) -> Cow<'_, str> {
+    let fd = match stream {
+        Stream::Stdout => libc::STDOUT_FILENO,
+        Stream::Stderr => libc::STDERR_FILENO,
+        Stream::Fd(fd) => fd,
+    };
+    // SAFETY: isatty 只查询描述符
+    let level = if unsafe { libc::isatty(fd) } != 0 { color_level() } else { ColorLevel::None };
+    if level == ColorLevel::None || text.is_empty() {
+        return Cow::Borrowed(text);
+    }
+
+    let style = role_style(role);
+    let mut codes = Vec::new();
+    if style.bold {
+        codes.push("1".to_string());
+    }
+    if style.reverse {
+        codes.push("7".to_string());
+    }
+    match (level, style.rgb) {
+        (ColorLevel::TrueColor, Some((r, g, b))) => codes.push(format!("38;2;{};{};{}", r, g, b)),
+        (ColorLevel::Ansi256, Some(rgb)) => codes.push(format!("38;5;{}", to_ansi256(rgb))),
+        _ if style.basic != 0 => codes.push(style.basic.to_string()),
+        _ => {}
+    }
+    Cow::Owned(format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text))
+}
+
+// 错误信息的标签，例如 "错误:"，输出到标准错误
+pub fn error(label: &str) -> Cow<'_, str> {
+    paint(label, Role::Error, Stream::Stderr)
+}
+
+// 给 用户@主机:目录 $ 形式的提示符上色
+pub fn prompt(prompt: &str) -> Cow<'_, str> {
+    let Some((who, rest)) = prompt.split_once(':') else {
+        return Cow::Borrowed(prompt);
+    };
+    let (dir, tail) = match rest.rfind(' ') {
+        Some(i) => rest.split_at(rest[..i].trim_end().len()),
+        None => (rest, ""),
+    };
+    Cow::Owned(format!(
+        "{}:{}{}",
+        paint(who, Role::Prompt, Stream::Stdout),
+        paint(dir, Role::Path, Stream::Stdout),
+        tail
+    ))
+}
+
+// 把真彩色换算为 256 色中 6x6x6 色块里最接近的颜色
+fn to_ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |v: u8| (v as u16 * 5 + 127) / 255;
+    (16 + 36 * level(r) + 6 * level(g) + level(b)) as u8
+}
+
+// 在 terminfo 数据库中查找终端的 max_colors
+fn terminfo_colors(term: &str) -> Option<u32> {
+    let first = term.chars().next()?;
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    dirs.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"].map(PathBuf::from));
+
+    // 子目录按首字母命名，有的系统（macOS）用首字母的十六进制编码
+    let data = dirs.iter().find_map(|dir| {
+        fs::read(dir.join(first.to_string()).join(term))
+            .or_else(|_| fs::read(dir.join(format!("{:x}", first as u32)).join(term)))
+            .ok()
+    })?;
+    max_colors(&data)
+}
+
+// 编译后的 terminfo：12 字节的头部（魔数，名字、布尔能力、数值能力的大小……），
+// 之后依次是名字和布尔能力，对齐到偶数位置后是数值能力，max_colors 是其中第 13 个（从 0 数）
+fn max_colors(data: &[u8]) -> Option<u32> {
+    const MAX_COLORS: usize = 13;
+    let short = |i: usize| data.get(i..i + 2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+    // 旧格式的数值是 16 位的，扩展格式（ncurses 6.1 起）是 32 位的
+    let width = match short(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let names = usize::try_from(short(2)?).ok()?;
+    let booleans = usize::try_from(short(4)?).ok()?;
+    let numbers = usize::try_from(short(6)?).ok()?;
+    if numbers <= MAX_COLORS {
+        return None;
+    }
+
+    let mut offset = 12 + names + booleans;
+    offset += offset % 2;
+    offset += MAX_COLORS * width;
+    let bytes = data.get(offset..offset + width)?;
+    let value = match width {
+        2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+        _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    };
+    u32::try_from(value).ok().filter(|&value| value > 0)
+}
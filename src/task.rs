@@ -0,0 +1,169 @@
+use crate::command::exit_child;
+use crate::date::format_time;
+use crate::error::ShellError;
+use crate::jobs::{fork_background, report_finished};
+use crate::parser::{command_text, parse_input};
+use crate::shell::Shell;
+use crate::stdio::Stdio;
+use crate::vars::Variables;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "用法: task run 名字 [--] 命令... | task [list] | task logs 名字 | task stop 名字";
+
+// 命名任务的进程号和日志保存在 ~/.rsh/tasks/ 下：名字.pid 和 名字.log，
+// 因此Shell退出之后任务继续运行，之后的会话中仍然可以查看日志和停止任务
+fn task_dir(vars: &Variables) -> Result<PathBuf, ShellError> {
+    match vars.get("HOME") {
+        Some(home) => Ok(Path::new(home).join(".rsh/tasks")),
+        None => Err(ShellError::CommandError("task: 无法确定HOME目录".to_string())),
+    }
+}
+
+// 内建命令 task：
+//   task run 名字 [--] 命令...   在后台运行命令，输出写入日志，登记到作业表
+//   task [list]                   列出任务和它们是否还在运行
+//   task logs 名字                输出任务的日志
+//   task stop 名字                终止任务的整个进程组
+pub fn run_task(shell: &mut Shell, args: &[String]) -> Result<(), ShellError> {
+    let dir = task_dir(&shell.vars)?;
+    match args.split_first() {
+        None => list(shell, &dir),
+        Some((sub, [])) if sub == "list" => list(shell, &dir),
+        Some((sub, [name, command @ ..])) if sub == "run" => {
+            let command = match command.split_first() {
+                Some((dashes, rest)) if dashes == "--" => rest,
+                _ => command,
+            };
+            if command.is_empty() {
+                return Err(ShellError::CommandError(USAGE.to_string()));
+            }
+            start(shell, &dir, check_name(name)?, command_text(command))
+        }
+        Some((sub, [name])) if sub == "logs" => {
+            let log = dir.join(format!("{}.log", check_name(name)?));
+            let mut file = fs::File::open(&log).map_err(|_| not_found(name))?;
+            io::copy(&mut file, &mut shell.stdio.out())?;
+            Ok(())
+        }
+        Some((sub, [name])) if sub == "stop" => stop(shell, &dir, check_name(name)?),
+        _ => Err(ShellError::CommandError(USAGE.to_string())),
+    }
+}
+
+// 名字用作文件名，只允许字母、数字、- _ 和 .，不能以 . 开头
+fn check_name(name: &str) -> Result<&str, ShellError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if valid {
+        Ok(name)
+    } else {
+        Err(ShellError::CommandError(format!("task: 无效的任务名 '{}'", name)))
+    }
+}
+
+fn not_found(name: &str) -> ShellError {
+    ShellError::CommandError(format!("task: 没有名为 '{}' 的任务", name))
+}
+
+// 读取 名字.pid：第一行是进程号，第二行是命令
+fn read_pid_file(path: &Path) -> Option<(libc::pid_t, String)> {
+    let text = fs::read_to_string(path).ok()?;
+    let (pid, command) = text.split_once('\n')?;
+    Some((pid.parse().ok()?, command.trim_end().to_string()))
+}
+
+// 任务是否还在运行：任务进程是自己的进程组的组长，据此排除进程号被其他进程重新使用的情况
+fn is_running(pid: libc::pid_t) -> bool {
+    // SAFETY: 只查询进程组
+    unsafe { libc::getpgid(pid) == pid }
+}
+
+// 任务进程：独立的进程组，忽略 SIGHUP，标准输出和标准错误追加到日志文件
+fn start(shell: &mut Shell, dir: &Path, name: &str, command: String) -> Result<(), ShellError> {
+    let pid_file = dir.join(format!("{}.pid", name));
+    if let Some((pid, _)) = read_pid_file(&pid_file)
+        && is_running(pid)
+    {
+        return Err(ShellError::CommandError(format!(
+            "task: 任务 '{}' 已经在运行（进程 {}）",
+            name, pid
+        )));
+    }
+    // 现在就检查语法，错误报告在终端上而不是日志中
+    let lists = parse_input(&command, &shell.aliases)?;
+
+    fs::create_dir_all(dir)?;
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{}.log", name)))?;
+    // SAFETY: time 接受空指针
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    writeln!(log, "=== {} {}", format_time(now, "%Y-%m-%d %H:%M:%S", false)?, command)?;
+
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+    let pid = fork_background()?;
+    if pid == 0 {
+        // SAFETY: 子进程把输出改为日志文件；忽略的 SIGHUP 在执行其他程序后仍然被忽略
+        unsafe {
+            libc::signal(libc::SIGHUP, libc::SIG_IGN);
+            libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+        }
+        drop(log);
+        // 命令的输出都写到日志中，而不是Shell原来的标准输出
+        shell.stdio = Stdio::default();
+        exit_child(shell, lists);
+    }
+
+    fs::write(&pid_file, format!("{}\n{}\n", pid, command))?;
+    let id = shell.jobs.add(pid, format!("task {}: {}", name, command), None);
+    writeln!(shell.stdio.err(), "[{}] {}  {}", id, pid, name)?;
+    Ok(())
+}
+
+fn list(shell: &mut Shell, dir: &Path) -> Result<(), ShellError> {
+    report_finished(&mut shell.stdio.err(), &mut shell.jobs)?;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut tasks: Vec<(String, libc::pid_t, String)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?.strip_suffix(".pid")?.to_string();
+            let (pid, command) = read_pid_file(&path)?;
+            Some((name, pid, command))
+        })
+        .collect();
+    tasks.sort();
+    for (name, pid, command) in tasks {
+        let state = if is_running(pid) { "运行中" } else { "已结束" };
+        writeln!(shell.stdio.out(), "{}  {}  {}  {}", name, state, pid, command)?;
+    }
+    Ok(())
+}
+
+// 停止任务：向进程组发送 SIGTERM（停止的进程需要 SIGCONT 才能处理），保留日志
+fn stop(shell: &mut Shell, dir: &Path, name: &str) -> Result<(), ShellError> {
+    let pid_file = dir.join(format!("{}.pid", name));
+    let (pid, _) = read_pid_file(&pid_file).ok_or_else(|| not_found(name))?;
+    if !is_running(pid) {
+        return Err(ShellError::CommandError(format!("task: 任务 '{}' 没有在运行", name)));
+    }
+    // SAFETY: 向任务的进程组发送信号
+    unsafe {
+        libc::kill(-pid, libc::SIGTERM);
+        libc::kill(-pid, libc::SIGCONT);
+    }
+    fs::remove_file(&pid_file)?;
+    // 本会话启动的任务由作业表回收并报告
+    report_finished(&mut shell.stdio.err(), &mut shell.jobs)?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use crate::vars::Variables;
+
+// 查询标准输入所连终端的窗口大小，返回 (列数, 行数)
+pub fn window_size() -> Option<(u16, u16)> {
+    // SAFETY: winsize 是纯数据结构，ioctl 只向其中写入
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDIN_FILENO, libc::TIOCGWINSZ, &mut size) < 0 || size.ws_col == 0 {
+            return None;
+        }
+        Some((size.ws_col, size.ws_row))
+    }
+}
+
+// 按当前终端大小更新 COLUMNS 和 LINES（类似bash的 checkwinsize），主循环在每次显示提示符之前调用，
+// 保证之后启动的程序拿到的是调整后的大小。Shell自己画的界面不读这两个变量：行编辑器自己处理 SIGWINCH，
+// 命令面板收到 SIGWINCH 后重画（见 signals::install_resize_handler），后台输出重画提示符时重新查询窗口大小
+pub fn update_window_size(vars: &mut Variables) {
+    if let Some((columns, lines)) = window_size() {
+        let columns = columns.to_string();
+        let lines = lines.to_string();
+        if vars.get("COLUMNS") != Some(columns.as_str()) {
+            vars.set("COLUMNS", &columns);
+        }
+        if vars.get("LINES") != Some(lines.as_str()) {
+            vars.set("LINES", &lines);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+// 测试共用的辅助函数
+use std::fs;
+use std::path::PathBuf;
+
+// 测试用的临时路径，不创建；name 在所有测试中不重复，例如以模块名开头
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rsh-test-{}-{}", std::process::id(), name))
+}
+
+// 测试用的临时目录，已经创建
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
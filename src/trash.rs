@@ -0,0 +1,205 @@
+use crate::date::format_time;
+use crate::error::ShellError;
+use crate::vars::Variables;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// 回收站中的一项，来自 info 目录下的 .trashinfo 文件
+struct TrashEntry {
+    // files 目录下的名字
+    name: String,
+    original: PathBuf,
+    deleted: String,
+    // .trashinfo 的修改时间，同一秒内删除的按它区分先后
+    written: SystemTime,
+}
+
+// 按 XDG 回收站规范的主目录回收站：$XDG_DATA_HOME/Trash，默认为 ~/.local/share/Trash
+fn trash_dir(vars: &Variables) -> Result<PathBuf, ShellError> {
+    if let Some(data) = vars.get("XDG_DATA_HOME").filter(|data| data.starts_with('/')) {
+        return Ok(Path::new(data).join("Trash"));
+    }
+    match vars.get("HOME") {
+        Some(home) => Ok(Path::new(home).join(".local/share/Trash")),
+        None => Err(ShellError::CommandError("del: 无法确定HOME目录".to_string())),
+    }
+}
+
+// 内建命令 del：del 文件... 把文件或目录移到回收站，而不是直接删除；
+// del --restore 列出回收站中的内容，del --restore 路径... 把按原路径删除的文件（同一路径删除过多次时取最近的）移回原处
+pub fn run_del(out: &mut impl Write, vars: &Variables, args: &[String]) -> Result<(), ShellError> {
+    let trash = trash_dir(vars)?;
+    match args.split_first() {
+        None => Err(ShellError::CommandError("用法: del 文件... 或 del --restore [路径...]".to_string())),
+        Some((flag, paths)) if flag == "--restore" => {
+            let entries = read_entries(&trash)?;
+            if paths.is_empty() {
+                for entry in &entries {
+                    writeln!(out, "{}  {}", entry.deleted, entry.original.display())?;
+                }
+                return Ok(());
+            }
+            for path in paths {
+                restore(&trash, &entries, path)?;
+            }
+            Ok(())
+        }
+        Some(_) => {
+            for path in args {
+                move_to_trash(&trash, path)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn move_to_trash(trash: &Path, path: &str) -> Result<(), ShellError> {
+    let error = |message: String| ShellError::CommandError(format!("del: '{}': {}", path, message));
+    let original = absolute(path).ok_or_else(|| error("不能删除这个路径".to_string()))?;
+    // 不跟随符号链接，删除的是链接本身
+    fs::symlink_metadata(&original).map_err(|e| error(e.to_string()))?;
+    if trash.starts_with(&original) || original.starts_with(trash) {
+        return Err(error("不能删除回收站或其中的文件".to_string()));
+    }
+
+    let files = trash.join("files");
+    let info = trash.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    // 先独占地创建 .trashinfo 占住名字，同名时加上 .2、.3 等后缀
+    let base = original.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut number = 1;
+    let (name, mut info_file, info_path) = loop {
+        let name = if number == 1 { base.clone() } else { format!("{}.{}", base, number) };
+        let info_path = info.join(format!("{}.trashinfo", name));
+        if !files.join(&name).exists() {
+            match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(file) => break (name, file, info_path),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(ShellError::Io(e)),
+            }
+        }
+        number += 1;
+    };
+
+    // SAFETY: time 接受空指针，只返回当前时间
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let deleted = format_time(now, "%Y-%m-%dT%H:%M:%S", false)?;
+    let written = write!(
+        info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(&original),
+        deleted
+    );
+    let moved = written.and_then(|()| fs::rename(&original, files.join(&name)));
+    if let Err(e) = moved {
+        let _ = fs::remove_file(&info_path);
+        if e.kind() == io::ErrorKind::CrossesDevices {
+            return Err(error("与回收站不在同一个文件系统上".to_string()));
+        }
+        return Err(error(e.to_string()));
+    }
+    Ok(())
+}
+
+fn restore(trash: &Path, entries: &[TrashEntry], path: &str) -> Result<(), ShellError> {
+    let error = |message: &str| ShellError::CommandError(format!("del: '{}': {}", path, message));
+    let original = absolute(path).ok_or_else(|| error("无效的路径"))?;
+    // entries 按删除时间排列，取最近的一次
+    let entry = entries
+        .iter()
+        .rfind(|entry| entry.original == original)
+        .ok_or_else(|| error("回收站中没有这个文件"))?;
+    if fs::symlink_metadata(&original).is_ok() {
+        return Err(error("原位置已经存在"));
+    }
+
+    fs::rename(trash.join("files").join(&entry.name), &original).map_err(|e| error(&e.to_string()))?;
+    fs::remove_file(trash.join("info").join(format!("{}.trashinfo", entry.name)))?;
+    Ok(())
+}
+
+// 读取回收站中的全部条目，按删除时间排列；格式不对的 .trashinfo 被忽略
+fn read_entries(trash: &Path) -> Result<Vec<TrashEntry>, ShellError> {
+    let dir = match fs::read_dir(trash.join("info")) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ShellError::Io(e)),
+    };
+
+    let mut entries = Vec::new();
+    for item in dir {
+        let item = item?;
+        let file_name = item.file_name().to_string_lossy().into_owned();
+        let Some(name) = file_name.strip_suffix(".trashinfo") else {
+            continue;
+        };
+        let Ok(text) = fs::read_to_string(item.path()) else {
+            continue;
+        };
+        let written = item.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+        let field = |key: &str| text.lines().find_map(|line| line.strip_prefix(key)).map(str::to_string);
+        if let (Some(path), Some(deleted)) = (field("Path="), field("DeletionDate=")) {
+            entries.push(TrashEntry {
+                name: name.to_string(),
+                original: decode_path(&path),
+                deleted,
+                written,
+            });
+        }
+    }
+    entries.sort_by(|a, b| (&a.deleted, a.written).cmp(&(&b.deleted, b.written)));
+    Ok(entries)
+}
+
+// 绝对路径：目录部分解析符号链接，最后一个分量保持原样；没有文件名（例如 / 和 ..）时为 None
+fn absolute(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path.trim_end_matches('/'));
+    let name = path.file_name()?;
+    if name == ".." {
+        return None;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent).ok()?,
+        _ => env::current_dir().ok()?,
+    };
+    Some(parent.join(name))
+}
+
+// .trashinfo 中的路径按 URL 的规则转义，字母数字、/ 和 -_.~ 之外的字节写成 %XX
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_path(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(std::ffi::OsStr::from_bytes(&decoded))
+}
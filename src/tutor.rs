@@ -0,0 +1,224 @@
+use crate::error::ShellError;
+use crate::history::should_record;
+use crate::read::read_stdin_line;
+use crate::shell::{Output, Shell};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// 检查一次练习的结果：参数是执行后的Shell、输入的命令行和捕获的输出
+type Check = Box<dyn Fn(&Shell, &str, &Output) -> bool>;
+
+// 一道练习
+struct Exercise {
+    task: String,
+    hint: String,
+    check: Check,
+}
+
+impl Exercise {
+    fn new(task: impl Into<String>, hint: impl Into<String>, check: impl Fn(&Shell, &str, &Output) -> bool + 'static) -> Self {
+        Exercise {
+            task: task.into(),
+            hint: hint.into(),
+            check: Box::new(check),
+        }
+    }
+}
+
+// 一节课：先显示讲解，然后依次做练习
+struct Lesson {
+    name: &'static str,
+    title: &'static str,
+    intro: &'static str,
+    exercises: Vec<Exercise>,
+}
+
+// 用户对一道练习的处理结果
+enum Outcome {
+    Passed,
+    Skipped,
+    Quit,
+}
+
+// 内建命令 tutor：tutor [课程...] 或 tutor list
+// 交互式教程，练习中输入的命令在当前Shell中执行并检查结果；
+// 练习中输入 :hint 查看提示，:skip 跳过，:quit 退出
+pub fn run_tutor(shell: &mut Shell, args: &[String]) -> Result<(), ShellError> {
+    let practice_file = std::env::temp_dir().join(format!("rsh-tutor-{}.txt", std::process::id()));
+    let lessons = lessons(&practice_file);
+
+    if args.len() == 1 && args[0] == "list" {
+        let mut out = shell.stdio.out();
+        for lesson in &lessons {
+            writeln!(out, "{:<10} {}", lesson.name, lesson.title)?;
+        }
+        return Ok(());
+    }
+    for name in args {
+        if !lessons.iter().any(|lesson| lesson.name == name) {
+            return Err(ShellError::CommandError(format!(
+                "tutor: 没有课程 '{}'，可以用 tutor list 查看",
+                name
+            )));
+        }
+    }
+
+    let selected = lessons
+        .iter()
+        .filter(|lesson| args.is_empty() || args.iter().any(|name| name == lesson.name));
+    let result = run_lessons(shell, selected);
+    let _ = fs::remove_file(&practice_file);
+    result
+}
+
+fn run_lessons<'a>(shell: &mut Shell, lessons: impl Iterator<Item = &'a Lesson>) -> Result<(), ShellError> {
+    let mut out = shell.stdio.out();
+    writeln!(out, "欢迎使用 rsh 教程。练习中的命令会在当前Shell中真实执行。")?;
+    writeln!(out, "输入 :hint 查看提示，:skip 跳过这道练习，:quit 退出教程。")?;
+    let (mut passed, mut total) = (0, 0);
+
+    for lesson in lessons {
+        writeln!(out)?;
+        writeln!(out, "== {} ==", lesson.title)?;
+        writeln!(out, "{}", lesson.intro)?;
+        for (i, exercise) in lesson.exercises.iter().enumerate() {
+            total += 1;
+            writeln!(out)?;
+            writeln!(out, "练习 {}: {}", i + 1, exercise.task)?;
+            match practice(shell, exercise)? {
+                Outcome::Passed => passed += 1,
+                Outcome::Skipped => {}
+                Outcome::Quit => {
+                    writeln!(out, "已退出教程，完成了 {} 道练习。", passed)?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "教程结束，完成了 {} 道练习中的 {} 道。", total, passed)?;
+    Ok(())
+}
+
+// 反复读取命令直到练习通过、跳过或退出
+fn practice(shell: &mut Shell, exercise: &Exercise) -> Result<Outcome, ShellError> {
+    let mut out = shell.stdio.out();
+    loop {
+        write!(out, "tutor> ")?;
+        let Some(line) = read_stdin_line()? else {
+            writeln!(out)?;
+            return Ok(Outcome::Quit);
+        };
+        let line = String::from_utf8_lossy(&line).trim().to_string();
+
+        match line.as_str() {
+            "" => writeln!(out, "{}", exercise.task)?,
+            ":hint" => writeln!(out, "提示: {}", exercise.hint)?,
+            ":skip" => return Ok(Outcome::Skipped),
+            ":quit" => return Ok(Outcome::Quit),
+            _ => {
+                let output = shell.run_str(&line)?;
+                out.write_all(output.stdout.as_bytes())?;
+                shell.stdio.err().write_all(output.stderr.as_bytes())?;
+                if (exercise.check)(shell, &line, &output) {
+                    writeln!(out, "✓ 正确！")?;
+                    return Ok(Outcome::Passed);
+                }
+                writeln!(out, "✗ 还不对，再试一次（:hint 查看提示，:skip 跳过）")?;
+            }
+        }
+    }
+}
+
+fn lessons(practice_file: &Path) -> Vec<Lesson> {
+    let file = practice_file.display().to_string();
+    let content = |path: &Path| fs::read_to_string(path).unwrap_or_default();
+
+    vec![
+        Lesson {
+            name: "pipes",
+            title: "管道",
+            intro: "管道 | 把前一个命令的标准输出连接到后一个命令的标准输入，\n\
+                    例如 ls | sort -r 把文件名倒序排列。可以连接任意多个命令。",
+            exercises: vec![
+                Exercise::new(
+                    "用 head 取出 /etc/passwd 的前 3 行，再用管道交给 wc -l 数出行数",
+                    "head -n 3 /etc/passwd | wc -l",
+                    |_, line, output| line.contains('|') && output.stdout.trim() == "3",
+                ),
+                Exercise::new(
+                    "把 printf 'b\\na\\nc\\n' 的输出排序，再只保留第一行，输出 a",
+                    "printf 'b\\na\\nc\\n' | sort | head -n 1",
+                    |_, line, output| line.matches('|').count() >= 2 && output.stdout.trim() == "a",
+                ),
+            ],
+        },
+        Lesson {
+            name: "redirect",
+            title: "重定向",
+            intro: "> 文件 把标准输出写入文件（覆盖），>> 文件 追加到末尾，< 文件 从文件读取标准输入，\n\
+                    2> 文件 重定向标准错误，2>&1 让标准错误和标准输出去同一个地方。",
+            exercises: {
+                let (first, second, third) = (practice_file.to_path_buf(), practice_file.to_path_buf(), file.clone());
+                vec![
+                    Exercise::new(
+                        format!("用 > 把 hello 写入 {}", file),
+                        format!("echo hello > {}", file),
+                        move |_, _, _| content(&first) == "hello\n",
+                    ),
+                    Exercise::new(
+                        format!("用 >> 在 {} 末尾追加一行 world", file),
+                        format!("echo world >> {}", file),
+                        move |_, _, _| content(&second) == "hello\nworld\n",
+                    ),
+                    Exercise::new(
+                        format!("用 < 让 wc -l 从 {} 读取，数出行数", file),
+                        format!("wc -l < {}", file),
+                        move |_, line, output| line.contains('<') && line.contains(&third) && output.stdout.trim() == "2",
+                    ),
+                ]
+            },
+        },
+        Lesson {
+            name: "jobs",
+            title: "作业控制",
+            intro: "命令末尾加 & 在后台运行，Shell立即回到提示符并显示 [作业号] 进程号。\n\
+                    jobs 列出仍在运行的后台作业，jobs -l 还会显示它们的CPU和内存占用。",
+            exercises: vec![
+                Exercise::new(
+                    "在后台运行 sleep 30",
+                    "sleep 30 &",
+                    |shell, line, _| {
+                        line.ends_with('&') && shell.jobs.iter().any(|job| job.command.contains("sleep"))
+                    },
+                ),
+                Exercise::new(
+                    "用 jobs 查看刚才的后台作业",
+                    "jobs",
+                    |_, line, output| line.starts_with("jobs") && output.stdout.contains("sleep"),
+                ),
+            ],
+        },
+        Lesson {
+            name: "history",
+            title: "历史记录",
+            intro: "上下方向键浏览历史，Ctrl-R 向前搜索历史中的命令。\n\
+                    HISTIGNORE 是以冒号分隔的通配符模式，匹配的命令不记入历史；\n\
+                    HISTCONTROL=ignorespace 时以空格开头的命令不记入历史；set -o private 完全停止记录。",
+            exercises: vec![
+                Exercise::new(
+                    "设置 HISTIGNORE，使 ls 开头的命令不记入历史",
+                    "HISTIGNORE='ls*'",
+                    |shell, _, _| !should_record(shell, "ls -l") && should_record(shell, "echo hi"),
+                ),
+                Exercise::new(
+                    "开启隐私模式，暂时不记录任何历史（之后可以用 set +o private 关闭）",
+                    "set -o private",
+                    |shell, _, _| shell.options.private,
+                ),
+            ],
+        },
+    ]
+}
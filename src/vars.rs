@@ -0,0 +1,270 @@
+use crate::error::ShellError;
+use crate::parser::quote_word;
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::CString;
+use std::io::{self, Write};
+
+// 单个Shell变量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub value: String,
+    // 是否导出到子进程的环境中
+    pub exported: bool,
+}
+
+// Shell变量表，子进程的环境变量由其中已导出的变量构成
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    vars: BTreeMap<String, Variable>,
+    // 数组变量（mapfile 等），不会导出
+    arrays: BTreeMap<String, Vec<String>>,
+    // 启动外部命令时使用的 "名字=值" 字符串，已导出的变量改变时丢弃
+    environ: OnceCell<Vec<CString>>,
+}
+
+// env-save 保存的变量快照
+pub type EnvSnapshots = BTreeMap<String, Variables>;
+
+impl Variables {
+    // 以当前进程的环境变量初始化，全部视为已导出
+    pub fn from_env() -> Self {
+        let vars = env::vars()
+            .map(|(name, value)| {
+                (
+                    name,
+                    Variable {
+                        value,
+                        exported: true,
+                    },
+                )
+            })
+            .collect();
+        Variables {
+            vars,
+            ..Variables::default()
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|v| v.value.as_str())
+    }
+
+    // 设置变量的值，保留原有的导出属性；同名的数组变量被取代
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.arrays.remove(name);
+        match self.vars.get_mut(name) {
+            Some(var) => {
+                var.value = value.to_string();
+                if var.exported {
+                    self.environ.take();
+                }
+            }
+            None => {
+                self.vars.insert(
+                    name.to_string(),
+                    Variable {
+                        value: value.to_string(),
+                        exported: false,
+                    },
+                );
+            }
+        }
+    }
+
+    // 导出变量，value 为 None 时只标记已有变量
+    pub fn export(&mut self, name: &str, value: Option<&str>) {
+        let var = self.vars.entry(name.to_string()).or_insert_with(|| Variable {
+            value: String::new(),
+            exported: true,
+        });
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = value.to_string();
+        }
+        self.environ.take();
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.remove(name);
+        self.arrays.remove(name);
+    }
+
+    pub fn get_array(&self, name: &str) -> Option<&[String]> {
+        self.arrays.get(name).map(|a| a.as_slice())
+    }
+
+    // 设置数组变量，同名的普通变量被取代
+    pub fn set_array(&mut self, name: &str, elements: Vec<String>) {
+        self.remove(name);
+        self.arrays.insert(name.to_string(), elements);
+    }
+
+    fn remove(&mut self, name: &str) {
+        if self.vars.remove(name).is_some_and(|var| var.exported) {
+            self.environ.take();
+        }
+    }
+
+    // 传给子进程的环境变量
+    pub fn exported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, var)| (name.as_str(), var.value.as_str()))
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<&Variable> {
+        self.vars.get(name)
+    }
+
+    // 恢复 get_variable 保存的变量，None 表示原来没有这个变量
+    pub fn restore_variable(&mut self, name: &str, saved: Option<Variable>) {
+        match saved {
+            Some(var) => {
+                if var.exported || self.vars.get(name).is_some_and(|v| v.exported) {
+                    self.environ.take();
+                }
+                self.arrays.remove(name);
+                self.vars.insert(name.to_string(), var);
+            }
+            None => self.remove(name),
+        }
+    }
+
+    // 传给子进程的 "名字=值" 字符串，在已导出的变量改变之前重复使用；含有空字符的变量无法传递，被跳过
+    pub fn environ(&self) -> &[CString] {
+        self.environ.get_or_init(|| {
+            self.exported()
+                .filter_map(|(name, value)| CString::new(format!("{}={}", name, value)).ok())
+                .collect()
+        })
+    }
+
+    // 在 environ 之外加上只对一个命令有效的赋值（FOO=bar 命令），同名的导出变量被取代，
+    // 同一个名字赋值多次时以最后一次为准
+    pub fn environ_with(&self, assignments: &[(String, String)]) -> Vec<CString> {
+        let assigned = |name: &[u8]| assignments.iter().any(|(assigned, _)| assigned.as_bytes() == name);
+        let mut environ: Vec<CString> = self
+            .environ()
+            .iter()
+            .filter(|entry| !assigned(entry.to_bytes().split(|&b| b == b'=').next().unwrap_or_default()))
+            .cloned()
+            .collect();
+        for (i, (name, value)) in assignments.iter().enumerate() {
+            if assignments[i + 1..].iter().all(|(later, _)| later != name)
+                && let Ok(entry) = CString::new(format!("{}={}", name, value))
+            {
+                environ.push(entry);
+            }
+        }
+        environ
+    }
+}
+
+// 变量名：字母或下划线开头，由字母、数字和下划线组成
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// 内建命令 export：export [NAME[=value] ...]，不带参数时列出已导出的变量
+pub fn run_export(out: &mut impl Write, vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    if args.is_empty() {
+        for (name, value) in vars.exported() {
+            writeln!(out, "export {}={}", name, quote_word(value))?;
+        }
+        return Ok(());
+    }
+
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if !is_valid_name(name) {
+            return Err(ShellError::CommandError(format!("export: 无效的变量名 '{}'", name)));
+        }
+        vars.export(name, value);
+    }
+
+    Ok(())
+}
+
+// 内建命令 unset：unset NAME ...
+pub fn run_unset(vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    for name in args {
+        vars.unset(name);
+    }
+    Ok(())
+}
+
+// 内建命令 env-save：env-save <名称>，保存当前全部变量；不带参数时列出已保存的快照
+pub fn run_env_save(out: &mut impl Write, snapshots: &mut EnvSnapshots, vars: &Variables, args: &[String]) -> Result<(), ShellError> {
+    match args {
+        [] => {
+            for name in snapshots.keys() {
+                writeln!(out, "{}", name)?;
+            }
+            Ok(())
+        }
+        [name] => {
+            snapshots.insert(name.clone(), vars.clone());
+            Ok(())
+        }
+        _ => Err(ShellError::CommandError("用法: env-save <名称>".to_string())),
+    }
+}
+
+// 内建命令 env-restore：env-restore <名称>，用快照替换当前全部变量
+pub fn run_env_restore(snapshots: &EnvSnapshots, vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    let name = match args {
+        [name] => name,
+        _ => return Err(ShellError::CommandError("用法: env-restore <名称>".to_string())),
+    };
+
+    match snapshots.get(name) {
+        Some(snapshot) => {
+            *vars = snapshot.clone();
+            Ok(())
+        }
+        None => Err(ShellError::CommandError(format!("env-restore: 没有名为 '{}' 的快照", name))),
+    }
+}
+
+// 内建命令 pushenv：pushenv [NAME=value ...]
+// 把当前变量压栈，再导出给定的临时修改，之后可用 popenv 恢复
+pub fn run_pushenv(stack: &mut Vec<Variables>, vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    stack.push(vars.clone());
+    if args.is_empty() {
+        return Ok(());
+    }
+    if let Err(e) = run_export(&mut io::sink(), vars, args) {
+        // 参数有误时撤销压栈，保持状态不变
+        if let Some(saved) = stack.pop() {
+            *vars = saved;
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+// 内建命令 popenv：恢复最近一次 pushenv 时的变量
+pub fn run_popenv(stack: &mut Vec<Variables>, vars: &mut Variables, args: &[String]) -> Result<(), ShellError> {
+    if !args.is_empty() {
+        return Err(ShellError::CommandError("popenv: 不接受参数".to_string()));
+    }
+
+    match stack.pop() {
+        Some(saved) => {
+            *vars = saved;
+            Ok(())
+        }
+        None => Err(ShellError::CommandError("popenv: 环境栈为空".to_string())),
+    }
+}
@@ -0,0 +1,258 @@
+// WASM插件：在wasmtime沙箱中运行的内建命令和补全，插件只能访问自身的线性内存，
+// 除了宿主提供的输出函数之外无法进行任何系统调用；每次调用插件最多执行 FUEL_PER_CALL
+// 单位的燃料（大致相当于指令数），死循环的插件会被中止而不会卡住Shell
+//
+// 插件模块需要导出：
+//   memory                                  线性内存
+//   rsh_abi_version() -> i32                必须等于 WASM_PLUGIN_ABI_VERSION
+//   rsh_builtins() -> i32                   指向以NUL结尾、以换行分隔的内建命令名列表
+//   rsh_alloc(len: i32) -> i32              分配len字节内存，用于传入参数
+//   rsh_call(ptr: i32, len: i32) -> i32     执行命令，参数为"命令名\0参数1\0参数2\0..."，返回状态码
+// 可选导出：
+//   rsh_complete(ptr: i32, len: i32) -> i32 补全参数，传入"命令名\0正在输入的词\0"，
+//                                           返回指向以NUL结尾、以换行分隔的补全词列表
+// 宿主提供导入：
+//   rsh.write(fd: i32, ptr: i32, len: i32) -> i32   向标准输出(1)或标准错误(2)写入，失败返回-1
+use crate::completion::CompletionRegistry;
+use crate::error::ShellError;
+use crate::stdio::Stdio;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Module, Store, TypedFunc};
+
+pub const WASM_PLUGIN_ABI_VERSION: i32 = 1;
+
+// 每次调用插件的函数时给予的燃料
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+// 单个已实例化的WASM插件
+struct WasmPlugin {
+    // 存放插件这次调用时Shell的标准输出和标准错误，供 rsh.write 使用
+    store: Store<Stdio>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    call: TypedFunc<(i32, i32), i32>,
+    complete: Option<TypedFunc<(i32, i32), i32>>,
+}
+
+// 已加载的WASM插件及其提供的内建命令；插件同时被注册的补全引用
+#[derive(Default)]
+pub struct WasmPlugins {
+    // 启用燃料计量的引擎，第一次加载插件时创建
+    engine: Option<Engine>,
+    plugins: Vec<Rc<RefCell<WasmPlugin>>>,
+    // 命令名到插件下标的映射
+    builtins: HashMap<String, usize>,
+}
+
+impl fmt::Debug for WasmPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.builtins.keys()).finish()
+    }
+}
+
+impl WasmPlugins {
+    // 加载目录下所有 .wasm 插件，单个插件失败只打印错误
+    pub fn load_dir(&mut self, dir: &Path, registry: &mut CompletionRegistry) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+                continue;
+            }
+            if let Err(e) = self.load(&path, registry) {
+                eprintln!("WASM插件加载失败 '{}': {}", path.display(), e);
+            }
+        }
+    }
+
+    // 编译并实例化单个插件，注册其内建命令，插件导出了 rsh_complete 时同时注册补全
+    pub fn load(&mut self, path: &Path, registry: &mut CompletionRegistry) -> Result<(), ShellError> {
+        let engine = self.engine()?;
+        let module = Module::from_file(&engine, path).map_err(wasm_error)?;
+
+        let mut linker = Linker::new(&engine);
+        linker.func_wrap("rsh", "write", host_write).map_err(wasm_error)?;
+
+        let mut store = Store::new(&engine, Stdio::default());
+        refuel(&mut store)?;
+        let instance = linker.instantiate(&mut store, &module).map_err(wasm_error)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| ShellError::CommandError("插件未导出 memory".to_string()))?;
+
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "rsh_abi_version")
+            .and_then(|f| f.call(&mut store, ()))
+            .map_err(wasm_error)?;
+        if version != WASM_PLUGIN_ABI_VERSION {
+            return Err(ShellError::CommandError(format!(
+                "ABI版本不匹配: 需要 {}，插件为 {}",
+                WASM_PLUGIN_ABI_VERSION, version
+            )));
+        }
+
+        refuel(&mut store)?;
+        let names_ptr = instance
+            .get_typed_func::<(), i32>(&mut store, "rsh_builtins")
+            .and_then(|f| f.call(&mut store, ()))
+            .map_err(wasm_error)?;
+        let names = read_c_string(memory.data(&store), names_ptr)?;
+
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "rsh_alloc")
+            .map_err(wasm_error)?;
+        let call = instance
+            .get_typed_func::<(i32, i32), i32>(&mut store, "rsh_call")
+            .map_err(wasm_error)?;
+        let complete = instance
+            .get_typed_func::<(i32, i32), i32>(&mut store, "rsh_complete")
+            .ok();
+
+        let has_completer = complete.is_some();
+        let plugin = Rc::new(RefCell::new(WasmPlugin {
+            store,
+            memory,
+            alloc,
+            call,
+            complete,
+        }));
+        if has_completer {
+            let plugin = Rc::clone(&plugin);
+            registry.add_provider(Box::new(move |command, prefix| {
+                plugin.borrow_mut().complete(command, prefix).unwrap_or_default()
+            }));
+        }
+
+        let index = self.plugins.len();
+        self.plugins.push(plugin);
+        for name in names.split('\n').filter(|n| !n.is_empty()) {
+            self.builtins.insert(name.to_string(), index);
+        }
+
+        Ok(())
+    }
+
+    fn engine(&mut self) -> Result<Engine, ShellError> {
+        if let Some(engine) = &self.engine {
+            return Ok(engine.clone());
+        }
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(wasm_error)?;
+        self.engine = Some(engine.clone());
+        Ok(engine)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.builtins.contains_key(name)
+    }
+
+    // 执行WASM插件内建命令，不是插件命令时返回 None
+    pub fn call(&mut self, stdio: Stdio, name: &str, args: &[String]) -> Option<Result<i32, ShellError>> {
+        let index = *self.builtins.get(name)?;
+        Some(self.plugins[index].borrow_mut().call(stdio, name, args))
+    }
+}
+
+impl WasmPlugin {
+    fn call(&mut self, stdio: Stdio, name: &str, args: &[String]) -> Result<i32, ShellError> {
+        *self.store.data_mut() = stdio;
+        let (ptr, len) = self.pass(std::iter::once(name).chain(args.iter().map(|a| a.as_str())))?;
+        refuel(&mut self.store)?;
+        self.call.call(&mut self.store, (ptr, len)).map_err(wasm_error)
+    }
+
+    // 调用插件的 rsh_complete，返回它给出的补全词
+    fn complete(&mut self, command: &str, prefix: &str) -> Result<Vec<String>, ShellError> {
+        if self.complete.is_none() {
+            return Ok(Vec::new());
+        }
+        let (ptr, len) = self.pass([command, prefix])?;
+        refuel(&mut self.store)?;
+        let Some(complete) = &self.complete else {
+            return Ok(Vec::new());
+        };
+        let words_ptr = complete.call(&mut self.store, (ptr, len)).map_err(wasm_error)?;
+        let words = read_c_string(self.memory.data(&self.store), words_ptr)?;
+        Ok(words.split('\n').filter(|w| !w.is_empty()).map(str::to_string).collect())
+    }
+
+    // 把各部分以NUL结尾依次写入插件用 rsh_alloc 分配的内存，返回指针和长度
+    fn pass<'a>(&mut self, parts: impl IntoIterator<Item = &'a str>) -> Result<(i32, i32), ShellError> {
+        let mut payload = Vec::new();
+        for part in parts {
+            payload.extend_from_slice(part.as_bytes());
+            payload.push(0);
+        }
+
+        let len = i32::try_from(payload.len()).map_err(|_| ShellError::CommandError("插件参数太长".to_string()))?;
+        refuel(&mut self.store)?;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(wasm_error)?;
+        let offset = usize::try_from(ptr).map_err(|_| out_of_bounds())?;
+        self.memory
+            .write(&mut self.store, offset, &payload)
+            .map_err(wasm_error)?;
+        Ok((ptr, len))
+    }
+}
+
+// 为下一次调用插件补满燃料
+fn refuel(store: &mut Store<Stdio>) -> Result<(), ShellError> {
+    store.set_fuel(FUEL_PER_CALL).map_err(wasm_error)
+}
+
+// 宿主函数 rsh.write；ptr 和 len 由插件给出，负数或越界时返回-1
+fn host_write(mut caller: Caller<'_, Stdio>, fd: i32, ptr: i32, len: i32) -> i32 {
+    let stdio = *caller.data();
+    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
+        Some(memory) => memory,
+        None => return -1,
+    };
+
+    let (Ok(start), Ok(size)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return -1;
+    };
+    let bytes = match start.checked_add(size).and_then(|end| memory.data(&caller).get(start..end)) {
+        Some(bytes) => bytes,
+        None => return -1,
+    };
+
+    let result = match fd {
+        1 => stdio.out().write_all(bytes),
+        2 => stdio.err().write_all(bytes),
+        _ => return -1,
+    };
+    if result.is_ok() { len } else { -1 }
+}
+
+// 从插件内存中读取以NUL结尾的字符串
+fn read_c_string(data: &[u8], ptr: i32) -> Result<String, ShellError> {
+    let bytes = usize::try_from(ptr)
+        .ok()
+        .and_then(|start| data.get(start..))
+        .ok_or_else(out_of_bounds)?;
+    let end = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| ShellError::CommandError("插件字符串缺少结尾的NUL".to_string()))?;
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+fn out_of_bounds() -> ShellError {
+    ShellError::CommandError("插件返回了越界指针".to_string())
+}
+
+fn wasm_error(e: impl fmt::Display) -> ShellError {
+    ShellError::CommandError(e.to_string())
+}
@@ -0,0 +1,136 @@
+use crate::command::execute_single_command;
+use crate::diagnostic;
+use crate::error::ShellError;
+use crate::glob;
+use crate::parser::{Command, SimpleCommand};
+use crate::shell::Shell;
+use crate::signals::InterruptGuard;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+// 检查 Ctrl-C 的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// 最后一次变化后安静这么久才执行命令，保存文件、git checkout 等产生的一串事件只触发一次
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// 一个监视模式：root 是模式开头不含通配符的部分，rest 是其余部分（为空表示监视 root 本身）
+struct WatchPattern {
+    root: PathBuf,
+    rest: Option<String>,
+}
+
+impl WatchPattern {
+    fn new(cwd: &Path, pattern: &str) -> WatchPattern {
+        let components: Vec<&str> = pattern.split('/').collect();
+        let literal = components.iter().take_while(|c| !glob::has_wildcards(c)).count();
+        let root = match components[..literal].join("/") {
+            root if root.is_empty() && pattern.starts_with('/') => PathBuf::from("/"),
+            root if root.is_empty() => cwd.to_path_buf(),
+            root => cwd.join(root),
+        };
+        let rest = (literal < components.len()).then(|| components[literal..].join("/"));
+        WatchPattern { root, rest }
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        match &self.rest {
+            None => true,
+            Some(rest) => glob::matches_path(rest, &relative.to_string_lossy()),
+        }
+    }
+}
+
+// 内建命令 onchange：onchange 模式... -- 命令 [参数...]
+// 先执行一次命令，之后监视的文件每次变化都重新执行，按 Ctrl-C 停止监视
+// 模式支持通配符，** 匹配任意层目录，例如 onchange 'src/**/*.rs' -- cargo test
+pub fn run_onchange(shell: &mut Shell, args: &[String]) -> Result<(), ShellError> {
+    let separator = args.iter().position(|arg| arg == "--").ok_or_else(usage)?;
+    let (patterns, command) = (&args[..separator], &args[separator + 1..]);
+    let (program, command_args) = command.split_first().ok_or_else(usage)?;
+    if patterns.is_empty() {
+        return Err(usage());
+    }
+    let command = Command::Simple(SimpleCommand {
+        program: program.clone(),
+        args: command_args.to_vec(),
+        ..SimpleCommand::default()
+    });
+
+    let cwd = env::current_dir()?;
+    let patterns: Vec<WatchPattern> = patterns.iter().map(|p| WatchPattern::new(&cwd, p)).collect();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| ShellError::CommandError(format!("onchange: 无法创建文件监视: {}", e)))?;
+    for pattern in &patterns {
+        if !pattern.root.exists() {
+            return Err(ShellError::CommandError(format!(
+                "onchange: '{}' 不存在",
+                pattern.root.display()
+            )));
+        }
+        watcher
+            .watch(&pattern.root, RecursiveMode::Recursive)
+            .map_err(|e| ShellError::CommandError(format!("onchange: 无法监视 '{}': {}", pattern.root.display(), e)))?;
+    }
+
+    let interrupt = InterruptGuard::install()?;
+    writeln!(shell.stdio.err(), "onchange: 正在监视，按 Ctrl-C 退出")?;
+    run_once(shell, &command);
+
+    while !interrupt.interrupted() {
+        let event = match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => event,
+            Ok(Err(e)) => {
+                writeln!(shell.stdio.err(), "onchange: 文件监视出错: {}", e)?;
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(ShellError::CommandError("onchange: 文件监视意外停止".to_string()));
+            }
+        };
+        let Some(changed) = changed_path(&event, &patterns) else {
+            continue;
+        };
+
+        // 等到变化停止，期间的事件全部合并
+        while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+        if interrupt.interrupted() {
+            break;
+        }
+        writeln!(shell.stdio.err(), "onchange: {} 已变化", changed.display())?;
+        run_once(shell, &command);
+    }
+    Ok(())
+}
+
+// 事件中第一个匹配某个模式的路径，只读访问不算变化
+fn changed_path<'a>(event: &'a Event, patterns: &[WatchPattern]) -> Option<&'a Path> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return None;
+    }
+    event
+        .paths
+        .iter()
+        .find(|path| patterns.iter().any(|pattern| pattern.matches(path)))
+        .map(|path| path.as_path())
+}
+
+// 执行一次命令，失败只报告，继续监视
+fn run_once(shell: &mut Shell, command: &Command) {
+    if let Err(e) = execute_single_command(shell, command) {
+        diagnostic::report_command(&mut shell.stdio.err(), &e, &command.text());
+    }
+}
+
+fn usage() -> ShellError {
+    ShellError::CommandError("用法: onchange 模式... -- 命令 [参数...]".to_string())
+}